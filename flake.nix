@@ -50,18 +50,22 @@
               path: type:
               (craneLib.filterCargoSources path type)
               || (lib.hasSuffix ".sql" path)
-              || (lib.hasSuffix ".expected" path);
+              || (lib.hasSuffix ".expected" path)
+              || (lib.hasSuffix ".sqlfluff" path);
           };
 
           commonArgs = {
             inherit src;
             strictDeps = true;
+            # The --staged and --changed-since tests drive a real repository
+            nativeCheckInputs = [ pkgs.git ];
           };
 
           cargoArtifacts = craneLib.buildDepsOnly commonArgs;
 
           wasmArgs = commonArgs // {
             CARGO_BUILD_TARGET = "wasm32-unknown-unknown";
+            cargoExtraArgs = "--locked --no-default-features --features wasm";
             doCheck = false;
           };
 
@@ -1,4 +1,7 @@
-import init, { format_sql_wasm } from "../../pkg/rs_sql_indent.js";
+import init, {
+  format_sql_wasm,
+  type FormatOptions,
+} from "../../pkg/rs_sql_indent.js";
 import wasmUrl from "../../pkg/rs_sql_indent_bg.wasm?url";
 
 let wasmReady = false;
@@ -16,5 +19,8 @@ export function formatSql(
   if (!wasmReady) {
     throw new Error("WASM module not initialized");
   }
-  return format_sql_wasm(input, uppercase, style);
+  return format_sql_wasm(input, {
+    uppercase,
+    style: style as FormatOptions["style"],
+  }).output;
 }
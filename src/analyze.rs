@@ -0,0 +1,315 @@
+//! Lightweight statement introspection from the token stream: what kind of
+//! statement each one is, which tables it names and how it nests. Like the
+//! formatter it does not parse SQL, so the answers are best effort.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::config::Dialect;
+use crate::lexer;
+use crate::token::{KeywordKind, Token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StatementKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Merge,
+    /// `CREATE`, `ALTER`, `DROP`, `TRUNCATE` and the like
+    Ddl,
+    Other,
+}
+
+impl StatementKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatementKind::Select => "SELECT",
+            StatementKind::Insert => "INSERT",
+            StatementKind::Update => "UPDATE",
+            StatementKind::Delete => "DELETE",
+            StatementKind::Merge => "MERGE",
+            StatementKind::Ddl => "DDL",
+            StatementKind::Other => "OTHER",
+        }
+    }
+}
+
+impl fmt::Display for StatementKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What [`analyze`] found out about one statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementInfo {
+    pub kind: StatementKind,
+    /// Where the statement is in the input, terminating semicolon included
+    pub range: Range<usize>,
+    /// Tables the statement reads or writes, qualified as written
+    /// (`schema.table`), in order of first mention. CTE names are left out.
+    pub tables: Vec<String>,
+    /// Whether a parenthesized query appears, CTE bodies aside
+    pub has_subquery: bool,
+    /// Whether the statement defines common table expressions
+    pub has_cte: bool,
+    /// Deepest parenthesis nesting, subqueries included
+    pub max_depth: usize,
+}
+
+/// Describe each statement of `input`, split as by
+/// [`split_statements`](crate::split_statements). Statements holding
+/// nothing but comments are skipped.
+pub fn analyze(input: &str, dialect: Dialect) -> Vec<StatementInfo> {
+    lexer::statement_ranges(input, dialect)
+        .into_iter()
+        .filter_map(|range| {
+            let tokens: Vec<Token<'_>> =
+                lexer::tokenize_with_dialect(&input[range.clone()], dialect)
+                    .into_iter()
+                    .filter(|t| {
+                        !matches!(
+                            t,
+                            Token::Whitespace(_) | Token::LineComment(_) | Token::BlockComment(_)
+                        )
+                    })
+                    .collect();
+            if tokens
+                .iter()
+                .all(|t| matches!(t, Token::Semicolon | Token::BatchSeparator(_)))
+            {
+                return None;
+            }
+            let (tables, ctes) = table_names(&tokens);
+            Some(StatementInfo {
+                kind: statement_kind(&tokens),
+                range,
+                tables,
+                has_subquery: has_subquery(&tokens),
+                has_cte: ctes > 0,
+                max_depth: max_depth(&tokens),
+            })
+        })
+        .collect()
+}
+
+/// Whether a parenthesized query other than a CTE body appears.
+fn has_subquery(tokens: &[Token<'_>]) -> bool {
+    tokens.windows(3).any(|window| match window {
+        [Token::Keyword(KeywordKind::As), Token::OpenParen, _] => false,
+        [
+            _,
+            Token::OpenParen,
+            Token::Keyword(KeywordKind::Select | KeywordKind::With),
+        ] => true,
+        _ => false,
+    })
+}
+
+/// Kind of a statement from its first DML keyword outside parentheses, so
+/// `WITH ... INSERT` counts as an insert.
+fn statement_kind(tokens: &[Token<'_>]) -> StatementKind {
+    if let Some(Token::Keyword(kw)) = tokens.first()
+        && kw.is_ddl_starter()
+    {
+        return StatementKind::Ddl;
+    }
+    let mut depth = 0usize;
+    for token in tokens {
+        match token {
+            Token::OpenParen => depth += 1,
+            Token::CloseParen => depth = depth.saturating_sub(1),
+            Token::Keyword(kw) if depth == 0 => match kw {
+                KeywordKind::Select => return StatementKind::Select,
+                KeywordKind::Insert => return StatementKind::Insert,
+                KeywordKind::Update => return StatementKind::Update,
+                KeywordKind::Delete => return StatementKind::Delete,
+                _ => {}
+            },
+            Token::Identifier(word) if depth == 0 && word.eq_ignore_ascii_case("MERGE") => {
+                return StatementKind::Merge;
+            }
+            _ => {}
+        }
+    }
+    StatementKind::Other
+}
+
+fn max_depth(tokens: &[Token<'_>]) -> usize {
+    let mut depth = 0usize;
+    let mut max = 0;
+    for token in tokens {
+        match token {
+            Token::OpenParen => {
+                depth += 1;
+                max = max.max(depth);
+            }
+            Token::CloseParen => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    max
+}
+
+/// Names following FROM, JOIN, INTO, UPDATE, TABLE and REFERENCES (and the
+/// rest of a comma-separated FROM list), minus the statement's CTE names,
+/// which are counted.
+fn table_names(tokens: &[Token<'_>]) -> (Vec<String>, usize) {
+    let mut ctes = Vec::new();
+    let mut tables: Vec<String> = Vec::new();
+    // Paren depth of the FROM list being read, if any
+    let mut from_depth: Option<usize> = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match token {
+            Token::OpenParen => depth += 1,
+            Token::CloseParen => {
+                depth = depth.saturating_sub(1);
+                from_depth = from_depth.filter(|&d| d <= depth);
+            }
+            Token::Comma if from_depth == Some(depth) => {
+                if let Some((name, len)) = qualified_name(&tokens[i + 1..]) {
+                    tables.push(name);
+                    i += len;
+                }
+            }
+            Token::Keyword(kw) => {
+                if matches!(kw, KeywordKind::As)
+                    && tokens.get(i + 1) == Some(&Token::OpenParen)
+                    && let Some(name) = i.checked_sub(1).and_then(|p| name_text(&tokens[p]))
+                {
+                    ctes.push(name.to_ascii_lowercase());
+                }
+                if *kw == KeywordKind::From {
+                    from_depth = Some(depth);
+                } else if kw.is_clause_starter() || kw.is_order_modifier() {
+                    from_depth = from_depth.filter(|&d| d < depth);
+                }
+
+                let introduces_table = kw.is_join_keyword()
+                    || matches!(
+                        kw,
+                        KeywordKind::From
+                            | KeywordKind::Into
+                            | KeywordKind::Update
+                            | KeywordKind::Table
+                            | KeywordKind::References
+                            | KeywordKind::Truncate
+                    );
+                if introduces_table {
+                    let mut start = i + 1;
+                    while let Some(Token::Keyword(
+                        KeywordKind::IfExists | KeywordKind::IfNotExists | KeywordKind::Table,
+                    )) = tokens.get(start)
+                    {
+                        start += 1;
+                    }
+                    if let Some((name, len)) = qualified_name(&tokens[start..]) {
+                        tables.push(name);
+                        i = start + len;
+                        continue;
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    tables.retain(|name| !ctes.contains(&name.to_ascii_lowercase()));
+    let mut seen = Vec::new();
+    tables.retain(|name| {
+        let first = !seen.contains(name);
+        seen.push(name.clone());
+        first
+    });
+    (tables, ctes.len())
+}
+
+fn name_text(token: &Token<'_>) -> Option<String> {
+    match token {
+        Token::Identifier(name) => Some(name.to_string()),
+        Token::QuotedIdentifier(name) => Some(format!("\"{}\"", name)),
+        Token::BacktickIdentifier(name) => Some(format!("`{}`", name)),
+        Token::BracketIdentifier(name) => Some(format!("[{}]", name)),
+        _ => None,
+    }
+}
+
+/// A dotted name at the start of `tokens` and the number of tokens it spans.
+fn qualified_name(tokens: &[Token<'_>]) -> Option<(String, usize)> {
+    let mut name = name_text(tokens.first()?)?;
+    let mut len = 1;
+    while tokens.get(len) == Some(&Token::Dot)
+        && let Some(part) = tokens.get(len + 1).and_then(name_text)
+    {
+        name.push('.');
+        name.push_str(&part);
+        len += 2;
+    }
+    Some((name, len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let input = "with recent as (select * from orders)\n\
+                     select * from recent r join public.users u on u.id = r.user_id, users;\n\
+                     /* only a comment */;\n\
+                     update t set a = (select max(a) from t);";
+        let statements = analyze(input, Dialect::Generic);
+        assert_eq!(
+            statements,
+            vec![
+                StatementInfo {
+                    kind: StatementKind::Select,
+                    range: 0..108,
+                    tables: vec![
+                        "orders".to_string(),
+                        "public.users".to_string(),
+                        "users".to_string()
+                    ],
+                    has_subquery: false,
+                    has_cte: true,
+                    max_depth: 1,
+                },
+                StatementInfo {
+                    kind: StatementKind::Update,
+                    range: 131..input.len(),
+                    tables: vec!["t".to_string()],
+                    has_subquery: true,
+                    has_cte: false,
+                    max_depth: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statement_kinds() {
+        let kinds: Vec<StatementKind> = analyze(
+            "with x as (select 1) insert into t select * from x; \
+             create table t (a int); merge into t using u on t.a = u.a; vacuum",
+            Dialect::Generic,
+        )
+        .iter()
+        .map(|s| s.kind)
+        .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                StatementKind::Insert,
+                StatementKind::Ddl,
+                StatementKind::Merge,
+                StatementKind::Other
+            ]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! `--changed-since`: find the lines of a file changed since a git ref, so
+//! only the statements touching them are reformatted and legacy SQL nobody
+//! edited keeps its layout.
+
+use std::io;
+use std::ops::Range;
+use std::path::Path;
+
+use super::staged::git;
+
+/// The lines of `path` changed since `git_ref`, as (first line, count)
+/// pairs counted from 1 in the working tree copy. A count of 0 marks lines
+/// deleted after the given one. `None` means the file did not exist at the
+/// ref, so all of it is new.
+pub fn changed_lines(git_ref: &str, path: &Path) -> io::Result<Option<Vec<(usize, usize)>>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::other("not a file"))?
+        .to_string_lossy();
+    git(
+        dir,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", git_ref),
+        ],
+        None,
+    )
+    .map_err(|_| io::Error::other(format!("unknown git revision `{}`", git_ref)))?;
+    if git(
+        dir,
+        &["cat-file", "-e", &format!("{}:./{}", git_ref, name)],
+        None,
+    )
+    .is_err()
+    {
+        return Ok(None);
+    }
+    let output = git(
+        dir,
+        &[
+            "diff",
+            "-U0",
+            "--no-color",
+            "--no-ext-diff",
+            git_ref,
+            "--",
+            &name,
+        ],
+        None,
+    )?;
+    Ok(Some(parse_hunks(&String::from_utf8_lossy(&output))))
+}
+
+/// The new-side line spans of the `@@ -a,b +c,d @@` headers of a unified
+/// diff. A missing count is 1.
+fn parse_hunks(diff: &str) -> Vec<(usize, usize)> {
+    diff.lines()
+        .filter_map(|line| {
+            let header = line.strip_prefix("@@ -")?;
+            let new = header.split(' ').nth(1)?.strip_prefix('+')?;
+            let (start, count) = new.split_once(',').unwrap_or((new, "1"));
+            Some((start.parse().ok()?, count.parse().ok()?))
+        })
+        .collect()
+}
+
+/// Byte ranges of `input` covering the changed `lines`, each without its
+/// line break. Deleted lines become an empty range at the end of the line
+/// before them.
+pub fn byte_ranges(input: &str, lines: &[(usize, usize)]) -> Vec<Range<usize>> {
+    let mut starts = vec![0];
+    starts.extend(input.match_indices('\n').map(|(i, _)| i + 1));
+    // The end of line `n` (from 1), before its line break
+    let line_end = |n: usize| match starts.get(n) {
+        Some(&next) => input[..next - 1]
+            .strip_suffix('\r')
+            .map_or(next - 1, str::len),
+        None => input.len(),
+    };
+    lines
+        .iter()
+        .map(|&(first, count)| {
+            if count == 0 {
+                let end = if first == 0 { 0 } else { line_end(first) };
+                return end..end;
+            }
+            let start = starts.get(first - 1).copied().unwrap_or(input.len());
+            start..line_end(first + count - 1).max(start)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hunks() {
+        let diff = "diff --git a/q.sql b/q.sql\nindex 1111111..2222222 100644\n\
+                    --- a/q.sql\n+++ b/q.sql\n@@ -2 +2 @@ select a;\n-select b;\n+SELECT b;\n\
+                    @@ -5,2 +4,0 @@ select c;\n-x\n-y\n@@ -9,0 +8,3 @@\n+a\n+b\n+c\n";
+        assert_eq!(parse_hunks(diff), vec![(2, 1), (4, 0), (8, 3)]);
+    }
+
+    #[test]
+    fn test_byte_ranges() {
+        let input = "select a;\r\nselect b;\nselect c;";
+        assert_eq!(
+            byte_ranges(input, &[(2, 1), (1, 0), (0, 0), (2, 2)]),
+            vec![11..20, 9..9, 0..0, 11..30]
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! Read `sql-indent.toml`, the tool's own configuration file. It uses the
+//! keys printed by `--show-config`:
+//!
+//! ```toml
+//! preset = "compact"   # applied first, so the keys below override it
+//! style = "dataops"
+//! dialect = "bigquery"
+//! uppercase = false
+//! indent_width = 2
+//! placeholders = "dollar"
+//! ```
+//!
+//! A single file can override them with a comment on its first line:
+//!
+//! ```sql
+//! -- sql-indent: style=aligned, case=lower, indent=2
+//! ```
+
+use rs_sql_indent::config::{FormatOptions, Preset, validate_indent_width};
+
+pub const FILE_NAME: &str = "sql-indent.toml";
+
+const FILE_DIRECTIVE: &str = "sql-indent:";
+
+/// Override `options` with the settings in `sql-indent.toml` contents.
+/// Unknown keys and malformed values are errors, reported with their line
+/// number, so typos do not silently fall back to the defaults.
+pub fn apply(contents: &str, options: &mut FormatOptions) -> Result<(), String> {
+    let mut settings = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected `key = value`", index + 1))?;
+        settings.push((index + 1, key.trim(), value.trim()));
+    }
+
+    // The preset is a starting point wherever it appears in the file
+    settings.sort_by_key(|&(_, key, _)| key != "preset");
+    for (line, key, value) in settings {
+        apply_setting(key, value, options).map_err(|e| format!("line {}: {}", line, e))?;
+    }
+    Ok(())
+}
+
+/// A [`FormatOptions`] field under its `sql-indent.toml` key. The file,
+/// the `-- sql-indent:` directive, the daemon's `options` and
+/// `--show-config` all read and write the options through [`FIELDS`].
+pub struct Field {
+    pub key: &'static str,
+    /// Whether the value is written as a quoted string
+    pub quoted: bool,
+    /// The value, unquoted, or `None` when the field is unset
+    pub get: fn(&FormatOptions) -> Option<String>,
+    /// Set the field from an unquoted value
+    pub set: fn(&str, &mut FormatOptions) -> Result<(), String>,
+    /// How an optional field is left unset. `None` for required fields.
+    pub unset: Option<Unset>,
+}
+
+pub struct Unset {
+    /// What an unset field means, as `--show-config` explains it
+    pub meaning: &'static str,
+    pub clear: fn(&mut FormatOptions),
+}
+
+/// Every field of [`FormatOptions`], in `--show-config` order.
+pub const FIELDS: &[Field] = &[
+    Field {
+        key: "style",
+        quoted: true,
+        get: |options| Some(options.style.to_string()),
+        set: |value, options| {
+            options.style = value.parse()?;
+            Ok(())
+        },
+        unset: None,
+    },
+    Field {
+        key: "dialect",
+        quoted: true,
+        get: |options| Some(options.dialect.to_string()),
+        set: |value, options| {
+            options.dialect = value.parse()?;
+            Ok(())
+        },
+        unset: None,
+    },
+    Field {
+        key: "uppercase",
+        quoted: false,
+        get: |options| Some(options.uppercase.to_string()),
+        set: |value, options| {
+            options.uppercase = match value {
+                "true" => true,
+                "false" => false,
+                _ => return Err(format!("`uppercase` must be true or false, got {}", value)),
+            };
+            Ok(())
+        },
+        unset: None,
+    },
+    Field {
+        key: "indent_width",
+        quoted: false,
+        get: |options| options.indent_width.map(|width| width.to_string()),
+        set: |value, options| {
+            let width = value
+                .parse()
+                .map_err(|_| format!("`indent_width` must be a number, got {}", value))?;
+            options.indent_width = Some(validate_indent_width(width)?);
+            Ok(())
+        },
+        unset: Some(Unset {
+            meaning: "the style's default",
+            clear: |options| options.indent_width = None,
+        }),
+    },
+    Field {
+        key: "placeholders",
+        quoted: true,
+        get: |options| options.placeholders.map(|style| style.to_string()),
+        set: |value, options| {
+            options.placeholders = Some(value.parse()?);
+            Ok(())
+        },
+        unset: Some(Unset {
+            meaning: "kept as written",
+            clear: |options| options.placeholders = None,
+        }),
+    },
+];
+
+/// The field stored under `key`.
+pub fn field(key: &str) -> Option<&'static Field> {
+    FIELDS.iter().find(|field| field.key == key)
+}
+
+/// Override `options` with one `key = value` setting, the value written as
+/// in the file: strings quoted, booleans and numbers bare.
+pub fn apply_setting(key: &str, value: &str, options: &mut FormatOptions) -> Result<(), String> {
+    if key == "preset" {
+        let name = string_value(value)?;
+        Preset::find(name)
+            .ok_or_else(|| format!("unknown preset `{}`", name))?
+            .apply(options);
+        return Ok(());
+    }
+    let field = field(key).ok_or_else(|| format!("unknown key `{}`", key))?;
+    let value = if field.quoted {
+        string_value(value)?
+    } else {
+        value
+    };
+    (field.set)(value, options)
+}
+
+/// Override `options` with the `-- sql-indent: key=value, ...` comment on
+/// the first line of `input`, if there is one. The keys are `preset`,
+/// `style`, `dialect`, `case` (`upper` or `lower`) and `indent`, checked
+/// as in `sql-indent.toml`. The `-- sql-indent: off` and `on` region
+/// markers are not settings.
+pub fn apply_directive(input: &str, options: &mut FormatOptions) -> Result<(), String> {
+    let Some(settings) = directive(input) else {
+        return Ok(());
+    };
+    let mut settings = settings
+        .split(',')
+        .map(|setting| {
+            let (key, value) = setting.split_once('=').ok_or_else(|| {
+                format!(
+                    "`{}` in the sql-indent directive is not `key=value`",
+                    setting.trim()
+                )
+            })?;
+            Ok((key.trim(), value.trim()))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    settings.sort_by_key(|&(key, _)| key != "preset");
+    for (key, value) in settings {
+        apply_directive_setting(key, value, options)
+            .map_err(|e| format!("sql-indent directive: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Whether the first line of `input` is a `-- sql-indent:` settings comment.
+pub fn has_directive(input: &str) -> bool {
+    directive(input).is_some()
+}
+
+/// The settings after `sql-indent:` in a comment on the first line.
+fn directive(input: &str) -> Option<&str> {
+    let first_line = input.lines().next()?;
+    let text = first_line.trim().strip_prefix("--")?.trim();
+    let prefix = text.get(..FILE_DIRECTIVE.len())?;
+    if !prefix.eq_ignore_ascii_case(FILE_DIRECTIVE) {
+        return None;
+    }
+    let settings = text[prefix.len()..].trim();
+    let region_marker = settings.eq_ignore_ascii_case("on") || settings.eq_ignore_ascii_case("off");
+    (!region_marker).then_some(settings)
+}
+
+fn apply_directive_setting(
+    key: &str,
+    value: &str,
+    options: &mut FormatOptions,
+) -> Result<(), String> {
+    match key {
+        "preset" | "style" | "dialect" => {
+            apply_setting(key, &format!("\"{}\"", value.to_ascii_lowercase()), options)
+        }
+        "case" => {
+            options.uppercase = match value.to_ascii_lowercase().as_str() {
+                "upper" => true,
+                "lower" => false,
+                _ => return Err(format!("`case` must be upper or lower, got {}", value)),
+            };
+            Ok(())
+        }
+        "indent" => apply_setting("indent_width", value, options),
+        _ => Err(format!("unknown key `{}`", key)),
+    }
+}
+
+fn string_value(value: &str) -> Result<&str, String> {
+    value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a quoted string, got {}", value))
+}
+
+/// Drop a `#` comment, unless the `#` is inside a string.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_sql_indent::config::{Dialect, FormatStyle, PlaceholderStyle};
+
+    fn parse(contents: &str) -> Result<FormatOptions, String> {
+        let mut options = FormatOptions::default();
+        apply(contents, &mut options).map(|()| options)
+    }
+
+    #[test]
+    fn test_all_keys() {
+        assert_eq!(
+            parse(
+                "# team defaults\nstyle = \"dataops\"\ndialect = \"postgresql\"\n\
+                 uppercase = false  # dbt convention\nindent_width = 2\n\
+                 placeholders = \"dollar\"\n"
+            ),
+            Ok(FormatOptions {
+                uppercase: false,
+                style: FormatStyle::Dataops,
+                dialect: Dialect::Postgres,
+                indent_width: Some(2),
+                placeholders: Some(PlaceholderStyle::Dollar),
+            })
+        );
+    }
+
+    #[test]
+    fn test_preset_applies_first() {
+        let options = parse("uppercase = true\npreset = \"sqlfluff\"\n").unwrap();
+        assert!(options.uppercase);
+        assert_eq!(options.indent_width, Some(4));
+    }
+
+    #[test]
+    fn test_directive() {
+        let mut options = FormatOptions::default();
+        apply_directive(
+            "-- SQL-Indent: style=Aligned, case=lower ,indent=2, preset=compact\nselect 1",
+            &mut options,
+        )
+        .unwrap();
+        assert_eq!(
+            options,
+            FormatOptions {
+                uppercase: false,
+                style: FormatStyle::Aligned,
+                dialect: Dialect::Generic,
+                indent_width: Some(2),
+                placeholders: None,
+            }
+        );
+
+        for input in [
+            "-- sql-indent: off\nselect 1",
+            "select 1 -- sql-indent: style=aligned",
+        ] {
+            let mut unchanged = FormatOptions::default();
+            apply_directive(input, &mut unchanged).unwrap();
+            assert_eq!(unchanged, FormatOptions::default());
+        }
+        assert_eq!(
+            apply_directive("-- sql-indent: colour=red", &mut options),
+            Err("sql-indent directive: unknown key `colour`".to_string())
+        );
+        assert_eq!(
+            apply_directive("-- sql-indent: aligned", &mut options),
+            Err("`aligned` in the sql-indent directive is not `key=value`".to_string())
+        );
+        assert_eq!(
+            apply_directive("-- sql-indent: indent=99999999999", &mut options),
+            Err(
+                "sql-indent directive: indent width must be between 1 and 16, got 99999999999"
+                    .to_string()
+            )
+        );
+        assert_eq!(options.indent_width, Some(2));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse("style = \"basic\"\nstlye = \"aligned\""),
+            Err("line 2: unknown key `stlye`".to_string())
+        );
+        assert_eq!(
+            parse("style = fancy"),
+            Err("line 1: expected a quoted string, got fancy".to_string())
+        );
+        assert_eq!(
+            parse("[format]"),
+            Err("line 1: expected `key = value`".to_string())
+        );
+        assert_eq!(
+            parse("indent_width = 100000000000"),
+            Err("line 1: indent width must be between 1 and 16, got 100000000000".to_string())
+        );
+        assert_eq!(
+            parse("indent_width = 0"),
+            Err("line 1: indent width must be between 1 and 16, got 0".to_string())
+        );
+    }
+}
@@ -0,0 +1,52 @@
+//! Find the `sql-indent.toml` files that apply to a directory.
+
+use std::path::{Path, PathBuf};
+
+use super::config_file::FILE_NAME;
+
+/// The `sql-indent.toml` files in `dir` and its parents, up to and
+/// including the root of the git repository containing it (or the
+/// filesystem root outside a repository). The outermost comes first, so
+/// applying them in order lets the nearest file override its ancestors.
+pub fn config_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for ancestor in dir.ancestors() {
+        let candidate = ancestor.join(FILE_NAME);
+        if candidate.is_file() {
+            files.push(candidate);
+        }
+        if ancestor.join(".git").exists() {
+            break;
+        }
+    }
+    files.reverse();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_stops_at_git_root() {
+        let root =
+            std::env::temp_dir().join(format!("rs-sql-indent-search-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        let repo = root.join("repo");
+        let package = repo.join("packages/app");
+        fs::create_dir_all(package.join("queries")).unwrap();
+        fs::create_dir_all(repo.join(".git")).unwrap();
+        for dir in [&root, &repo, &package] {
+            fs::write(dir.join(FILE_NAME), "").unwrap();
+        }
+
+        assert_eq!(
+            config_files(&package.join("queries")),
+            vec![repo.join(FILE_NAME), package.join(FILE_NAME)]
+        );
+        assert_eq!(config_files(&repo), vec![repo.join(FILE_NAME)]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+//! `--daemon`: a long-running formatter for editor plugins, so formatting
+//! on every keystroke does not pay for a process spawn. Requests and
+//! replies are JSON, one per line:
+//!
+//! ```text
+//! > {"id": 1, "sql": "select a from t", "options": {"style": "streamline"}}
+//! < {"id":1,"output":"SELECT\n  a\nFROM\n  t","changed":true,"diagnostics":[]}
+//! > {"id": 2, "sql": "select 'a"}
+//! < {"id":2,"output":"SELECT\n    'a'","changed":true,"diagnostics":[{"severity":"error","message":"unclosed string literal","offset":7,"line":1,"column":8}]}
+//! ```
+//!
+//! `options` takes the keys of `sql-indent.toml` and overrides the options
+//! the daemon was started with; `id`, if given, is echoed back. A request
+//! that cannot be served gets `{"id":...,"error":"..."}` and the daemon
+//! carries on. It exits at the end of its input.
+
+use std::io::{self, BufRead, Write};
+
+use rs_sql_indent::{FormatOptions, Severity, format_with_diagnostics};
+
+use super::config_file;
+use super::json::{self, Value};
+use super::report::json_string;
+
+/// Answer each request line of `reader` on `writer` with `defaults` as the
+/// base options, flushing after every reply.
+pub fn run<R: BufRead, W: Write>(
+    reader: R,
+    mut writer: W,
+    defaults: &FormatOptions,
+) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        writeln!(writer, "{}", respond(&line, defaults))?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// The reply line to one request line.
+fn respond(line: &str, defaults: &FormatOptions) -> String {
+    let request = match json::parse(line) {
+        Ok(request) => request,
+        Err(e) => return reply(None, &format!("\"error\":{}", json_string(&e))),
+    };
+    let id = request.get("id");
+    match format_request(&request, defaults) {
+        Ok(body) => reply(id, &body),
+        Err(e) => reply(id, &format!("\"error\":{}", json_string(&e))),
+    }
+}
+
+fn reply(id: Option<&Value>, body: &str) -> String {
+    match id {
+        Some(id) => format!("{{\"id\":{},{}}}", id, body),
+        None => format!("{{{}}}", body),
+    }
+}
+
+/// The `output`, `changed` and `diagnostics` members of a reply.
+fn format_request(request: &Value, defaults: &FormatOptions) -> Result<String, String> {
+    let Some(Value::String(sql)) = request.get("sql") else {
+        return Err("request needs an `sql` string".to_string());
+    };
+    let mut options = *defaults;
+    match request.get("options") {
+        None | Some(Value::Null) => {}
+        Some(Value::Object(settings)) => apply_options(settings, &mut options)?,
+        Some(_) => return Err("`options` must be an object".to_string()),
+    }
+    config_file::apply_directive(sql, &mut options)?;
+
+    let formatted = format_with_diagnostics(sql, &options);
+    let diagnostics: Vec<String> = formatted
+        .diagnostics
+        .iter()
+        .map(|d| {
+            let severity = match d.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            format!(
+                "{{\"severity\":\"{}\",\"message\":{},\"offset\":{},\"line\":{},\"column\":{}}}",
+                severity,
+                json_string(d.message),
+                d.span.start,
+                d.span.line,
+                d.span.column
+            )
+        })
+        .collect();
+    let changed = sql.strip_suffix('\n').unwrap_or(sql) != formatted.text;
+    Ok(format!(
+        "\"output\":{},\"changed\":{},\"diagnostics\":[{}]",
+        json_string(&formatted.text),
+        changed,
+        diagnostics.join(",")
+    ))
+}
+
+/// Apply `options` members as `sql-indent.toml` settings, the preset first,
+/// so they are checked as the file's are. `null` clears an optional field,
+/// such as `indent_width`.
+fn apply_options(settings: &[(String, Value)], options: &mut FormatOptions) -> Result<(), String> {
+    let mut settings: Vec<&(String, Value)> = settings.iter().collect();
+    settings.sort_by_key(|(key, _)| key != "preset");
+    for (key, value) in settings {
+        match (key.as_str(), value) {
+            (_, Value::Null) => {
+                let unset = config_file::field(key).and_then(|field| field.unset.as_ref());
+                let unset = unset
+                    .ok_or_else(|| format!("`{}` must be a string, number or boolean", key))?;
+                (unset.clear)(options)
+            }
+            (_, Value::String(s)) => {
+                config_file::apply_setting(key, &format!("\"{}\"", s), options)?
+            }
+            (_, Value::Bool(b)) => config_file::apply_setting(key, &b.to_string(), options)?,
+            (_, Value::Number(n)) => config_file::apply_setting(key, n, options)?,
+            _ => return Err(format!("`{}` must be a string, number or boolean", key)),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(input: &str) -> String {
+        let mut out = Vec::new();
+        run(input.as_bytes(), &mut out, &FormatOptions::default()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_requests() {
+        assert_eq!(
+            session(
+                "{\"id\": 1, \"sql\": \"select a from t\", \"options\": {\"style\": \"streamline\", \"uppercase\": false}}\n\
+                 \n\
+                 {\"id\": \"b\", \"sql\": \"SELECT\\n    'a\"}\n"
+            ),
+            "{\"id\":1,\"output\":\"select\\n  a\\nfrom\\n  t\",\"changed\":true,\"diagnostics\":[]}\n\
+             {\"id\":\"b\",\"output\":\"SELECT\\n    'a'\",\"changed\":true,\"diagnostics\":\
+             [{\"severity\":\"error\",\"message\":\"unclosed string literal\",\"offset\":11,\"line\":2,\"column\":5}]}\n"
+        );
+    }
+
+    #[test]
+    fn test_unchanged() {
+        assert_eq!(
+            session("{\"sql\": \"SELECT\\n    1\\n\"}"),
+            "{\"output\":\"SELECT\\n    1\",\"changed\":false,\"diagnostics\":[]}\n"
+        );
+    }
+
+    #[test]
+    fn test_errors_keep_the_daemon_running() {
+        assert_eq!(
+            session(
+                "not json\n\
+                 {\"id\": 2, \"sql\": \"select 1\", \"options\": {\"style\": \"fancy\"}}\n\
+                 {\"id\": 3, \"options\": {}}\n\
+                 {\"id\": 5, \"sql\": \"select 1\", \"options\": {\"indent_width\": 100000000000}}\n\
+                 {\"id\": 4, \"sql\": \"select 1\", \"options\": {\"indent_width\": 2, \"placeholders\": null}}\n"
+            ),
+            "{\"error\":\"expected a value at byte 0\"}\n\
+             {\"id\":2,\"error\":\"unknown style `fancy`, expected one of: basic, streamline, aligned, dataops\"}\n\
+             {\"id\":3,\"error\":\"request needs an `sql` string\"}\n\
+             {\"id\":5,\"error\":\"indent width must be between 1 and 16, got 100000000000\"}\n\
+             {\"id\":4,\"output\":\"SELECT\\n  1\",\"changed\":true,\"diagnostics\":[]}\n"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! dbt projects: which directories of a project hold SQL, read from its
+//! `dbt_project.yml`, and formatting of models whose Jinja header (the
+//! `{{ config(...) }}` call, `{% set %}` assignments and `{# #}` comments
+//! before the SQL) is kept in place as written.
+//!
+//! Jinja in the SQL itself is opaque to the lexer either way: `{{ ref(...) }}`
+//! formats like a table name and `{% if %}` blocks indent their contents.
+
+use rs_sql_indent::config::Dialect;
+use rs_sql_indent::lexer;
+use rs_sql_indent::token::{BlockControl, Token};
+
+pub const PROJECT_FILE: &str = "dbt_project.yml";
+
+/// Project settings naming directories of SQL files, with dbt's defaults.
+/// Macros are Jinja rather than SQL, and seeds are CSV, so neither is here.
+const SQL_PATH_KEYS: &[(&str, &str)] = &[
+    ("model-paths", "models"),
+    ("analysis-paths", "analyses"),
+    ("snapshot-paths", "snapshots"),
+    ("test-paths", "tests"),
+];
+
+/// The directories holding SQL files of the project described by
+/// `dbt_project.yml` contents, relative to the project root. Settings left
+/// out take dbt's defaults; `source-paths` is read as the old name of
+/// `model-paths`.
+pub fn sql_paths(contents: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    for &(key, default) in SQL_PATH_KEYS {
+        let mut found = list_setting(contents, key);
+        if found.is_none() && key == "model-paths" {
+            found = list_setting(contents, "source-paths");
+        }
+        paths.extend(found.unwrap_or_else(|| vec![default.to_string()]));
+    }
+    paths
+}
+
+/// A top-level list setting of a YAML document, written either as
+/// `key: [a, "b"]` or as a block of `- a` items.
+fn list_setting(contents: &str, key: &str) -> Option<Vec<String>> {
+    let mut lines = contents.lines();
+    let value = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        (name.trim_end() == key && !line.starts_with([' ', '\t'])).then_some(value)
+    })?;
+    let value = strip_yaml_comment(value).trim();
+    if let Some(items) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+        return Some(
+            items
+                .split(',')
+                .map(unquote)
+                .filter(|item| !item.is_empty())
+                .collect(),
+        );
+    }
+    if !value.is_empty() {
+        return Some(vec![unquote(value)]);
+    }
+    let items = lines
+        .map(|line| strip_yaml_comment(line).trim())
+        .filter(|line| !line.is_empty())
+        .map_while(|line| line.strip_prefix('-').map(unquote))
+        .collect();
+    Some(items)
+}
+
+fn strip_yaml_comment(line: &str) -> &str {
+    match line.find(" #") {
+        Some(i) => &line[..i],
+        None if line.trim_start().starts_with('#') => "",
+        None => line,
+    }
+}
+
+fn unquote(item: &str) -> String {
+    item.trim().trim_matches(['"', '\'']).to_string()
+}
+
+/// A model split into its Jinja header and the SQL after it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Model<'a> {
+    /// Template expressions, assignments and comments before the SQL,
+    /// without the line breaks that follow them
+    pub header: &'a str,
+    /// The rest of the model, from the end of the header
+    pub body: &'a str,
+}
+
+impl<'a> Model<'a> {
+    pub fn parse(input: &'a str, dialect: Dialect) -> Model<'a> {
+        let mut end = 0;
+        for (token, range) in lexer::tokenize_with_offsets(input, dialect) {
+            match token {
+                Token::Whitespace(_) => {}
+                Token::TemplateVariable(_) if !token.is_template_control() => end = range.end,
+                Token::TemplateBlock(_)
+                    if token.template_control() == Some(BlockControl::Standalone) =>
+                {
+                    end = range.end
+                }
+                _ => break,
+            }
+        }
+        Model {
+            header: &input[..end],
+            body: &input[end..],
+        }
+    }
+
+    /// The header followed by `formatted`, the formatted body, on the next
+    /// line or after a blank line, as the header was followed in the input.
+    pub fn join(&self, formatted: &str) -> String {
+        if self.header.is_empty() {
+            return formatted.to_string();
+        }
+        if self.body.trim().is_empty() {
+            return format!("{}{}", self.header, formatted.trim_start());
+        }
+        let gap = &self.body[..self.body.len() - self.body.trim_start().len()];
+        let separator = if gap.matches('\n').count() > 1 {
+            "\n\n"
+        } else {
+            "\n"
+        };
+        format!("{}{}{}", self.header, separator, formatted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_sql_indent::{FormatOptions, format_sql};
+
+    fn format_model(input: &str, options: &FormatOptions) -> String {
+        let model = Model::parse(input, options.dialect);
+        model.join(&format_sql(model.body, options))
+    }
+
+    #[test]
+    fn test_sql_paths() {
+        assert_eq!(
+            sql_paths(
+                "name: shop\nmodel-paths: [\"models\", 'marts']  # both\n\
+                 snapshot-paths:\n  - snaps\n  # legacy\n  - old_snaps\nmacro-paths: [\"macros\"]\n"
+            ),
+            vec!["models", "marts", "analyses", "snaps", "old_snaps", "tests"]
+        );
+        assert_eq!(
+            sql_paths("source-paths: [src]\nmodels:\n  model-paths: [nested]\n"),
+            vec!["src", "analyses", "snapshots", "tests"]
+        );
+    }
+
+    #[test]
+    fn test_format_model_keeps_header() {
+        let input = "{{ config(materialized='table') }}\n{% set days = 7 %}\n\n\
+                     select id from {{ ref('orders') }}\n\
+                     {% if is_incremental() %} where d > {{ days }} {% endif %}\n";
+        assert_eq!(
+            format_model(input, &FormatOptions::default()),
+            "{{ config(materialized='table') }}\n{% set days = 7 %}\n\n\
+             SELECT\n    id\nFROM\n    {{ ref('orders') }}\n    {% if is_incremental() %}\n    \
+             WHERE\n        d > {{ days }}\n    {% endif %}"
+        );
+    }
+
+    #[test]
+    fn test_model_without_header() {
+        let options = FormatOptions::default();
+        assert_eq!(format_model("select 1", &options), "SELECT\n    1");
+        assert_eq!(
+            format_model("{% if x %}select 1{% endif %}", &options),
+            format_sql("{% if x %}select 1{% endif %}", &options)
+        );
+        assert_eq!(
+            format_model("{{ config(enabled=false) }}\n", &options),
+            "{{ config(enabled=false) }}"
+        );
+    }
+}
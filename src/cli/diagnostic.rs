@@ -0,0 +1,79 @@
+//! rustc-style rendering of lexer diagnostics:
+//!
+//! ```text
+//! error: unclosed string literal
+//!  --> query.sql:3:15
+//!   |
+//! 3 | where name = 'abc
+//!   |              ^
+//! ```
+
+use rs_sql_indent::Severity;
+use rs_sql_indent::lexer::Diagnostic;
+
+/// Render `diagnostic` against `source`, the text of `name` starting at
+/// line `first_line`, labelled with `severity`.
+pub fn render(
+    name: &str,
+    source: &str,
+    diagnostic: &Diagnostic,
+    severity: Severity,
+    first_line: usize,
+) -> String {
+    let offset = diagnostic.offset;
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[offset..]
+        .find('\n')
+        .map_or(source.len(), |i| offset + i);
+    let (line, column) = line_column(source, offset);
+    let line = line + first_line - 1;
+    // Keep tabs so the caret lines up with the source line
+    let caret_indent: String = source[line_start..offset]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let gutter = " ".repeat(line.to_string().len());
+    let label = match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    format!(
+        "{}: {}\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {}\n{gutter} | {}^\n",
+        label,
+        diagnostic.message,
+        name,
+        line,
+        column,
+        line,
+        source[line_start..line_end].trim_end_matches('\r'),
+        caret_indent,
+    )
+}
+
+/// 1-based line and column (in characters) of byte `offset` in `source`.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+    (
+        source[..offset].matches('\n').count() + 1,
+        source[line_start..offset].chars().count() + 1,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let source = "select a\nfrom t\n\twhere b = 'x";
+        let diagnostic = Diagnostic {
+            message: "unclosed string literal",
+            offset: source.find('\'').unwrap(),
+        };
+        assert_eq!(
+            render("q.sql", source, &diagnostic, Severity::Error, 9),
+            "error: unclosed string literal\n  --> q.sql:11:12\n   |\n11 | \twhere b = 'x\n   | \t          ^\n"
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Line-based unified diff (Myers' algorithm) between the original input and
+//! the formatted output.
+
+const CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Render a unified diff from `old` to `new`. Returns an empty string when
+/// both texts are identical.
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let a: Vec<&str> = old.split_inclusive('\n').collect();
+    let b: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = diff_lines(&a, &b);
+
+    let mut out = format!("--- {}\n+++ {}\n", old_label, new_label);
+    for hunk in hunks(&edits) {
+        write_hunk(&mut out, &edits[hunk.0..hunk.1], &a, &b);
+    }
+    out
+}
+
+/// Shortest edit script turning `a` into `b`.
+fn diff_lines(a: &[&str], b: &[&str]) -> Vec<Edit> {
+    let n = a.len() as isize;
+    let m = b.len() as isize;
+    let max = (n + m) as usize;
+    let offset = max as isize;
+    let mut v = vec![0isize; 2 * max + 2];
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    'search: for d in 0..=max as isize {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    // Walk the trace backwards to recover the edit path
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let idx = (k + offset) as usize;
+        let prev_k = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[(prev_k + offset) as usize];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                edits.push(Edit::Insert(prev_y as usize));
+            } else {
+                edits.push(Edit::Delete(prev_x as usize));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Ranges of `edits` forming hunks: each change plus `CONTEXT` lines of
+/// surrounding equal lines, merging changes that are close together.
+fn hunks(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, edit) in edits.iter().enumerate() {
+        if matches!(edit, Edit::Equal(..)) {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT);
+        let end = (i + 1 + CONTEXT).min(edits.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+fn write_hunk(out: &mut String, edits: &[Edit], a: &[&str], b: &[&str]) {
+    let old_start = edits.iter().find_map(|e| match e {
+        Edit::Equal(i, _) | Edit::Delete(i) => Some(*i),
+        Edit::Insert(_) => None,
+    });
+    let new_start = edits.iter().find_map(|e| match e {
+        Edit::Equal(_, j) | Edit::Insert(j) => Some(*j),
+        Edit::Delete(_) => None,
+    });
+    let old_len = edits
+        .iter()
+        .filter(|e| !matches!(e, Edit::Insert(_)))
+        .count();
+    let new_len = edits
+        .iter()
+        .filter(|e| !matches!(e, Edit::Delete(_)))
+        .count();
+
+    out.push_str(&format!(
+        "@@ -{} +{} @@\n",
+        hunk_range(old_start, old_len, a.len()),
+        hunk_range(new_start, new_len, b.len())
+    ));
+    for edit in edits {
+        let (sign, line) = match *edit {
+            Edit::Equal(i, _) => (' ', a[i]),
+            Edit::Delete(i) => ('-', a[i]),
+            Edit::Insert(j) => ('+', b[j]),
+        };
+        out.push(sign);
+        out.push_str(line);
+        if !line.ends_with('\n') {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    }
+}
+
+/// `start,len` in the 1-based form used by hunk headers. An empty range
+/// points at the line before it.
+fn hunk_range(start: Option<usize>, len: usize, total: usize) -> String {
+    let start = match (start, len) {
+        (_, 0) => start.unwrap_or(total),
+        (Some(s), _) => s + 1,
+        (None, _) => 1,
+    };
+    if len == 1 {
+        start.to_string()
+    } else {
+        format!("{},{}", start, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_is_empty() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "a", "b"), "");
+    }
+
+    #[test]
+    fn test_single_change() {
+        let diff = unified_diff("select 1\n", "SELECT\n    1\n", "q.sql", "q.sql");
+        assert_eq!(
+            diff,
+            "--- q.sql\n+++ q.sql\n@@ -1 +1,2 @@\n-select 1\n+SELECT\n+    1\n"
+        );
+    }
+
+    #[test]
+    fn test_context_lines_and_separate_hunks() {
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n";
+        let new = "1\nx\n3\n4\n5\n6\n7\n8\n9\n10\ny\n12\n";
+        let diff = unified_diff(old, new, "a", "b");
+        assert_eq!(
+            diff,
+            "--- a\n+++ b\n\
+             @@ -1,5 +1,5 @@\n 1\n-2\n+x\n 3\n 4\n 5\n\
+             @@ -8,5 +8,5 @@\n 8\n 9\n 10\n-11\n+y\n 12\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_trailing_newline() {
+        let diff = unified_diff("select 1", "SELECT 1\n", "a", "b");
+        assert_eq!(
+            diff,
+            "--- a\n+++ b\n@@ -1 +1 @@\n-select 1\n\\ No newline at end of file\n+SELECT 1\n"
+        );
+    }
+
+    #[test]
+    fn test_insert_into_empty() {
+        let diff = unified_diff("", "a\n", "a", "b");
+        assert_eq!(diff, "--- a\n+++ b\n@@ -0,0 +1 @@\n+a\n");
+    }
+}
@@ -0,0 +1,224 @@
+//! `doctor` subcommand: list the places where the formatter is likely to
+//! produce surprising output, so users know what to expect before adopting
+//! it on a codebase.
+
+use std::ops::Range;
+use std::path::PathBuf;
+
+use clap::Args;
+use rs_sql_indent::token::Token;
+use rs_sql_indent::{Dialect, lexer};
+
+use super::diagnostic::line_column;
+
+#[derive(Args)]
+pub struct DoctorArgs {
+    /// SQL files or directories to scan. Reads stdin when none are given
+    pub paths: Vec<PathBuf>,
+}
+
+/// Something in the input the formatter does not properly understand.
+#[derive(Debug, PartialEq)]
+pub struct Finding {
+    /// Byte offset in the input
+    pub offset: usize,
+    pub message: String,
+}
+
+/// Malformed literals and parentheses, characters the lexer only passes
+/// through, and syntax that another dialect would recognize.
+pub fn scan(input: &str, dialect: Dialect) -> Vec<Finding> {
+    let mut findings: Vec<Finding> = lexer::diagnostics(input, dialect)
+        .into_iter()
+        .map(|d| Finding {
+            offset: d.offset,
+            message: d.message.to_string(),
+        })
+        .collect();
+
+    let tokens = lexer::tokenize_with_offsets(input, dialect);
+    let mut i = 0;
+    while i < tokens.len() {
+        let (token, range) = &tokens[i];
+        let next = tokens.get(i + 1).map(|(t, _)| t);
+        let message = match token {
+            Token::Operator(op) | Token::Error(op) => fallback_message(op, next, dialect),
+            Token::Identifier(word)
+                if word.eq_ignore_ascii_case("GO") && alone_on_line(input, range) =>
+            {
+                Some("`GO` batch separator is only recognized with --dialect tsql".to_string())
+            }
+            Token::Identifier(word)
+                if word.eq_ignore_ascii_case("DELIMITER")
+                    && dialect != Dialect::Mysql
+                    && at_line_start(input, range) =>
+            {
+                Some("`DELIMITER` commands are only recognized with --dialect mysql".to_string())
+            }
+            _ => None,
+        };
+        if let Some(message) = message {
+            findings.push(Finding {
+                offset: range.start,
+                message,
+            });
+            // Report a quoted name or a `$$` string once, not per token
+            i = match (token, next) {
+                (Token::Error("`"), _) => last_of(&tokens, i + 1, &[Token::Error("`")]),
+                (Token::Error("$"), Some(Token::Error("$"))) => {
+                    last_of(&tokens, i + 2, &[Token::Error("$"), Token::Error("$")])
+                }
+                _ => i,
+            };
+        } else if matches!(token, Token::Operator("/"))
+            && dialect != Dialect::Oracle
+            && alone_on_line(input, range)
+        {
+            findings.push(Finding {
+                offset: range.start,
+                message: "`/` terminator line is only recognized with --dialect oracle".to_string(),
+            });
+        }
+        i += 1;
+    }
+
+    findings.sort_by_key(|f| f.offset);
+    findings
+}
+
+/// Why a character the lexer fell back on is a problem, if it is one.
+fn fallback_message(op: &str, next: Option<&Token<'_>>, dialect: Dialect) -> Option<String> {
+    let message = match op {
+        "`" => format!(
+            "backtick-quoted identifiers are not recognized by the {} dialect; \
+             try --dialect mysql, --dialect bigquery or --dialect spark",
+            dialect
+        ),
+        "[" => "bracket-quoted identifiers are only recognized with --dialect tsql; \
+             elsewhere brackets are formatted as separate tokens"
+            .to_string(),
+        "$" => match next {
+            Some(Token::Error("$")) => {
+                "dollar-quoted strings are only recognized with --dialect postgres or \
+                 --dialect snowflake; \
+                 the body is formatted as SQL"
+                    .to_string()
+            }
+            _ => "unknown character `$`".to_string(),
+        },
+        "@" => format!(
+            "variables are not recognized by the {} dialect; try --dialect tsql or --dialect bigquery",
+            dialect
+        ),
+        "#" => {
+            "`#` comments are not recognized; the rest of the line is formatted as SQL".to_string()
+        }
+        "\\" => format!(
+            "backslash escapes are not recognized by the {} dialect; \
+             try --dialect mysql, --dialect bigquery or --dialect spark",
+            dialect
+        ),
+        _ if !op.is_ascii() => format!("unknown character `{}`", op),
+        _ => return None,
+    };
+    Some(message)
+}
+
+/// Index of the last token of the first `closing` sequence at or after
+/// `from`, or the end of `tokens`.
+fn last_of(tokens: &[(Token<'_>, Range<usize>)], from: usize, closing: &[Token<'_>]) -> usize {
+    (from..tokens.len())
+        .find(|&j| {
+            tokens[j..]
+                .iter()
+                .map(|(t, _)| t)
+                .take(closing.len())
+                .eq(closing)
+        })
+        .map_or(tokens.len(), |j| j + closing.len() - 1)
+}
+
+fn alone_on_line(input: &str, range: &Range<usize>) -> bool {
+    let after = input[range.end..].split('\n').next().unwrap_or("");
+    at_line_start(input, range) && after.trim().is_empty()
+}
+
+fn at_line_start(input: &str, range: &Range<usize>) -> bool {
+    let before = input[..range.start].rsplit('\n').next().unwrap_or("");
+    before.trim().is_empty()
+}
+
+/// One `name:line:column: message` line per finding, or a note that there
+/// were none.
+pub fn render(name: &str, input: &str, findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return format!("{}: no problems found\n", name);
+    }
+    let mut out = String::new();
+    for finding in findings {
+        let (line, column) = line_column(input, finding.offset);
+        out.push_str(&format!(
+            "{}:{}:{}: {}\n",
+            name, line, column, finding.message
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(input: &str, dialect: Dialect) -> Vec<(usize, String)> {
+        scan(input, dialect)
+            .into_iter()
+            .map(|f| (f.offset, f.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_dialect_specific_syntax() {
+        let found = messages(
+            "select `a`, $1 from t where x = @v\nGO\nselect $$ b $$",
+            Dialect::Generic,
+        );
+        let offsets: Vec<usize> = found.iter().map(|(offset, _)| *offset).collect();
+        // `$1` and `@v` are placeholders the formatter keeps together
+        assert_eq!(offsets, vec![7, 35, 45]);
+        assert!(found[0].1.starts_with("backtick-quoted identifiers"));
+        assert!(found[1].1.starts_with("`GO` batch separator"));
+        assert!(found[2].1.starts_with("dollar-quoted strings"));
+        assert!(
+            messages("select @v", Dialect::Postgres)[0]
+                .1
+                .starts_with("variables are not recognized by the postgres")
+        );
+
+        assert_eq!(
+            messages("select `a` from t\nGO", Dialect::Bigquery).len(),
+            1
+        );
+        assert!(messages("select @v\nGO", Dialect::Tsql).is_empty());
+        assert!(
+            messages("DELIMITER //\nselect 1//", Dialect::Generic)[0]
+                .1
+                .starts_with("`DELIMITER` commands")
+        );
+        assert!(messages("DELIMITER //\nselect 1//", Dialect::Mysql).is_empty());
+    }
+
+    #[test]
+    fn test_render() {
+        let input = "select a\nfrom t where b = «c» and (d";
+        assert_eq!(
+            render("q.sql", input, &scan(input, Dialect::Generic)),
+            "q.sql:2:18: unknown character `«`\n\
+             q.sql:2:20: unknown character `»`\n\
+             q.sql:2:26: unclosed parenthesis\n"
+        );
+        assert_eq!(
+            render("q.sql", "select 1", &[]),
+            "q.sql: no problems found\n"
+        );
+    }
+}
@@ -0,0 +1,333 @@
+//! `--embedded yaml` and `--embedded json`: format SQL stored as values of
+//! configuration documents (Grafana dashboards, dbt schema files, k8s
+//! config maps) under keys matching `--sql-key`, leaving the rest of the
+//! document as written.
+//!
+//! YAML values become literal block scalars indented under their key:
+//!
+//! ```text
+//! rawSql: "select a from t"       rawSql: |-
+//!                            =>     SELECT
+//!                                       a
+//!                                   ...
+//! ```
+//!
+//! JSON values stay strings, with the line breaks escaped.
+
+use std::path::Path;
+
+use super::embedded::looks_like_sql;
+use super::glob::Pattern;
+use super::json::{self, Value};
+use super::report::json_string;
+
+/// Keys formatted when no `--sql-key` is given.
+pub const DEFAULT_KEYS: &[&str] = &["rawSql", "query", "sql"];
+
+fn key_matches(keys: &[Pattern], key: &str) -> bool {
+    keys.iter().any(|pattern| pattern.matches(Path::new(key)))
+}
+
+/// Whether `text` is SQL without dashboard variables or macros (`$host`,
+/// `${host}`, `$__timeFilter(...)`, `[[host]]`), which the SQL lexer would
+/// split apart.
+fn is_plain_sql(text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let has_variable = text.contains("[[")
+        || bytes.windows(2).any(|pair| match pair {
+            [b'$', c] => c.is_ascii_alphabetic() || *c == b'_' || *c == b'{',
+            _ => false,
+        });
+    looks_like_sql(text) && !has_variable
+}
+
+/// `format`ted SQL with each non-empty line indented by `indent`.
+fn indented(formatted: &str, indent: &str) -> String {
+    let mut out = String::with_capacity(formatted.len());
+    for line in formatted.trim_end().lines() {
+        if !line.is_empty() {
+            out.push_str(indent);
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// Reformat the values of matching keys in a YAML document. Block scalars
+/// (`|`, `>`) keep their indentation, folded ones turning literal so the
+/// line breaks survive; single-line scalars are rewritten as `|-` blocks.
+/// Flow collections, multi-line flow scalars and values that are not SQL
+/// are left alone.
+pub fn format_yaml<F>(source: &str, keys: &[Pattern], format: F) -> String
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        i += 1;
+        let Some(entry) = yaml_entry(line).filter(|e| key_matches(keys, e.key)) else {
+            out.push_str(line);
+            continue;
+        };
+        let (value, comment) = split_comment(&line[entry.value_start..]);
+
+        if let Some(indicator) = value.strip_prefix(['|', '>']) {
+            if !indicator
+                .chars()
+                .all(|c| matches!(c, '-' | '+' | '0'..='9'))
+            {
+                out.push_str(line);
+                continue;
+            }
+            // The block runs while lines are blank or indented past the key;
+            // trailing blank lines are copied through as they are
+            let start = i;
+            let mut end = i;
+            while i < lines.len()
+                && (lines[i].trim().is_empty() || indent_of(lines[i]) > entry.column)
+            {
+                if !lines[i].trim().is_empty() {
+                    end = i + 1;
+                }
+                i += 1;
+            }
+            i = end;
+            let body = lines[start..end].concat();
+            let replacement = body
+                .lines()
+                .find(|l| !l.trim().is_empty())
+                .filter(|_| is_plain_sql(&body))
+                .and_then(|first| {
+                    let indent = &first[..indent_of(first)];
+                    format(&body).ok().map(|f| indented(&f, indent))
+                });
+            match replacement {
+                Some(text) => {
+                    let head = &line[..entry.value_start];
+                    let indicator = format!("|{}", indicator);
+                    out.push_str(head.trim_end());
+                    out.push(' ');
+                    out.push_str(&indicator);
+                    out.push_str(comment);
+                    out.push('\n');
+                    out.push_str(&text);
+                }
+                None => {
+                    out.push_str(line);
+                    out.push_str(&body);
+                }
+            }
+            continue;
+        }
+
+        let continued = lines
+            .get(i)
+            .is_some_and(|next| !next.trim().is_empty() && indent_of(next) > entry.column);
+        let replacement = scalar_text(value)
+            .filter(|sql| !continued && is_plain_sql(sql))
+            .and_then(|sql| format(&sql).ok());
+        match replacement {
+            Some(formatted) => {
+                out.push_str(line[..entry.value_start].trim_end());
+                out.push_str(" |-");
+                out.push_str(comment);
+                out.push('\n');
+                out.push_str(&indented(&formatted, &" ".repeat(entry.column + 2)));
+            }
+            None => out.push_str(line),
+        }
+    }
+    out
+}
+
+/// A `key: value` line of a YAML mapping.
+struct Entry<'a> {
+    /// The key, unquoted
+    key: &'a str,
+    /// Column the key starts at, after any `- ` of a sequence item
+    column: usize,
+    /// Byte offset in the line just past the `:`
+    value_start: usize,
+}
+
+fn yaml_entry(line: &str) -> Option<Entry<'_>> {
+    let mut column = indent_of(line);
+    let mut rest = &line[column..];
+    while let Some(item) = rest.strip_prefix("- ") {
+        let spaces = item.len() - item.trim_start_matches(' ').len();
+        column += 2 + spaces;
+        rest = &item[spaces..];
+    }
+    let (key, after_key) = match rest.as_bytes().first()? {
+        quote @ (b'"' | b'\'') => {
+            let close = rest[1..].find(*quote as char)? + 1;
+            (&rest[1..close], &rest[close + 1..])
+        }
+        b'#' => return None,
+        _ => {
+            let colon = rest
+                .char_indices()
+                .find(|&(i, c)| c == ':' && rest[i + 1..].starts_with([' ', '\t', '\r', '\n']))
+                .map(|(i, _)| i)
+                .or_else(|| rest.trim_end().strip_suffix(':').map(str::len))?;
+            (&rest[..colon], &rest[colon..])
+        }
+    };
+    let after_colon = after_key.strip_prefix(':')?;
+    Some(Entry {
+        key,
+        column,
+        value_start: line.len() - after_colon.len(),
+    })
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+/// The value of a line and its trailing ` # comment` (with the space).
+fn split_comment(value: &str) -> (&str, &str) {
+    let value = value.trim_end_matches(['\r', '\n']);
+    let mut quote = None;
+    for (i, c) in value.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '#') if i == 0 || value[..i].ends_with([' ', '\t']) => {
+                let comment_start = value[..i].trim_end().len();
+                return (value[..i].trim(), &value[comment_start..]);
+            }
+            _ => {}
+        }
+    }
+    (value.trim(), "")
+}
+
+/// The text of a single-line plain, single- or double-quoted scalar.
+fn scalar_text(value: &str) -> Option<String> {
+    match value.as_bytes().first()? {
+        b'"' => match json::parse(value) {
+            Ok(Value::String(text)) => Some(text),
+            _ => None,
+        },
+        b'\'' => {
+            let inner = value.strip_prefix('\'')?.strip_suffix('\'')?;
+            // A lone quote inside means the scalar ended earlier
+            (!inner.replace("''", "").contains('\'')).then(|| inner.replace("''", "'"))
+        }
+        b'[' | b'{' | b'&' | b'*' | b'!' | b'|' | b'>' => None,
+        _ => Some(value.to_string()),
+    }
+}
+
+/// Reformat the string values of matching keys in a JSON document, keeping
+/// everything else byte for byte.
+pub fn format_json<F>(source: &str, keys: &[Pattern], format: F) -> String
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    let bytes = source.as_bytes();
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != b'"' {
+            i += 1;
+            continue;
+        }
+        let key_end = string_end(bytes, i);
+        let colon = skip_whitespace(bytes, key_end);
+        if bytes.get(colon) != Some(&b':') {
+            i = key_end;
+            continue;
+        }
+        let value_start = skip_whitespace(bytes, colon + 1);
+        let matched = matches!(json::parse(&source[i..key_end]), Ok(Value::String(key)) if key_matches(keys, &key));
+        if !matched || bytes.get(value_start) != Some(&b'"') {
+            i = value_start;
+            continue;
+        }
+        let value_end = string_end(bytes, value_start);
+        if let Ok(Value::String(sql)) = json::parse(&source[value_start..value_end])
+            && is_plain_sql(&sql)
+            && let Ok(formatted) = format(&sql)
+        {
+            out.push_str(&source[copied..value_start]);
+            out.push_str(&json_string(formatted.trim_end()));
+            copied = value_end;
+        }
+        i = value_end;
+    }
+    out.push_str(&source[copied..]);
+    out
+}
+
+/// Index just past the closing quote of the JSON string opening at `start`.
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i] != b'"' {
+        i += if bytes[i] == b'\\' { 2 } else { 1 };
+    }
+    (i + 1).min(bytes.len())
+}
+
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while bytes.get(i).is_some_and(|b| b.is_ascii_whitespace()) {
+        i += 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_sql_indent::{FormatOptions, format_sql};
+
+    fn keys() -> Vec<Pattern> {
+        DEFAULT_KEYS.iter().map(|k| Pattern::new(k)).collect()
+    }
+
+    fn format(sql: &str) -> Result<String, String> {
+        Ok(format!("{}\n", format_sql(sql, &FormatOptions::default())))
+    }
+
+    #[test]
+    fn test_yaml() {
+        let source = "panels:\n  - title: CPU\n    rawSql: \"select host from metrics\"  # per host\n\
+                      \x20   other: select a from t\n    targets:\n      - query: |\n          select a\n\
+                      \x20           from t\n\n        refId: A\n      - query: SELECT $__timeFilter(time) FROM t\n\
+                      \x20     - query: >-\n          select 'it''s'\n";
+        assert_eq!(
+            format_yaml(source, &keys(), format),
+            "panels:\n  - title: CPU\n    rawSql: |-  # per host\n      SELECT\n          host\n\
+             \x20     FROM\n          metrics\n    other: select a from t\n    targets:\n\
+             \x20     - query: |\n          SELECT\n              a\n          FROM\n              t\n\n\
+             \x20       refId: A\n      - query: SELECT $__timeFilter(time) FROM t\n\
+             \x20     - query: |-\n          SELECT\n              'it''s'\n"
+        );
+    }
+
+    #[test]
+    fn test_yaml_scalars() {
+        assert_eq!(scalar_text("'it''s'"), Some("it's".to_string()));
+        assert_eq!(scalar_text("\"a\\nb\""), Some("a\nb".to_string()));
+        assert_eq!(scalar_text("[a, b]"), None);
+        assert_eq!(split_comment("select 1 # note\n"), ("select 1", " # note"));
+        assert_eq!(split_comment("'a # b'\n"), ("'a # b'", ""));
+    }
+
+    #[test]
+    fn test_json() {
+        let source = "{\"targets\": [{\"rawSql\": \"select a from t\", \"refId\": \"A\"}],\n \
+                      \"query\": 5, \"sql\": \"not sql\", \"title\": \"select b from u\"}";
+        assert_eq!(
+            format_json(source, &keys(), format),
+            "{\"targets\": [{\"rawSql\": \"SELECT\\n    a\\nFROM\\n    t\", \"refId\": \"A\"}],\n \
+             \"query\": 5, \"sql\": \"not sql\", \"title\": \"select b from u\"}"
+        );
+    }
+}
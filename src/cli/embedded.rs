@@ -0,0 +1,589 @@
+//! `--embedded`: format SQL held in multi-line string literals of Python,
+//! Ruby, Go and Rust source files, or in values of YAML and JSON documents
+//! (see [`document`](super::document)), leaving the rest of the file
+//! untouched.
+//!
+//! Only literals whose text starts on the line after the opening delimiter
+//! are rewritten; the SQL keeps the indentation of its first line and the
+//! closing delimiter stays where it was:
+//!
+//! ```text
+//!     query = """
+//!         SELECT ...
+//!     """
+//! ```
+//!
+//! The queries of sqlx's `query!` macros are rewritten when written on one
+//! line too. SQL that formats to several lines then moves onto lines of its
+//! own, one level deeper than the macro call, in the layout above.
+
+use std::ops::Range;
+
+use clap::ValueEnum;
+use rs_sql_indent::Token;
+use rs_sql_indent::lexer;
+
+use super::document;
+use super::glob::Pattern;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Host {
+    /// `"""..."""` and `'''...'''` (f-strings and `.format` templates are
+    /// skipped)
+    Python,
+    /// Heredocs: `<<~SQL`, `<<-SQL`, `<<SQL`
+    Ruby,
+    /// Raw strings: `` `...` ``
+    Go,
+    /// Raw strings `r#"..."#` and strings without escapes, as passed to
+    /// `sqlx::query!`, `query_as!` or diesel's `sql_query`; single-line ones
+    /// only in sqlx's macros
+    Rust,
+    /// Values of the `--sql-key` keys, written back as block scalars
+    Yaml,
+    /// String values of the `--sql-key` keys
+    Json,
+}
+
+impl Host {
+    /// File extensions searched for when a directory is given.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Host::Python => &["py"],
+            Host::Ruby => &["rb"],
+            Host::Go => &["go"],
+            Host::Rust => &["rs"],
+            Host::Yaml => &["yaml", "yml"],
+            Host::Json => &["json"],
+        }
+    }
+}
+
+const SQL_STARTERS: &[&str] = &[
+    "SELECT", "WITH", "INSERT", "UPDATE", "DELETE", "MERGE", "CREATE", "ALTER", "DROP",
+];
+
+/// Reformat every SQL literal in `source` with `format`, or in documents
+/// the values of the keys matching `keys`. Literals that are not SQL, use
+/// host interpolation, or fail to format are left as they are.
+pub fn format_source<F>(host: Host, source: &str, keys: &[Pattern], format: F) -> String
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    match host {
+        Host::Yaml => return document::format_yaml(source, keys, format),
+        Host::Json => return document::format_json(source, keys, format),
+        _ => {}
+    }
+    let mut output = String::with_capacity(source.len());
+    let mut copied = 0;
+    for range in find_literals(host, source) {
+        let content = &source[range.clone()];
+        let replacement = if content.contains('\n') {
+            reformat_literal(host, content, &format)
+        } else {
+            let line_start = source[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            let line = &source[line_start..range.start];
+            let indent = &line[..line.len() - line.trim_start().len()];
+            reformat_inline(host, content, indent, &format)
+        };
+        let Some(replacement) = replacement else {
+            continue;
+        };
+        output.push_str(&source[copied..range.start]);
+        output.push_str(&replacement);
+        copied = range.end;
+    }
+    output.push_str(&source[copied..]);
+    output
+}
+
+fn reformat_literal<F>(host: Host, content: &str, format: &F) -> Option<String>
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    // `content` runs from just after the opening line break to the start
+    // of the closing delimiter (or terminator line)
+    let body_end = content.rfind('\n').map_or(0, |i| i + 1);
+    let (body, closing) = content.split_at(body_end);
+    if !closing.trim().is_empty() || !looks_like_sql(body) || has_interpolation(host, body) {
+        return None;
+    }
+
+    let indent: String = body
+        .lines()
+        .find(|line| !line.trim().is_empty())?
+        .chars()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    let formatted = format(body).ok()?;
+
+    let mut replaced = String::with_capacity(content.len());
+    for line in formatted.trim_end().lines() {
+        if !line.is_empty() {
+            replaced.push_str(&indent);
+        }
+        replaced.push_str(line);
+        replaced.push('\n');
+    }
+    replaced.push_str(closing);
+    Some(replaced)
+}
+
+/// Reformat a literal written on one line, whose line is indented by
+/// `indent`. SQL that still fits on one line stays there.
+fn reformat_inline<F>(host: Host, content: &str, indent: &str, format: &F) -> Option<String>
+where
+    F: Fn(&str) -> Result<String, String>,
+{
+    if !looks_like_sql(content) || has_interpolation(host, content) {
+        return None;
+    }
+    let formatted = format(content).ok()?;
+    let formatted = formatted.trim_end();
+    if !formatted.contains('\n') {
+        return Some(formatted.to_string());
+    }
+
+    let mut replaced = String::from("\n");
+    for line in formatted.lines() {
+        if !line.is_empty() {
+            replaced.push_str(indent);
+            replaced.push_str("    ");
+        }
+        replaced.push_str(line);
+        replaced.push('\n');
+    }
+    replaced.push_str(indent);
+    Some(replaced)
+}
+
+pub fn looks_like_sql(text: &str) -> bool {
+    let first = text
+        .trim_start()
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or("");
+    SQL_STARTERS.iter().any(|kw| first.eq_ignore_ascii_case(kw))
+}
+
+/// Placeholders the SQL lexer would split apart (`%d`, `#{x}`, `$1`,
+/// `:name`): formatting could break the host program, so such literals are
+/// skipped. Python's DB-API `%s` and `%(name)s` lex as placeholders, and
+/// its `.format` templates are never picked up. Rust literals are bound by
+/// sqlx and diesel, whose `$1`, `?` and `:name` lex as placeholders; only
+/// `format!` arguments count there.
+fn has_interpolation(host: Host, text: &str) -> bool {
+    let bytes = text.as_bytes();
+    let host_specific = match host {
+        Host::Python => has_percent_format(text),
+        Host::Ruby => text.contains("#{"),
+        Host::Go => false,
+        Host::Rust => return text.contains('{'),
+        Host::Yaml | Host::Json => false,
+    };
+    host_specific
+        || bytes.windows(2).enumerate().any(|(i, pair)| match pair {
+            [b'$', c] => c.is_ascii_digit(),
+            [b':', c] => {
+                (c.is_ascii_alphabetic() || *c == b'_') && (i == 0 || bytes[i - 1] != b':')
+            }
+            _ => false,
+        })
+}
+
+/// Whether `text` uses `%` formatting beyond the DB-API placeholders: a `%`
+/// outside SQL strings written against what follows it, such as `%d` or an
+/// escaped `%%`, which formatting could split.
+fn has_percent_format(text: &str) -> bool {
+    lexer::tokenize(text).windows(2).any(|pair| match pair {
+        [Token::Operator(op), next] => op.contains('%') && !matches!(next, Token::Whitespace(_)),
+        _ => false,
+    })
+}
+
+/// Byte ranges of the text of every multi-line literal in `source`: from
+/// the line after the opening delimiter up to the closing one. The text of
+/// single-line sqlx queries runs between the delimiters.
+fn find_literals(host: Host, source: &str) -> Vec<Range<usize>> {
+    match host {
+        Host::Python => python_literals(source),
+        Host::Ruby => ruby_heredocs(source),
+        Host::Go => go_raw_strings(source),
+        Host::Rust => rust_strings(source),
+        Host::Yaml | Host::Json => Vec::new(),
+    }
+}
+
+/// Index just past the line break following `pos`, if only whitespace
+/// comes before it.
+fn after_line_break(source: &str, pos: usize) -> Option<usize> {
+    let rest = &source[pos..];
+    let newline = rest.find('\n')?;
+    rest[..newline]
+        .trim()
+        .is_empty()
+        .then_some(pos + newline + 1)
+}
+
+fn python_literals(source: &str) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut literals = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'#' => i = source[i..].find('\n').map_or(bytes.len(), |n| i + n),
+            quote @ (b'"' | b'\'') => {
+                let triple = bytes.get(i..i + 3) == Some(&[quote; 3][..]);
+                let delimiter = if triple { 3 } else { 1 };
+                let start = i + delimiter;
+                let end = find_closing(bytes, start, quote, delimiter);
+                let prefix = source[..i]
+                    .chars()
+                    .rev()
+                    .take_while(|c| c.is_ascii_alphabetic())
+                    .collect::<String>();
+                // `{name}` fields of f-strings and `.format` templates would
+                // be split apart
+                let template = prefix.contains(['f', 'F'])
+                    || source
+                        .get(end + delimiter..)
+                        .is_some_and(|rest| rest.trim_start().starts_with(".format("));
+                if triple
+                    && !template
+                    && let Some(content_start) = after_line_break(source, start)
+                    && content_start <= end
+                {
+                    literals.push(content_start..end);
+                }
+                i = end + delimiter;
+            }
+            _ => i += 1,
+        }
+    }
+    literals
+}
+
+/// Start of the closing delimiter of a Python string opened before `start`.
+fn find_closing(bytes: &[u8], start: usize, quote: u8, delimiter: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\n' if delimiter == 1 => return i,
+            b if b == quote && bytes[i..].iter().take(delimiter).all(|&c| c == quote) => {
+                return i;
+            }
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn ruby_heredocs(source: &str) -> Vec<Range<usize>> {
+    let mut literals = Vec::new();
+    let mut search = 0;
+    while let Some(found) = source[search..].find("<<") {
+        let opener = search + found;
+        search = opener + 2;
+        let rest = &source[search..];
+        let (indented, rest) = match rest.strip_prefix(['~', '-']) {
+            Some(stripped) => (true, stripped),
+            None => (false, rest),
+        };
+        let (quoted, rest) = match rest.strip_prefix(['\'', '"']) {
+            Some(stripped) => (true, stripped),
+            None => (false, rest),
+        };
+        let id_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let id = &rest[..id_len];
+        if id.is_empty() || !id.starts_with(|c: char| c.is_ascii_uppercase() || c == '_') {
+            continue;
+        }
+        // `<<'SQL'` / `<<"SQL"` need their closing quote
+        if quoted && !rest[id_len..].starts_with(['\'', '"']) {
+            continue;
+        }
+
+        let Some(newline) = source[opener..].find('\n') else {
+            break;
+        };
+        let content_start = opener + newline + 1;
+        let mut line_start = content_start;
+        while line_start < source.len() {
+            let line_end = source[line_start..]
+                .find('\n')
+                .map_or(source.len(), |n| line_start + n);
+            let line = &source[line_start..line_end];
+            let line = if indented {
+                line.trim()
+            } else {
+                line.trim_end()
+            };
+            if line == id {
+                literals.push(content_start..line_start);
+                search = line_end;
+                break;
+            }
+            line_start = line_end + 1;
+        }
+    }
+    literals
+}
+
+fn go_raw_strings(source: &str) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut literals = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            }
+            b'"' | b'\'' => {
+                let quote = bytes[i];
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote && bytes[i] != b'\n' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i += 1;
+            }
+            b'`' => {
+                let start = i + 1;
+                let end = source[start..].find('`').map_or(bytes.len(), |n| start + n);
+                if let Some(content_start) = after_line_break(source, start)
+                    && content_start <= end
+                {
+                    literals.push(content_start..end);
+                }
+                i = end + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    literals
+}
+
+/// Raw strings, and plain strings without escapes, which formatting could
+/// move or break. Comments and character literals are skipped over.
+fn rust_strings(source: &str) -> Vec<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut literals = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let in_word = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_');
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = source[i..].find('\n').map_or(bytes.len(), |n| i + n);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let mut depth = 0;
+                while i < bytes.len() {
+                    match (bytes[i], bytes.get(i + 1)) {
+                        (b'/', Some(b'*')) => {
+                            depth += 1;
+                            i += 2;
+                        }
+                        (b'*', Some(b'/')) => {
+                            depth -= 1;
+                            i += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => i += 1,
+                    }
+                }
+            }
+            // A character literal, or else a lifetime
+            b'\'' => {
+                i += 1;
+                if bytes.get(i) == Some(&b'\\') {
+                    i = source[i + 2..]
+                        .find('\'')
+                        .map_or(bytes.len(), |n| i + 2 + n + 1);
+                } else if let Some(c) = source[i..].chars().next()
+                    && bytes.get(i + c.len_utf8()) == Some(&b'\'')
+                {
+                    i += c.len_utf8() + 1;
+                }
+            }
+            b'r' if !in_word => {
+                let hashes = bytes[i + 1..].iter().take_while(|&&b| b == b'#').count();
+                let start = i + 1 + hashes + 1;
+                if bytes.get(start - 1) != Some(&b'"') {
+                    i += 1;
+                    continue;
+                }
+                let closing = format!("\"{}", "#".repeat(hashes));
+                let end = source[start..]
+                    .find(&closing)
+                    .map_or(bytes.len(), |n| start + n);
+                if let Some(content_start) = after_line_break(source, start)
+                    && content_start <= end
+                {
+                    literals.push(content_start..end);
+                } else if is_sqlx_query(source, i) && !source[start..end].contains('\n') {
+                    literals.push(start..end);
+                }
+                i = end + closing.len();
+            }
+            b'"' => {
+                let start = i + 1;
+                let mut end = start;
+                while end < bytes.len() && bytes[end] != b'"' {
+                    end += if bytes[end] == b'\\' { 2 } else { 1 };
+                }
+                let end = end.min(bytes.len());
+                let text = &source[start..end];
+                if text.contains('\\') {
+                    // Escapes would change meaning if the text moved
+                } else if let Some(content_start) = after_line_break(source, start)
+                    && content_start <= end
+                {
+                    literals.push(content_start..end);
+                } else if is_sqlx_query(source, i) && !text.contains('\n') {
+                    literals.push(start..end);
+                }
+                i = end + 1;
+            }
+            _ => i += 1,
+        }
+    }
+    literals
+}
+
+/// Whether the string literal starting at `start` is the query of an sqlx
+/// macro: the first argument of `query!` or `query_scalar!`, or the second
+/// of `query_as!`, `_unchecked` variants included.
+fn is_sqlx_query(source: &str, start: usize) -> bool {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let before = source[..start].trim_end();
+    let (before, macros): (&str, &[&str]) = match before.strip_suffix(',') {
+        Some(rest) => {
+            let rest = rest.trim_end();
+            let record = rest.trim_end_matches(|c: char| is_word(c) || c == ':');
+            if record.len() == rest.len() {
+                return false;
+            }
+            (record, &["query_as!", "query_as_unchecked!"])
+        }
+        None => (
+            before,
+            &[
+                "query!",
+                "query_unchecked!",
+                "query_scalar!",
+                "query_scalar_unchecked!",
+            ],
+        ),
+    };
+    let Some(call) = before.trim_end().strip_suffix('(') else {
+        return false;
+    };
+    let call = call.trim_end();
+    macros.iter().any(|name| {
+        call.strip_suffix(name)
+            .is_some_and(|path| !path.ends_with(is_word))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upper(sql: &str) -> Result<String, String> {
+        Ok(sql
+            .split_whitespace()
+            .map(str::to_uppercase)
+            .collect::<Vec<_>>()
+            .join("\n  "))
+    }
+
+    #[test]
+    fn test_python() {
+        let source = "def f():\n    q = \"\"\"\n        select a\n          from t\n    \"\"\"\n    s = '''not sql\n'''\n    x = f\"\"\"\n    select {a}\n\"\"\"\n";
+        assert_eq!(
+            format_source(Host::Python, source, &[], upper),
+            "def f():\n    q = \"\"\"\n        SELECT\n          A\n          FROM\n          T\n    \"\"\"\n    s = '''not sql\n'''\n    x = f\"\"\"\n    select {a}\n\"\"\"\n"
+        );
+    }
+
+    #[test]
+    fn test_python_placeholders() {
+        let source = "q = \"\"\"\n    select a from t where b = %(b)s\n\"\"\"\n\
+                      r = \"\"\"\n    select {col} from t\n\"\"\".format(col=c)\n";
+        assert_eq!(
+            format_source(Host::Python, source, &[], upper),
+            "q = \"\"\"\n    SELECT\n      A\n      FROM\n      T\n      WHERE\n      B\n      =\n      \
+             %(B)S\n\"\"\"\nr = \"\"\"\n    select {col} from t\n\"\"\".format(col=c)\n"
+        );
+    }
+
+    #[test]
+    fn test_ruby_heredoc() {
+        let source = "sql = <<~SQL\n  select a from t\nSQL\nother = <<~SQL\n  select #{x}\nSQL\n";
+        assert_eq!(
+            format_source(Host::Ruby, source, &[], upper),
+            "sql = <<~SQL\n  SELECT\n    A\n    FROM\n    T\nSQL\nother = <<~SQL\n  select #{x}\nSQL\n"
+        );
+    }
+
+    #[test]
+    fn test_go_raw_string() {
+        let source =
+            "const q = `\n\tselect a from t\n`\nvar s = \"`\"\nconst p = `\n\tselect $1\n`\n";
+        assert_eq!(
+            format_source(Host::Go, source, &[], upper),
+            "const q = `\n\tSELECT\n\t  A\n\t  FROM\n\t  T\n`\nvar s = \"`\"\nconst p = `\n\tselect $1\n`\n"
+        );
+    }
+
+    #[test]
+    fn test_rust_strings() {
+        let source = "fn f() {\n    let c = '\"'; // \"\n    let row = sqlx::query!(\n        r#\"\n        select id from users where id = $1\n        \"#,\n        id\n    );\n    let s = format!(r\"\n        select {} from t\n    \", c);\n    let e = \"\n        select a\\tb\n    \";\n    let q = \"\n      select a from t\n    \";\n}\n";
+        assert_eq!(
+            format_source(Host::Rust, source, &[], upper),
+            "fn f() {\n    let c = '\"'; // \"\n    let row = sqlx::query!(\n        r#\"\n        SELECT\n          ID\n          FROM\n          USERS\n          WHERE\n          ID\n          =\n          $1\n        \"#,\n        id\n    );\n    let s = format!(r\"\n        select {} from t\n    \", c);\n    let e = \"\n        select a\\tb\n    \";\n    let q = \"\n      SELECT\n        A\n        FROM\n        T\n    \";\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_rust_single_line_sqlx_queries() {
+        let source = "    let a = sqlx::query!(\"select id from t where id = $1\", id);\n    \
+                      let b = query_as!(User, r#\"select 1\"#);\n    \
+                      let c = sql_query(\"select a from t\");\n    \
+                      let d = my_query!(\"select a from t\");\n";
+        assert_eq!(
+            format_source(Host::Rust, source, &[], upper),
+            "    let a = sqlx::query!(\"\n        SELECT\n          ID\n          FROM\n          T\n          \
+             WHERE\n          ID\n          =\n          $1\n    \", id);\n    \
+             let b = query_as!(User, r#\"\n        SELECT\n          1\n    \"#);\n    \
+             let c = sql_query(\"select a from t\");\n    \
+             let d = my_query!(\"select a from t\");\n"
+        );
+        let formatted = format_source(Host::Rust, source, &[], upper);
+        assert_eq!(format_source(Host::Rust, &formatted, &[], upper), formatted);
+        assert!(is_sqlx_query("query_scalar!( \"", 15));
+        assert!(!is_sqlx_query("query_as!(\"", 10));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        assert!(has_interpolation(Host::Go, "where a = :name"));
+        assert!(!has_interpolation(Host::Go, "select a::int"));
+        assert!(!has_interpolation(
+            Host::Python,
+            "where a = %s and b = %(b)s"
+        ));
+        assert!(!has_interpolation(
+            Host::Python,
+            "where a like 'x%' and b % 2 = 0"
+        ));
+        assert!(has_interpolation(Host::Python, "where a = %d"));
+        assert!(has_interpolation(Host::Python, "where a %% 2 = %(b)d"));
+        assert!(!has_interpolation(Host::Ruby, "where a = 1"));
+    }
+}
@@ -0,0 +1,315 @@
+//! `--encoding`, `--line-endings` and `--bom`: decode inputs that are not
+//! UTF-8, such as UTF-16 dumps from SQL Server tools or Latin-1 files from
+//! older systems, and write rewritten files back the way they were read,
+//! byte order mark and CRLF line endings included.
+
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// The encoding of a byte order mark at the start of the input,
+    /// otherwise UTF-8 if the input is valid UTF-8 and Latin-1 if it is not
+    #[default]
+    Auto,
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    #[value(name = "utf-16le", alias = "utf16le")]
+    Utf16Le,
+    #[value(name = "utf-16be", alias = "utf16be")]
+    Utf16Be,
+    #[value(name = "latin1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+impl Encoding {
+    /// The encoding announced by a byte order mark at the start of `bytes`.
+    pub fn from_bom(bytes: &[u8]) -> Option<Encoding> {
+        match bytes {
+            [0xEF, 0xBB, 0xBF, ..] => Some(Encoding::Utf8),
+            [0xFF, 0xFE, ..] => Some(Encoding::Utf16Le),
+            [0xFE, 0xFF, ..] => Some(Encoding::Utf16Be),
+            _ => None,
+        }
+    }
+
+    /// Whether each line of the input can be decoded on its own, which
+    /// streaming stdin relies on.
+    pub fn is_line_based(self) -> bool {
+        matches!(self, Encoding::Auto | Encoding::Utf8 | Encoding::Latin1)
+    }
+
+    /// Decode one line of a line-based encoding. `Auto` falls back to
+    /// Latin-1 for lines that are not valid UTF-8.
+    pub fn decode_line(self, bytes: Vec<u8>) -> Result<String, String> {
+        match self {
+            Encoding::Latin1 => Ok(latin1(&bytes)),
+            Encoding::Auto => Ok(String::from_utf8(bytes).unwrap_or_else(|e| latin1(e.as_bytes()))),
+            _ => String::from_utf8(bytes).map_err(|e| not_utf8(e.utf8_error())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LineEndings {
+    /// Those of the input, judged by its first line break
+    #[default]
+    Auto,
+    Lf,
+    Crlf,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Bom {
+    /// Keep the byte order mark of files that have one
+    #[default]
+    Auto,
+    /// Add one to every rewritten UTF-8 or UTF-16 file
+    Always,
+    /// Remove it from every rewritten file
+    Never,
+}
+
+/// How an input was encoded, so that it can be written back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileFormat {
+    /// Never `Auto`
+    pub encoding: Encoding,
+    /// Whether the input started with a byte order mark
+    pub bom: bool,
+    /// Whether lines end in `\r\n`
+    pub crlf: bool,
+}
+
+impl FileFormat {
+    /// Decode `bytes` as `encoding`, detecting it under `Auto`. The byte
+    /// order mark is left out of the text and CRLF line breaks become LF.
+    pub fn decode(bytes: &[u8], encoding: Encoding) -> Result<(String, FileFormat), String> {
+        let bom = Encoding::from_bom(bytes);
+        let encoding = match encoding {
+            Encoding::Auto => bom.unwrap_or(if std::str::from_utf8(bytes).is_ok() {
+                Encoding::Utf8
+            } else {
+                Encoding::Latin1
+            }),
+            encoding => encoding,
+        };
+        let bom = bom == Some(encoding);
+        let body = if bom {
+            &bytes[if encoding == Encoding::Utf8 { 3 } else { 2 }..]
+        } else {
+            bytes
+        };
+        let mut text = match encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => utf16(body, encoding)?,
+            Encoding::Latin1 => latin1(body),
+            _ => std::str::from_utf8(body).map_err(not_utf8)?.to_string(),
+        };
+        let crlf = text.find('\n').is_some_and(|i| text[..i].ends_with('\r'));
+        if text.contains("\r\n") {
+            text = text.replace("\r\n", "\n");
+        }
+        Ok((
+            text,
+            FileFormat {
+                encoding,
+                bom,
+                crlf,
+            },
+        ))
+    }
+
+    /// This format with the `--line-endings` and `--bom` overrides applied.
+    pub fn with(self, line_endings: LineEndings, bom: Bom) -> FileFormat {
+        FileFormat {
+            crlf: match line_endings {
+                LineEndings::Auto => self.crlf,
+                LineEndings::Lf => false,
+                LineEndings::Crlf => true,
+            },
+            bom: match bom {
+                Bom::Auto => self.bom,
+                Bom::Always => self.encoding != Encoding::Latin1,
+                Bom::Never => false,
+            },
+            ..self
+        }
+    }
+
+    /// `text` with its line breaks in this format.
+    pub fn line_endings<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.crlf {
+            Cow::Owned(text.replace('\n', "\r\n"))
+        } else {
+            Cow::Borrowed(text)
+        }
+    }
+
+    /// Encode `text`, which has LF line breaks, for writing to a file.
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, String> {
+        let text = self.line_endings(text);
+        let bom = self.bom.then_some('\u{FEFF}');
+        let mut bytes = Vec::with_capacity(text.len() + 3);
+        match self.encoding {
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let little = self.encoding == Encoding::Utf16Le;
+                let units = bom.into_iter().chain(text.chars());
+                for unit in units.flat_map(|c| c.encode_utf16(&mut [0; 2]).to_vec()) {
+                    bytes.extend(if little {
+                        unit.to_le_bytes()
+                    } else {
+                        unit.to_be_bytes()
+                    });
+                }
+            }
+            Encoding::Latin1 => {
+                for c in text.chars() {
+                    let byte = u8::try_from(u32::from(c))
+                        .map_err(|_| format!("`{}` cannot be written as latin1", c))?;
+                    bytes.push(byte);
+                }
+            }
+            _ => {
+                if let Some(bom) = bom {
+                    bytes.extend_from_slice(bom.encode_utf8(&mut [0; 3]).as_bytes());
+                }
+                bytes.extend_from_slice(text.as_bytes());
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+fn utf16(bytes: &[u8], encoding: Encoding) -> Result<String, String> {
+    if bytes.len() % 2 != 0 {
+        return Err("not valid utf-16: odd number of bytes".to_string());
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| {
+            let pair = [pair[0], pair[1]];
+            if encoding == Encoding::Utf16Le {
+                u16::from_le_bytes(pair)
+            } else {
+                u16::from_be_bytes(pair)
+            }
+        })
+        .collect();
+    String::from_utf16(&units).map_err(|_| "not valid utf-16: unpaired surrogate".to_string())
+}
+
+/// Every byte is the code point of the same value.
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+fn not_utf8(error: std::str::Utf8Error) -> String {
+    format!(
+        "not valid utf-8 at byte {}; pass --encoding to read it as another encoding",
+        error.valid_up_to()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let (text, format) = FileFormat::decode("séléct 1".as_bytes(), Encoding::Auto).unwrap();
+        assert_eq!(text, "séléct 1");
+        assert_eq!(format.encoding, Encoding::Utf8);
+
+        let (text, format) = FileFormat::decode(b"select '\xe9t\xe9'", Encoding::Auto).unwrap();
+        assert_eq!(text, "select 'été'");
+        assert_eq!(format.encoding, Encoding::Latin1);
+
+        let (text, format) =
+            FileFormat::decode(b"\xff\xfes\0e\0l\0 \0\xe9\0", Encoding::Auto).unwrap();
+        assert_eq!(text, "sel é");
+        assert_eq!(
+            format,
+            FileFormat {
+                encoding: Encoding::Utf16Le,
+                bom: true,
+                crlf: false
+            }
+        );
+    }
+
+    #[test]
+    fn test_bom_and_crlf() {
+        let bytes = b"\xef\xbb\xbfselect 1,\r\n  'a\r\nb'\r\n";
+        let (text, format) = FileFormat::decode(bytes, Encoding::Auto).unwrap();
+        assert_eq!(text, "select 1,\n  'a\nb'\n");
+        assert_eq!(
+            format,
+            FileFormat {
+                encoding: Encoding::Utf8,
+                bom: true,
+                crlf: true
+            }
+        );
+        assert_eq!(format.encode(&text).unwrap(), bytes);
+        assert_eq!(
+            format
+                .with(LineEndings::Lf, Bom::Never)
+                .encode("a\n")
+                .unwrap(),
+            b"a\n"
+        );
+
+        let (_, format) = FileFormat::decode(b"a\nb\r\n", Encoding::Auto).unwrap();
+        assert!(!format.crlf);
+        assert!(format.with(LineEndings::Crlf, Bom::Always).bom);
+        let (_, latin1) = FileFormat::decode(b"caf\xe9", Encoding::Auto).unwrap();
+        assert!(!latin1.with(LineEndings::Auto, Bom::Always).bom);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for (bytes, encoding) in [
+            (&b"\xfe\xff\0s\0e\0l\xd8\x3d\xde\x00"[..], Encoding::Auto),
+            (&b"s\0e\0l\0"[..], Encoding::Utf16Le),
+            (&b"caf\xe9"[..], Encoding::Latin1),
+        ] {
+            let (text, format) = FileFormat::decode(bytes, encoding).unwrap();
+            assert_eq!(format.encode(&text).unwrap(), bytes);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            FileFormat::decode(b"a\xff", Encoding::Utf8),
+            Err(
+                "not valid utf-8 at byte 1; pass --encoding to read it as another encoding"
+                    .to_string()
+            )
+        );
+        assert!(FileFormat::decode(b"\xff\xfea", Encoding::Auto).is_err());
+        let latin1 = FileFormat {
+            encoding: Encoding::Latin1,
+            bom: false,
+            crlf: false,
+        };
+        assert_eq!(
+            latin1.encode("select '€'"),
+            Err("`€` cannot be written as latin1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_line() {
+        assert_eq!(
+            Encoding::Auto.decode_line(b"caf\xe9\n".to_vec()).unwrap(),
+            "café\n"
+        );
+        assert_eq!(
+            Encoding::Auto.decode_line("café\n".into()).unwrap(),
+            "café\n"
+        );
+        assert!(Encoding::Utf8.decode_line(b"caf\xe9\n".to_vec()).is_err());
+    }
+}
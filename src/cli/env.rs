@@ -0,0 +1,113 @@
+//! `RS_SQL_INDENT_*` environment variables, for defaults set once in a CI
+//! image or dev container rather than in each repository.
+
+use clap::ValueEnum;
+use rs_sql_indent::config::{Preset, validate_indent_width};
+use rs_sql_indent::{Dialect, FormatOptions, FormatStyle};
+
+/// The variables read, in the order they are applied.
+pub const VARIABLES: &[&str] = &[
+    "RS_SQL_INDENT_PRESET",
+    "RS_SQL_INDENT_STYLE",
+    "RS_SQL_INDENT_DIALECT",
+    "RS_SQL_INDENT_CASE",
+    "RS_SQL_INDENT_INDENT_WIDTH",
+];
+
+/// Override `options` with the variables `lookup` returns a value for.
+/// Empty values are ignored, so a variable can be unset with `VAR=`.
+pub fn apply(
+    lookup: impl Fn(&str) -> Option<String>,
+    options: &mut FormatOptions,
+) -> Result<(), String> {
+    for &name in VARIABLES {
+        let Some(value) = lookup(name).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        let invalid = |expected: &str| format!("{}={}: expected {}", name, value, expected);
+        match name {
+            "RS_SQL_INDENT_PRESET" => Preset::find(&value)
+                .ok_or_else(|| invalid("pgformatter, sqlfluff or compact"))?
+                .apply(options),
+            "RS_SQL_INDENT_STYLE" => {
+                options.style =
+                    FormatStyle::from_str(&value, true).map_err(|_| invalid("a style name"))?;
+            }
+            "RS_SQL_INDENT_DIALECT" => {
+                options.dialect =
+                    Dialect::from_str(&value, true).map_err(|_| invalid("a dialect name"))?;
+            }
+            "RS_SQL_INDENT_CASE" => {
+                options.uppercase = match value.to_ascii_lowercase().as_str() {
+                    "upper" => true,
+                    "lower" => false,
+                    _ => return Err(invalid("upper or lower")),
+                };
+            }
+            _ => {
+                let width = value.parse().map_err(|_| invalid("a number"))?;
+                let width = validate_indent_width(width)
+                    .map_err(|e| format!("{}={}: {}", name, value, e))?;
+                options.indent_width = Some(width);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply_vars(vars: &[(&str, &str)]) -> Result<FormatOptions, String> {
+        let mut options = FormatOptions::default();
+        let lookup = |name: &str| {
+            vars.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| v.to_string())
+        };
+        apply(lookup, &mut options).map(|()| options)
+    }
+
+    #[test]
+    fn test_apply() {
+        assert_eq!(
+            apply_vars(&[
+                ("RS_SQL_INDENT_CASE", "lower"),
+                ("RS_SQL_INDENT_STYLE", "Dataops"),
+                ("RS_SQL_INDENT_DIALECT", ""),
+                ("RS_SQL_INDENT_INDENT_WIDTH", "2"),
+            ]),
+            Ok(FormatOptions {
+                uppercase: false,
+                style: FormatStyle::Dataops,
+                dialect: Dialect::Generic,
+                indent_width: Some(2),
+                placeholders: None,
+            })
+        );
+        // The preset comes first, so the other variables refine it
+        let options = apply_vars(&[
+            ("RS_SQL_INDENT_CASE", "upper"),
+            ("RS_SQL_INDENT_PRESET", "sqlfluff"),
+        ])
+        .unwrap();
+        assert!(options.uppercase);
+        assert_eq!(options.indent_width, Some(4));
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        assert_eq!(
+            apply_vars(&[("RS_SQL_INDENT_CASE", "title")]),
+            Err("RS_SQL_INDENT_CASE=title: expected upper or lower".to_string())
+        );
+        assert_eq!(
+            apply_vars(&[("RS_SQL_INDENT_INDENT_WIDTH", "100000000000")]),
+            Err(
+                "RS_SQL_INDENT_INDENT_WIDTH=100000000000: indent width must be between 1 and 16, got 100000000000"
+                    .to_string()
+            )
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! Safe rewrites applied to the token stream before formatting (`--fix`).
+//!
+//! Each rule only changes tokens in ways that cannot alter what the SQL
+//! means, so fixes can run unattended alongside formatting.
+
+use rs_sql_indent::token::Token;
+
+/// The fixes applied by [`apply`], in order.
+const RULES: &[fn(&mut Vec<Token<'_>>)] = &[not_equal, empty_statements, terminate_statements];
+
+/// Run every rule in [`RULES`] over `tokens`.
+pub fn apply(mut tokens: Vec<Token<'_>>) -> Vec<Token<'_>> {
+    for rule in RULES {
+        rule(&mut tokens);
+    }
+    tokens
+}
+
+fn is_trivia(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Whitespace(_) | Token::LineComment(_) | Token::BlockComment(_)
+    )
+}
+
+/// Write `!=` as the standard `<>`
+fn not_equal(tokens: &mut Vec<Token<'_>>) {
+    for token in tokens.iter_mut() {
+        if *token == Token::Operator("!=") {
+            *token = Token::Operator("<>");
+        }
+    }
+}
+
+/// Drop semicolons that end an empty statement
+fn empty_statements(tokens: &mut Vec<Token<'_>>) {
+    // Nothing before the first statement counts as a statement either
+    let mut statement_empty = true;
+    tokens.retain(|token| {
+        if is_trivia(token) {
+            return true;
+        }
+        let keep = !(statement_empty && *token == Token::Semicolon);
+        statement_empty = matches!(token, Token::Semicolon | Token::BatchSeparator(_));
+        keep
+    });
+}
+
+/// End the last statement with a semicolon
+fn terminate_statements(tokens: &mut Vec<Token<'_>>) {
+    let Some(last) = tokens.iter().rposition(|t| !is_trivia(t)) else {
+        return;
+    };
+    // Verbatim blocks carry their own terminators
+    if !matches!(
+        tokens[last],
+        Token::Semicolon
+            | Token::BatchSeparator(_)
+            | Token::ProceduralBlock(_)
+            | Token::Unformatted(_)
+            | Token::TemplateBlock(_)
+    ) {
+        tokens.insert(last + 1, Token::Semicolon);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_sql_indent::config::FormatOptions;
+    use rs_sql_indent::formatter::format_tokens;
+    use rs_sql_indent::lexer::tokenize;
+
+    fn fix(sql: &str) -> String {
+        format_tokens(&apply(tokenize(sql)), &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_not_equal() {
+        assert_eq!(
+            fix("select 1 where a != b;"),
+            "SELECT\n    1\nWHERE\n    a <> b;"
+        );
+    }
+
+    #[test]
+    fn test_empty_statements() {
+        assert_eq!(fix(";select 1;;\n;"), "SELECT\n    1;");
+    }
+
+    #[test]
+    fn test_terminate_last_statement() {
+        assert_eq!(fix("select 1 -- done"), "SELECT\n    1;\n\n-- done");
+        assert_eq!(fix("select 1;"), "SELECT\n    1;");
+        assert_eq!(fix("-- only a comment"), "-- only a comment");
+    }
+}
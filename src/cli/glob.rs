@@ -0,0 +1,152 @@
+//! Minimal glob patterns for path filtering: `*`, `?`, `[abc]`, `[!a-z]`
+//! within a path component and `**` across components.
+
+use std::path::{Component, Path};
+
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    segments: Vec<String>,
+    /// Patterns without a `/` match any single component, like `*.gen.sql`
+    /// or `vendor`, wherever it appears in the path.
+    anywhere: bool,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Self {
+        let trimmed = pattern.trim_start_matches("./").trim_end_matches('/');
+        Self {
+            segments: trimmed
+                .split('/')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+            anywhere: !trimmed.contains('/'),
+        }
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let components: Vec<String> = path
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect();
+
+        if self.anywhere {
+            let [segment] = self.segments.as_slice() else {
+                return false;
+            };
+            return components.iter().any(|c| match_segment(segment, c));
+        }
+        let segments: Vec<&str> = self.segments.iter().map(String::as_str).collect();
+        let components: Vec<&str> = components.iter().map(String::as_str).collect();
+        match_components(&segments, &components)
+    }
+}
+
+fn match_components(segments: &[&str], components: &[&str]) -> bool {
+    match segments.split_first() {
+        None => components.is_empty(),
+        Some((&"**", rest)) => {
+            (0..=components.len()).any(|skip| match_components(rest, &components[skip..]))
+        }
+        Some((segment, rest)) => match components.split_first() {
+            Some((component, tail)) => {
+                match_segment(segment, component) && match_components(rest, tail)
+            }
+            None => false,
+        },
+    }
+}
+
+fn match_segment(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_chars(&pattern, &text)
+}
+
+fn match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') => (0..=text.len()).any(|skip| match_chars(&pattern[1..], &text[skip..])),
+        Some('?') => !text.is_empty() && match_chars(&pattern[1..], &text[1..]),
+        Some('[') => match (text.first(), class_end(pattern)) {
+            (Some(&c), Some(end)) => {
+                class_matches(&pattern[1..end], c) && match_chars(&pattern[end + 1..], &text[1..])
+            }
+            // An unterminated class is a literal '['
+            (Some('['), None) => match_chars(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+        Some(&p) => text.first() == Some(&p) && match_chars(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Index of the `]` closing the class opened at `pattern[0]`.
+fn class_end(pattern: &[char]) -> Option<usize> {
+    // A ']' right after '[' or '[!' is a member, not the terminator
+    let first = if pattern.get(1) == Some(&'!') { 2 } else { 1 };
+    (first + 1..pattern.len()).find(|&i| pattern[i] == ']')
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.split_first() {
+        Some(('!', rest)) => (true, rest),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= class[i] <= c && c <= class[i + 2];
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, path: &str) -> bool {
+        Pattern::new(pattern).matches(Path::new(path))
+    }
+
+    #[test]
+    fn test_double_star() {
+        assert!(matches("vendor/**", "vendor/a.sql"));
+        assert!(matches("vendor/**", "vendor/deep/er/a.sql"));
+        assert!(matches("**/generated/*.sql", "db/generated/a.sql"));
+        assert!(matches("**/generated/*.sql", "generated/a.sql"));
+        assert!(!matches("vendor/**", "src/vendor.sql"));
+    }
+
+    #[test]
+    fn test_component_pattern_matches_anywhere() {
+        assert!(matches("*.gen.sql", "db/migrations/001.gen.sql"));
+        assert!(matches("fixtures", "tests/fixtures/a.sql"));
+        assert!(!matches("*.gen.sql", "db/gen.sql"));
+    }
+
+    #[test]
+    fn test_anchored_pattern() {
+        assert!(matches("./db/*.sql", "db/a.sql"));
+        assert!(matches("db/*.sql", "./db/a.sql"));
+        assert!(!matches("db/*.sql", "db/sub/a.sql"));
+    }
+
+    #[test]
+    fn test_question_mark_and_classes() {
+        assert!(matches("v?.sql", "v1.sql"));
+        assert!(!matches("v?.sql", "v10.sql"));
+        assert!(matches("v[0-9].sql", "v7.sql"));
+        assert!(!matches("v[!0-9].sql", "v7.sql"));
+        assert!(matches("v[!0-9].sql", "vx.sql"));
+        assert!(matches("[]].sql", "].sql"));
+    }
+}
@@ -0,0 +1,106 @@
+//! `.sqlindentignore` files: gitignore-style rules that keep paths out of
+//! directory walks.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::glob::Pattern;
+
+pub const IGNORE_FILE: &str = ".sqlindentignore";
+
+#[derive(Debug, Clone)]
+struct Rule {
+    pattern: Pattern,
+    /// `!pattern` re-includes a path an earlier rule ignored
+    negated: bool,
+    /// `pattern/` only matches directories
+    dir_only: bool,
+}
+
+/// The rules of one ignore file. Paths are matched relative to the
+/// directory containing it.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreFile {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+    /// Parse gitignore syntax: one pattern per line, `#` comments, `!` to
+    /// negate, a trailing `/` for directories only and a leading or inner
+    /// `/` to anchor the pattern to the ignore file's directory.
+    pub fn parse(contents: &str) -> Self {
+        let rules = contents
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+                Rule {
+                    pattern: Pattern::new(line),
+                    negated,
+                    dir_only: line.ends_with('/'),
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Read `dir/.sqlindentignore`, if there is one.
+    pub fn load(dir: &Path) -> io::Result<Option<Self>> {
+        match fs::read_to_string(dir.join(IGNORE_FILE)) {
+            Ok(contents) => Ok(Some(Self::parse(&contents))),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Whether `relative` is ignored (`Some(true)`), re-included
+    /// (`Some(false)`), or not mentioned at all. The last matching rule wins.
+    pub fn matched(&self, relative: &Path, is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && rule.pattern.matches(relative))
+            .map(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matched(rules: &str, path: &str, is_dir: bool) -> Option<bool> {
+        IgnoreFile::parse(rules).matched(Path::new(path), is_dir)
+    }
+
+    #[test]
+    fn test_comments_and_blank_lines() {
+        let rules = "# generated\n\n*.gen.sql\n";
+        assert_eq!(matched(rules, "db/a.gen.sql", false), Some(true));
+        assert_eq!(matched(rules, "db/a.sql", false), None);
+    }
+
+    #[test]
+    fn test_negation_last_match_wins() {
+        let rules = "*.sql\n!keep.sql\n";
+        assert_eq!(matched(rules, "a.sql", false), Some(true));
+        assert_eq!(matched(rules, "keep.sql", false), Some(false));
+    }
+
+    #[test]
+    fn test_directory_only() {
+        assert_eq!(matched("dumps/", "dumps", true), Some(true));
+        assert_eq!(matched("dumps/", "dumps", false), None);
+    }
+
+    #[test]
+    fn test_anchored() {
+        assert_eq!(matched("/fixtures", "fixtures", true), Some(true));
+        assert_eq!(matched("/fixtures", "tests/fixtures", true), None);
+        assert_eq!(matched("fixtures", "tests/fixtures", true), Some(true));
+    }
+}
@@ -0,0 +1,31 @@
+//! Building blocks of the command-line tool that are not part of the library.
+
+pub mod changed;
+pub mod color;
+pub mod config_file;
+pub mod config_search;
+pub mod daemon;
+pub mod dbt;
+pub mod diagnostic;
+pub mod diff;
+pub mod doctor;
+pub mod document;
+pub mod embedded;
+pub mod encoding;
+pub mod env;
+pub mod fix;
+pub mod glob;
+pub mod ignore;
+pub mod json;
+pub mod progress;
+pub mod report;
+pub mod show_config;
+pub mod split;
+pub mod sqlfluff;
+pub mod staged;
+pub mod stats;
+pub mod stream;
+pub mod tokenize;
+pub mod verify;
+pub mod walk;
+pub mod watch;
@@ -0,0 +1,66 @@
+//! `--progress`: a progress bar on stderr while a batch of files is
+//! formatted, so long runs over large repositories visibly advance.
+
+use std::io::{self, Write};
+
+const WIDTH: usize = 30;
+
+pub struct Progress {
+    total: usize,
+    done: usize,
+    enabled: bool,
+    visible: bool,
+}
+
+impl Progress {
+    pub fn new(total: usize, enabled: bool) -> Self {
+        Self {
+            total,
+            done: 0,
+            enabled,
+            visible: false,
+        }
+    }
+
+    /// Erase the bar so other messages can be printed on stderr.
+    pub fn clear(&mut self) {
+        if self.visible {
+            eprint!("\r\x1b[K");
+            self.visible = false;
+        }
+    }
+
+    /// Count one more file done and redraw the bar.
+    pub fn advance(&mut self) {
+        self.done += 1;
+        if self.enabled {
+            self.clear();
+            eprint!("{}", bar(self.done, self.total));
+            let _ = io::stderr().flush();
+            self.visible = true;
+        }
+    }
+}
+
+/// `[#########.....] 123/456 files`
+fn bar(done: usize, total: usize) -> String {
+    let filled = (WIDTH * done).checked_div(total).unwrap_or(WIDTH);
+    format!(
+        "[{}{}] {}/{} files",
+        "#".repeat(filled),
+        ".".repeat(WIDTH - filled),
+        done,
+        total
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bar() {
+        assert_eq!(bar(1, 3), "[##########....................] 1/3 files");
+        assert_eq!(bar(0, 0), format!("[{}] 0/0 files", "#".repeat(WIDTH)));
+    }
+}
@@ -0,0 +1,192 @@
+//! `--reporter`: how the results of a run are reported.
+
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Reporter {
+    /// Print the formatted SQL (or diff)
+    #[default]
+    Text,
+    /// Print one JSON document describing every input instead of the SQL
+    Json,
+}
+
+/// Outcome of formatting a single input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileReport {
+    pub path: String,
+    pub changed: bool,
+    pub bytes_in: usize,
+    pub bytes_out: usize,
+    pub warnings: Vec<String>,
+    /// Why the input was left alone, e.g. a failed `--verify`
+    pub error: Option<String>,
+}
+
+/// Render `reports` as a JSON document:
+/// `{"files": [{"path": ..., "changed": ..., ...}], "changed": <count>}`.
+pub fn to_json(reports: &[FileReport]) -> String {
+    let files: Vec<String> = reports
+        .iter()
+        .map(|r| {
+            let warnings: Vec<String> = r.warnings.iter().map(|w| json_string(w)).collect();
+            let error = r.error.as_deref().map_or_else(|| "null".to_string(), json_string);
+            format!(
+                "{{\"path\":{},\"changed\":{},\"bytes_in\":{},\"bytes_out\":{},\"warnings\":[{}],\"error\":{}}}",
+                json_string(&r.path),
+                r.changed,
+                r.bytes_in,
+                r.bytes_out,
+                warnings.join(","),
+                error
+            )
+        })
+        .collect();
+    let changed = reports.iter().filter(|r| r.changed).count();
+    format!(
+        "{{\"files\":[{}],\"changed\":{}}}",
+        files.join(","),
+        changed
+    )
+}
+
+/// One-line summary of a batch run, e.g.
+/// `42 files checked, 7 reformatted, 1 skipped (errors above)`.
+pub fn summary(reports: &[FileReport]) -> String {
+    let checked = reports.len();
+    let changed = reports.iter().filter(|r| r.changed).count();
+    let skipped = reports.iter().filter(|r| r.error.is_some()).count();
+    let mut line = format!(
+        "{} file{} checked, {} reformatted",
+        checked,
+        if checked == 1 { "" } else { "s" },
+        changed
+    );
+    if skipped > 0 {
+        line.push_str(&format!(", {} skipped (errors above)", skipped));
+    }
+    line
+}
+
+/// The inputs that failed and why, listed after a batch run so failures
+/// are not lost among the output for the other files.
+pub fn failures(reports: &[FileReport]) -> String {
+    let failed: Vec<&FileReport> = reports.iter().filter(|r| r.error.is_some()).collect();
+    let mut out = format!(
+        "Failed to format {} file{}:\n",
+        failed.len(),
+        if failed.len() == 1 { "" } else { "s" }
+    );
+    for report in failed {
+        out.push_str(&format!(
+            "  {}: {}\n",
+            report.path,
+            report.error.as_deref().unwrap_or_default()
+        ));
+    }
+    out
+}
+
+/// Quote `s` as a JSON string literal.
+pub fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let report = |changed, error: Option<&str>| FileReport {
+            path: "q.sql".to_string(),
+            changed,
+            bytes_in: 0,
+            bytes_out: 0,
+            warnings: Vec::new(),
+            error: error.map(str::to_string),
+        };
+        assert_eq!(
+            summary(&[report(true, None)]),
+            "1 file checked, 1 reformatted"
+        );
+        assert_eq!(
+            summary(&[
+                report(true, None),
+                report(false, None),
+                report(false, Some("bad"))
+            ]),
+            "3 files checked, 1 reformatted, 1 skipped (errors above)"
+        );
+    }
+
+    #[test]
+    fn test_failures() {
+        let report = |path: &str, error: Option<&str>| FileReport {
+            path: path.to_string(),
+            changed: false,
+            bytes_in: 0,
+            bytes_out: 0,
+            warnings: Vec::new(),
+            error: error.map(str::to_string),
+        };
+        assert_eq!(
+            failures(&[
+                report("a.sql", Some("cannot read the file: denied")),
+                report("b.sql", None),
+                report("c.sql", Some("malformed SQL: 1 problem reported above")),
+            ]),
+            "Failed to format 2 files:\n  \
+             a.sql: cannot read the file: denied\n  \
+             c.sql: malformed SQL: 1 problem reported above\n"
+        );
+    }
+
+    #[test]
+    fn test_json_string_escapes() {
+        assert_eq!(json_string("a\"b\\c\nd\u{1}"), r#""a\"b\\c\nd\u0001""#);
+    }
+
+    #[test]
+    fn test_to_json() {
+        let reports = vec![
+            FileReport {
+                path: "a.sql".to_string(),
+                changed: true,
+                bytes_in: 8,
+                bytes_out: 14,
+                warnings: Vec::new(),
+                error: None,
+            },
+            FileReport {
+                path: "b.sql".to_string(),
+                changed: false,
+                bytes_in: 0,
+                bytes_out: 0,
+                warnings: vec!["no SQL found".to_string()],
+                error: Some("bad".to_string()),
+            },
+        ];
+        assert_eq!(
+            to_json(&reports),
+            "{\"files\":[\
+             {\"path\":\"a.sql\",\"changed\":true,\"bytes_in\":8,\"bytes_out\":14,\"warnings\":[],\"error\":null},\
+             {\"path\":\"b.sql\",\"changed\":false,\"bytes_in\":0,\"bytes_out\":0,\"warnings\":[\"no SQL found\"],\"error\":\"bad\"}\
+             ],\"changed\":1}"
+        );
+    }
+}
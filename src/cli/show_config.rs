@@ -0,0 +1,99 @@
+//! `--show-config`: print the options a run would format with, after all
+//! configuration sources have been merged.
+
+use clap::ValueEnum;
+use rs_sql_indent::FormatOptions;
+
+use super::config_file::FIELDS;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ConfigFormat {
+    #[default]
+    Toml,
+    Json,
+}
+
+pub fn render(options: &FormatOptions, format: ConfigFormat) -> String {
+    let mut out = String::new();
+    for field in FIELDS {
+        let value = (field.get)(options);
+        let value = match (format, value) {
+            (_, Some(value)) if field.quoted => format!("\"{}\"", value),
+            (_, Some(value)) => value,
+            (ConfigFormat::Json, None) => "null".to_string(),
+            // TOML has no null: an unset field is left out, as a comment
+            (ConfigFormat::Toml, None) => {
+                let meaning = field.unset.as_ref().map_or("unset", |unset| unset.meaning);
+                out.push_str(&format!("# {}: {}\n", field.key, meaning));
+                continue;
+            }
+        };
+        match format {
+            ConfigFormat::Toml => out.push_str(&format!("{} = {}\n", field.key, value)),
+            ConfigFormat::Json => {
+                out.push(if out.is_empty() { '{' } else { ',' });
+                out.push_str(&format!("\"{}\":{}", field.key, value));
+            }
+        }
+    }
+    if format == ConfigFormat::Json {
+        out.push_str("}\n");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::config_file;
+    use rs_sql_indent::{Dialect, FormatStyle, PlaceholderStyle};
+
+    #[test]
+    fn test_render_toml() {
+        let options = FormatOptions {
+            uppercase: false,
+            style: FormatStyle::Dataops,
+            dialect: Dialect::Bigquery,
+            indent_width: Some(2),
+            placeholders: Some(PlaceholderStyle::Dollar),
+        };
+        assert_eq!(
+            render(&options, ConfigFormat::Toml),
+            "style = \"dataops\"\ndialect = \"bigquery\"\nuppercase = false\nindent_width = 2\n\
+             placeholders = \"dollar\"\n"
+        );
+    }
+
+    #[test]
+    fn test_render_toml_default() {
+        assert_eq!(
+            render(&FormatOptions::default(), ConfigFormat::Toml),
+            "style = \"basic\"\ndialect = \"generic\"\nuppercase = true\n\
+             # indent_width: the style's default\n# placeholders: kept as written\n"
+        );
+    }
+
+    #[test]
+    fn test_render_toml_round_trips() {
+        let options = FormatOptions {
+            uppercase: false,
+            style: FormatStyle::Aligned,
+            dialect: Dialect::Postgres,
+            indent_width: Some(3),
+            placeholders: Some(PlaceholderStyle::Colon),
+        };
+        for options in [options, FormatOptions::default()] {
+            let mut parsed = FormatOptions::default();
+            config_file::apply(&render(&options, ConfigFormat::Toml), &mut parsed).unwrap();
+            assert_eq!(parsed, options);
+        }
+    }
+
+    #[test]
+    fn test_render_json_default() {
+        assert_eq!(
+            render(&FormatOptions::default(), ConfigFormat::Json),
+            "{\"style\":\"basic\",\"dialect\":\"generic\",\"uppercase\":true,\"indent_width\":null,\"placeholders\":null}\n"
+        );
+    }
+}
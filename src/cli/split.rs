@@ -0,0 +1,49 @@
+//! `split` subcommand: write each statement of a script to its own file.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use rs_sql_indent::{FormatOptions, format_sql, lexer};
+
+#[derive(Args)]
+pub struct SplitArgs {
+    /// SQL file to split. Reads stdin when omitted
+    pub file: Option<PathBuf>,
+
+    /// Directory the numbered statement files are written to
+    #[arg(short = 'd', long, value_name = "DIR", default_value = ".")]
+    pub out_dir: PathBuf,
+
+    /// Format each statement before writing it
+    #[arg(long)]
+    pub format: bool,
+}
+
+/// Split `input` into `<stem>_001.sql`, `<stem>_002.sql`, ... inside the
+/// output directory and return the paths written, in statement order.
+pub fn run(args: &SplitArgs, input: &str, options: &FormatOptions) -> io::Result<Vec<PathBuf>> {
+    let statements = lexer::split_statements(input, options.dialect);
+    let stem = args.file.as_deref().and_then(Path::file_stem).map_or_else(
+        || "statement".to_string(),
+        |s| s.to_string_lossy().into_owned(),
+    );
+    let width = statements.len().to_string().len().max(3);
+
+    fs::create_dir_all(&args.out_dir)?;
+    let mut written = Vec::with_capacity(statements.len());
+    for (i, statement) in statements.iter().enumerate() {
+        let path = args
+            .out_dir
+            .join(format!("{}_{:0width$}.sql", stem, i + 1, width = width));
+        let text = if args.format {
+            format_sql(statement, options)
+        } else {
+            statement.to_string()
+        };
+        fs::write(&path, format!("{}\n", text))?;
+        written.push(path);
+    }
+    Ok(written)
+}
@@ -0,0 +1,194 @@
+//! Read the subset of a `.sqlfluff` configuration that maps onto
+//! [`FormatOptions`]: dialect, keyword capitalisation, indent width and
+//! comma position. Everything else in the file is ignored.
+
+use rs_sql_indent::config::{Dialect, FormatOptions, FormatStyle, validate_indent_width};
+
+/// Override `options` with the settings found in `.sqlfluff` contents.
+///
+/// Both the current (`capitalisation.keywords`, `layout:type:comma`) and the
+/// legacy (`L010`, `comma_style`) rule sections are understood. A dialect
+/// this formatter does not know and a `tab_space_size` outside the accepted
+/// indent widths are errors, reported with their line number.
+pub fn apply(contents: &str, options: &mut FormatOptions) -> Result<(), String> {
+    let mut section = String::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_ascii_lowercase();
+            continue;
+        }
+        let Some((key, value)) = line.split_once(['=', ':']) else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_ascii_lowercase();
+
+        match (section.as_str(), key.as_str()) {
+            ("sqlfluff", "dialect") => {
+                options.dialect = dialect_from_sqlfluff(&value)
+                    .map_err(|e| format!("line {}: {}", index + 1, e))?;
+            }
+            (
+                "sqlfluff:rules:capitalisation.keywords" | "sqlfluff:rules:l010",
+                "capitalisation_policy",
+            ) => match value.as_str() {
+                "upper" => options.uppercase = true,
+                "lower" => options.uppercase = false,
+                _ => {}
+            },
+            ("sqlfluff:indentation", "tab_space_size") => {
+                if let Ok(width) = value.parse() {
+                    let width = validate_indent_width(width)
+                        .map_err(|e| format!("line {}: {}", index + 1, e))?;
+                    options.indent_width = Some(width);
+                }
+            }
+            ("sqlfluff:layout:type:comma", "line_position") | ("sqlfluff:rules", "comma_style") => {
+                options.style = style_for_comma_position(options.style, &value);
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn dialect_from_sqlfluff(name: &str) -> Result<Dialect, String> {
+    match name {
+        "ansi" => Ok(Dialect::Generic),
+        "sparksql" | "databricks" | "hive" => Ok(Dialect::Spark),
+        other => other.parse(),
+    }
+}
+
+/// Pick the style closest to `style` that puts commas at `position`.
+fn style_for_comma_position(style: FormatStyle, position: &str) -> FormatStyle {
+    match (position, style) {
+        ("leading", FormatStyle::Basic | FormatStyle::Streamline) => FormatStyle::Dataops,
+        ("trailing", FormatStyle::Dataops | FormatStyle::Aligned) => FormatStyle::Basic,
+        _ => style,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> FormatOptions {
+        let mut options = FormatOptions::default();
+        apply(contents, &mut options).unwrap();
+        options
+    }
+
+    #[test]
+    fn test_empty_config_keeps_defaults() {
+        assert_eq!(parse(""), FormatOptions::default());
+    }
+
+    #[test]
+    fn test_full_config() {
+        let options = parse(
+            "[sqlfluff]\n\
+             dialect = bigquery\n\
+             max_line_length = 120\n\
+             \n\
+             [sqlfluff:indentation]\n\
+             indent_unit = space\n\
+             tab_space_size = 2\n\
+             \n\
+             [sqlfluff:rules:capitalisation.keywords]\n\
+             capitalisation_policy = lower\n\
+             \n\
+             [sqlfluff:layout:type:comma]\n\
+             line_position = leading\n",
+        );
+        assert_eq!(options.dialect, Dialect::Bigquery);
+        assert_eq!(options.indent_width, Some(2));
+        assert!(!options.uppercase);
+        assert_eq!(options.style, FormatStyle::Dataops);
+    }
+
+    #[test]
+    fn test_out_of_range_indent() {
+        let mut options = FormatOptions::default();
+        assert_eq!(
+            apply(
+                "[sqlfluff:indentation]\ntab_space_size = 99999999999\n",
+                &mut options
+            ),
+            Err("line 2: indent width must be between 1 and 16, got 99999999999".to_string())
+        );
+        assert_eq!(options.indent_width, None);
+    }
+
+    #[test]
+    fn test_legacy_rule_sections() {
+        let options = parse(
+            "[sqlfluff:rules]\ncomma_style = leading\n\n[sqlfluff:rules:L010]\ncapitalisation_policy = upper\n",
+        );
+        assert_eq!(options.style, FormatStyle::Dataops);
+        assert!(options.uppercase);
+    }
+
+    #[test]
+    fn test_comments_and_unknown_values_ignored() {
+        let options = parse(
+            "# team config\n[sqlfluff]\n; legacy comment\ndialect = ansi\n\n[sqlfluff:rules:capitalisation.keywords]\ncapitalisation_policy = consistent\n",
+        );
+        assert_eq!(options.dialect, Dialect::Generic);
+        assert!(options.uppercase);
+    }
+
+    #[test]
+    fn test_unknown_dialect() {
+        let mut options = FormatOptions::default();
+        assert_eq!(
+            apply("[sqlfluff]\ndialect = exasol\n", &mut options),
+            Err("line 2: unknown dialect `exasol`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_postgres_dialect() {
+        assert_eq!(
+            parse("[sqlfluff]\ndialect = postgres").dialect,
+            Dialect::Postgres
+        );
+    }
+
+    #[test]
+    fn test_spark_dialect_aliases() {
+        assert_eq!(
+            parse("[sqlfluff]\ndialect = sparksql").dialect,
+            Dialect::Spark
+        );
+        assert_eq!(
+            parse("[sqlfluff]\ndialect = databricks").dialect,
+            Dialect::Spark
+        );
+    }
+
+    #[test]
+    fn test_aligned_keeps_leading_commas() {
+        let mut options = FormatOptions {
+            style: FormatStyle::Aligned,
+            ..FormatOptions::default()
+        };
+        apply(
+            "[sqlfluff:layout:type:comma]\nline_position = leading",
+            &mut options,
+        )
+        .unwrap();
+        assert_eq!(options.style, FormatStyle::Aligned);
+        apply(
+            "[sqlfluff:layout:type:comma]\nline_position = trailing",
+            &mut options,
+        )
+        .unwrap();
+        assert_eq!(options.style, FormatStyle::Basic);
+    }
+}
@@ -0,0 +1,138 @@
+//! `--staged`: format the files staged in the git index, for use as a
+//! pre-commit hook.
+//!
+//! Contents are read from and written back to the index, so partially
+//! staged files are formatted as they will be committed. The working tree
+//! copy is updated too when it still matches the staged one.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// A regular file staged for commit.
+pub struct StagedFile {
+    /// Path relative to the root of the repository
+    pub path: PathBuf,
+    mode: String,
+    blob: String,
+}
+
+/// Top-level directory of the repository containing the current directory.
+pub fn repo_root() -> io::Result<PathBuf> {
+    let output = git(Path::new("."), &["rev-parse", "--show-toplevel"], None)?;
+    Ok(PathBuf::from(String::from_utf8_lossy(&output).trim_end()))
+}
+
+/// Files added or modified in the index with one of the given extensions.
+/// Deleted files, symlinks and submodules are left out.
+pub fn list(root: &Path, extensions: &[&str]) -> io::Result<Vec<StagedFile>> {
+    let output = git(
+        root,
+        &[
+            "diff",
+            "--cached",
+            "--raw",
+            "-z",
+            "--no-abbrev",
+            "--no-renames",
+            "--diff-filter=AM",
+        ],
+        None,
+    )?;
+    let output = String::from_utf8_lossy(&output);
+    Ok(parse_raw_diff(&output)
+        .into_iter()
+        .filter(|file| {
+            file.mode.starts_with("100") && super::walk::has_extension(&file.path, extensions)
+        })
+        .collect())
+}
+
+/// The staged contents of `file`.
+pub fn read(root: &Path, file: &StagedFile) -> io::Result<Vec<u8>> {
+    git(root, &["cat-file", "blob", &file.blob], None)
+}
+
+/// Replace the staged contents of `file` with `contents`. The working tree
+/// copy is rewritten as well when it is identical to `original`, the staged
+/// contents being replaced; otherwise its unstaged changes are left alone.
+pub fn update(root: &Path, file: &StagedFile, original: &[u8], contents: &[u8]) -> io::Result<()> {
+    let path = file.path.to_string_lossy();
+    let blob = git(
+        root,
+        &["hash-object", "-w", "--stdin", &format!("--path={}", path)],
+        Some(contents),
+    )?;
+    let blob = String::from_utf8_lossy(&blob);
+    let cacheinfo = format!("{},{},{}", file.mode, blob.trim_end(), path);
+    git(root, &["update-index", "--cacheinfo", &cacheinfo], None)?;
+
+    let worktree = root.join(&file.path);
+    if fs::read(&worktree).is_ok_and(|current| current == original) {
+        fs::write(&worktree, contents)?;
+    }
+    Ok(())
+}
+
+/// Entries of `git diff --raw -z` output:
+/// `:<old mode> <new mode> <old blob> <new blob> <status>\0<path>\0`.
+fn parse_raw_diff(output: &str) -> Vec<StagedFile> {
+    let mut fields = output.split('\0');
+    let mut files = Vec::new();
+    while let (Some(meta), Some(path)) = (fields.next(), fields.next()) {
+        let parts: Vec<&str> = meta.trim_start_matches(':').split(' ').collect();
+        if let [_, mode, _, blob, _] = parts[..] {
+            files.push(StagedFile {
+                path: PathBuf::from(path),
+                mode: mode.to_string(),
+                blob: blob.to_string(),
+            });
+        }
+    }
+    files
+}
+
+/// Run git in `dir`, returning its stdout or its error message.
+pub fn git(dir: &Path, args: &[&str], stdin: Option<&[u8]>) -> io::Result<Vec<u8>> {
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(input) = stdin {
+        child.stdin.take().unwrap().write_all(input)?;
+    }
+    drop(child.stdin.take());
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_raw_diff() {
+        let output = ":000000 100644 0000000000000000000000000000000000000000 \
+                      1111111111111111111111111111111111111111 A\0new.sql\0\
+                      :100644 100755 2222222222222222222222222222222222222222 \
+                      3333333333333333333333333333333333333333 M\0dir/run me.sql\0";
+        let files = parse_raw_diff(output);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, PathBuf::from("new.sql"));
+        assert_eq!(files[0].mode, "100644");
+        assert_eq!(files[0].blob, "1111111111111111111111111111111111111111");
+        assert_eq!(files[1].path, PathBuf::from("dir/run me.sql"));
+        assert_eq!(files[1].mode, "100755");
+    }
+}
@@ -0,0 +1,94 @@
+//! `stats` subcommand: statement and table statistics, totalled over
+//! [`analyze`] for each file.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use clap::Args;
+use rs_sql_indent::Dialect;
+use rs_sql_indent::analyze::analyze;
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// SQL files or directories to inspect. Reads stdin when none are given
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub statements: usize,
+    /// Statement count per kind: `SELECT`, `INSERT`, `UPDATE`, `DELETE`,
+    /// `MERGE`, `DDL` or `OTHER`
+    pub kinds: BTreeMap<&'static str, usize>,
+    /// Distinct table names, qualified as written (`schema.table`)
+    pub tables: BTreeSet<String>,
+    /// Deepest parenthesis nesting, subqueries included
+    pub max_depth: usize,
+}
+
+pub fn collect(input: &str, dialect: Dialect) -> Stats {
+    let mut stats = Stats::default();
+    for statement in analyze(input, dialect) {
+        stats.statements += 1;
+        *stats.kinds.entry(statement.kind.as_str()).or_default() += 1;
+        stats.max_depth = stats.max_depth.max(statement.max_depth);
+        stats.tables.extend(statement.tables);
+    }
+    stats
+}
+
+pub fn render(name: &str, stats: &Stats) -> String {
+    let kinds: Vec<String> = stats
+        .kinds
+        .iter()
+        .map(|(kind, count)| format!("{} {}", kind, count))
+        .collect();
+    let tables: Vec<&str> = stats.tables.iter().map(String::as_str).collect();
+    format!(
+        "{}\n  statements: {} ({})\n  tables: {}\n  max nesting depth: {}\n",
+        name,
+        stats.statements,
+        kinds.join(", "),
+        if tables.is_empty() {
+            "-".to_string()
+        } else {
+            tables.join(", ")
+        },
+        stats.max_depth
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect() {
+        let stats = collect(
+            "with recent as (select * from orders where id in (select id from archived))\n\
+             select * from recent r join public.users u on u.id = r.user_id, regions;\n\
+             insert into audit (id) values (1);\n\
+             create table if not exists audit (id int references public.users (id));",
+            Dialect::Generic,
+        );
+        assert_eq!(stats.statements, 3);
+        assert_eq!(
+            stats.kinds,
+            BTreeMap::from([("DDL", 1), ("INSERT", 1), ("SELECT", 1)])
+        );
+        assert_eq!(
+            stats.tables.iter().map(String::as_str).collect::<Vec<_>>(),
+            vec!["archived", "audit", "orders", "public.users", "regions"]
+        );
+        assert_eq!(stats.max_depth, 2);
+    }
+
+    #[test]
+    fn test_render() {
+        let stats = collect("select 1", Dialect::Generic);
+        assert_eq!(
+            render("<stdin>", &stats),
+            "<stdin>\n  statements: 1 (SELECT 1)\n  tables: -\n  max nesting depth: 0\n"
+        );
+    }
+}
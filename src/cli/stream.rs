@@ -0,0 +1,212 @@
+//! Statement-by-statement formatting of stdin, so piped workloads such as
+//! `pg_dump | rs-sql-indent` print each statement as soon as its
+//! terminating semicolon has been read instead of buffering the whole dump.
+//!
+//! Input is read and decoded a line at a time and split into runs of
+//! statements by [`StatementBuffer`], which keeps constructs whose tokens
+//! depend on text further ahead in one piece, so the output matches
+//! formatting the input as a whole.
+
+use std::io::{self, BufRead, Write};
+
+use rs_sql_indent::Dialect;
+use rs_sql_indent::reader::StatementBuffer;
+
+use super::encoding::{Encoding, LineEndings};
+
+/// Format `reader` to `writer` statement by statement, decoding each line
+/// with the line-based `encoding`. `format` turns a run of complete
+/// statements with LF line breaks, starting on the given line of the input,
+/// into formatted SQL ending in a newline. Its line breaks are written as
+/// `line_endings`, where `Auto` follows the first line of the input.
+/// Returns whether the input held anything besides whitespace.
+pub fn run<R, W, F>(
+    mut reader: R,
+    mut writer: W,
+    dialect: Dialect,
+    encoding: Encoding,
+    line_endings: LineEndings,
+    format: F,
+) -> io::Result<bool>
+where
+    R: BufRead,
+    W: Write,
+    F: Fn(&str, usize) -> io::Result<String>,
+{
+    let mut buffer = StatementBuffer::new(dialect);
+    let mut written = false;
+    let mut seen_input = false;
+    let mut line_number = 0;
+    let mut crlf = match line_endings {
+        LineEndings::Auto => None,
+        LineEndings::Lf => Some(false),
+        LineEndings::Crlf => Some(true),
+    };
+
+    loop {
+        let mut bytes = Vec::new();
+        if reader.read_until(b'\n', &mut bytes)? == 0 {
+            break;
+        }
+        line_number += 1;
+        if bytes.ends_with(b"\r\n") {
+            bytes.truncate(bytes.len() - 2);
+            bytes.push(b'\n');
+            crlf.get_or_insert(true);
+        } else if bytes.ends_with(b"\n") {
+            crlf.get_or_insert(false);
+        }
+        let line = encoding.decode_line(bytes).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: {}", line_number, e),
+            )
+        })?;
+        seen_input |= !line.trim().is_empty();
+        let Some(chunk) = buffer.push_line(&line) else {
+            continue;
+        };
+        write_chunk(
+            &mut writer,
+            &format(&chunk.text, chunk.first_line)?,
+            crlf.unwrap_or(false),
+            &mut written,
+        )?;
+        writer.flush()?;
+    }
+
+    if let Some(chunk) = buffer.finish() {
+        write_chunk(
+            &mut writer,
+            &format(&chunk.text, chunk.first_line)?,
+            crlf.unwrap_or(false),
+            &mut written,
+        )?;
+    }
+    Ok(seen_input)
+}
+
+/// Write formatted statements, keeping the blank line the formatter puts
+/// between statements. Chunks that format to nothing (a stray `;` under
+/// `--fix`) are dropped.
+fn write_chunk<W: Write>(
+    writer: &mut W,
+    formatted: &str,
+    crlf: bool,
+    written: &mut bool,
+) -> io::Result<()> {
+    if formatted.trim().is_empty() {
+        return Ok(());
+    }
+    let newline = if crlf { "\r\n" } else { "\n" };
+    if *written {
+        writer.write_all(newline.as_bytes())?;
+    }
+    writer.write_all(formatted.replace('\n', newline).as_bytes())?;
+    *written = true;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_sql_indent::{FormatOptions, FormatStyle, format_sql};
+    use std::cell::RefCell;
+
+    fn stream(input: &str, options: FormatOptions) -> (String, Vec<(String, usize)>) {
+        let chunks = RefCell::new(Vec::new());
+        let mut output = Vec::new();
+        let dialect = options.dialect;
+        run(
+            input.as_bytes(),
+            &mut output,
+            dialect,
+            Encoding::Auto,
+            LineEndings::Auto,
+            |sql, first_line| {
+                chunks.borrow_mut().push((sql.to_string(), first_line));
+                Ok(format!("{}\n", format_sql(sql, &options)))
+            },
+        )
+        .unwrap();
+        (String::from_utf8(output).unwrap(), chunks.into_inner())
+    }
+
+    #[test]
+    fn test_formats_each_statement_as_it_ends() {
+        let input = "select a from t;\nselect 'x;\ny' from u; -- note\nselect 3";
+        let (output, chunks) = stream(input, FormatOptions::default());
+        assert_eq!(
+            chunks,
+            vec![
+                ("select a from t;".to_string(), 1),
+                ("\nselect 'x;\ny' from u;".to_string(), 1),
+                (" -- note\nselect 3".to_string(), 3)
+            ]
+        );
+        let options = FormatOptions::default();
+        assert_eq!(output, format!("{}\n", format_sql(input, &options)));
+    }
+
+    #[test]
+    fn test_keeps_crlf_line_endings() {
+        let input = "select a from t;\r\nselect 'x\r\ny' from u;\r\n";
+        let (output, chunks) = stream(input, FormatOptions::default());
+        assert!(chunks.iter().all(|(sql, _)| !sql.contains('\r')));
+        let expected = format_sql(&input.replace("\r\n", "\n"), &FormatOptions::default());
+        assert_eq!(output, format!("{}\n", expected).replace('\n', "\r\n"));
+    }
+
+    #[test]
+    fn test_buffers_procedural_blocks() {
+        let input = "select 1;\nbegin\n  let x := 1;\n  return x;\nend;\nselect 2;\n";
+        let options = FormatOptions {
+            dialect: Dialect::Snowflake,
+            ..FormatOptions::default()
+        };
+        let (_, chunks) = stream(input, options);
+        assert_eq!(
+            chunks,
+            vec![
+                ("select 1;".to_string(), 1),
+                (
+                    "\nbegin\n  let x := 1;\n  return x;\nend;\nselect 2;".to_string(),
+                    1
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches_whole_input_formatting() {
+        for entry in std::fs::read_dir("tests/fixtures").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "sql") {
+                continue;
+            }
+            let input = std::fs::read_to_string(&path).unwrap();
+            let style = path.file_name().unwrap().to_str().unwrap();
+            let style: FormatStyle = style.split("__").next().unwrap().parse().unwrap();
+            for dialect in [
+                Dialect::Generic,
+                Dialect::Snowflake,
+                Dialect::Tsql,
+                Dialect::Oracle,
+            ] {
+                let options = FormatOptions {
+                    style,
+                    dialect,
+                    ..FormatOptions::default()
+                };
+                let expected = format!("{}\n", format_sql(&input, &options));
+                assert_eq!(
+                    stream(&input, options).0,
+                    expected,
+                    "{} ({})",
+                    path.display(),
+                    dialect
+                );
+            }
+        }
+    }
+}
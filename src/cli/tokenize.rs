@@ -0,0 +1,60 @@
+//! `tokenize` subcommand: dump the lexer's token stream as JSON lines.
+
+use std::path::PathBuf;
+
+use clap::Args;
+use rs_sql_indent::token::Token;
+use rs_sql_indent::{Dialect, lexer};
+
+use super::report::json_string;
+
+#[derive(Args)]
+pub struct TokenizeArgs {
+    /// SQL file to tokenize. Reads stdin when omitted
+    pub file: Option<PathBuf>,
+
+    /// Leave whitespace tokens out of the dump
+    #[arg(long)]
+    pub skip_whitespace: bool,
+}
+
+/// One JSON object per token: kind, source text, byte offset and 1-based
+/// line and column (in characters).
+pub fn run(args: &TokenizeArgs, input: &str, dialect: Dialect) -> String {
+    let mut out = String::new();
+    for spanned in lexer::tokenize_spanned(input, dialect) {
+        let span = spanned.span;
+        if args.skip_whitespace && matches!(spanned.token, Token::Whitespace(_)) {
+            continue;
+        }
+        out.push_str(&format!(
+            "{{\"kind\":\"{}\",\"text\":{},\"offset\":{},\"line\":{},\"column\":{}}}\n",
+            spanned.token.kind_name(),
+            json_string(&input[span.start..span.end]),
+            span.start,
+            span.line,
+            span.column
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions() {
+        let args = TokenizeArgs {
+            file: None,
+            skip_whitespace: true,
+        };
+        assert_eq!(
+            run(&args, "select\n  'é', x", Dialect::Generic),
+            "{\"kind\":\"Keyword\",\"text\":\"select\",\"offset\":0,\"line\":1,\"column\":1}\n\
+             {\"kind\":\"StringLiteral\",\"text\":\"'é'\",\"offset\":9,\"line\":2,\"column\":3}\n\
+             {\"kind\":\"Comma\",\"text\":\",\",\"offset\":13,\"line\":2,\"column\":6}\n\
+             {\"kind\":\"Identifier\",\"text\":\"x\",\"offset\":15,\"line\":2,\"column\":8}\n"
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! `--verify`: make sure formatting only changed whitespace and keyword case
+//! by comparing the token streams before and after.
+
+use rs_sql_indent::compare::first_difference;
+use rs_sql_indent::token::Token;
+use rs_sql_indent::{Dialect, lexer};
+
+/// Compare the significant tokens of `expected` with those of `output`.
+/// On mismatch, describe the first token that differs.
+pub fn check(expected: &[Token<'_>], output: &str, dialect: Dialect) -> Result<(), String> {
+    let spanned = lexer::tokenize_spanned(output, dialect);
+    let actual: Vec<Token<'_>> = spanned.iter().map(|t| t.token.clone()).collect();
+    let Some(difference) = first_difference(expected, &actual) else {
+        return Ok(());
+    };
+    let index = difference.position;
+    match (difference.left, difference.right) {
+        (Some(e), Some(a)) => Err(format!(
+            "token {} differs: expected {:?}, output has {:?} at line {}",
+            index, expected[e], actual[a], spanned[a].span.line
+        )),
+        (Some(e), None) => Err(format!(
+            "token {} missing from output: expected {:?}",
+            index, expected[e]
+        )),
+        (None, Some(a)) => Err(format!(
+            "unexpected token {} in output: {:?} at line {}",
+            index, actual[a], spanned[a].span.line
+        )),
+        (None, None) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_sql_indent::lexer::tokenize;
+
+    #[test]
+    fn test_whitespace_and_keyword_case_ignored() {
+        let expected = tokenize("select a,b from t");
+        assert_eq!(
+            check(
+                &expected,
+                "SELECT\n    a,\n    b\nFROM\n    t",
+                Dialect::Generic
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_reports_first_difference() {
+        let expected = tokenize("select a from t");
+        assert_eq!(
+            check(&expected, "SELECT\n    b\nFROM\n    t", Dialect::Generic),
+            Err(
+                "token 2 differs: expected Identifier(\"a\"), output has Identifier(\"b\") at line 2"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            check(&expected, "SELECT a", Dialect::Generic),
+            Err("token 3 missing from output: expected Keyword(From)".to_string())
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Expand the paths given on the command line into the SQL files to format.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use super::glob::Pattern;
+use super::ignore::IgnoreFile;
+
+/// Files named on the command line are taken as-is. Directories are searched
+/// recursively for files with one of the given extensions (`sql` for SQL
+/// files), skipping hidden entries, anything that matches one of `excludes`
+/// (relative to the directory given) and anything ignored by a
+/// `.sqlindentignore` file in the searched directories.
+pub fn collect_files(
+    paths: &[PathBuf],
+    excludes: &[Pattern],
+    extensions: &[&str],
+) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(
+                path,
+                path,
+                excludes,
+                extensions,
+                &mut Vec::new(),
+                &mut files,
+            )?;
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// `ignores` holds the ignore files of `dir` and its ancestors up to `root`,
+/// outermost first, each with the directory its rules are relative to.
+fn walk(
+    root: &Path,
+    dir: &Path,
+    excludes: &[Pattern],
+    extensions: &[&str],
+    ignores: &mut Vec<(PathBuf, IgnoreFile)>,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let loaded = match IgnoreFile::load(dir)? {
+        Some(ignore) => {
+            ignores.push((dir.to_path_buf(), ignore));
+            true
+        }
+        None => false,
+    };
+
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let is_dir = path.is_dir();
+        if hidden
+            || excludes.iter().any(|p| p.matches(relative))
+            || is_ignored(ignores, &path, is_dir)
+        {
+            continue;
+        }
+        if is_dir {
+            walk(root, &path, excludes, extensions, ignores, files)?;
+        } else if has_extension(&path, extensions) {
+            files.push(path);
+        }
+    }
+
+    if loaded {
+        ignores.pop();
+    }
+    Ok(())
+}
+
+/// Deeper ignore files override shallower ones, as with `.gitignore`.
+fn is_ignored(ignores: &[(PathBuf, IgnoreFile)], path: &Path, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|(base, ignore)| {
+            let relative = path.strip_prefix(base).unwrap_or(path);
+            ignore.matched(relative, is_dir)
+        })
+        .unwrap_or(false)
+}
+
+pub fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .is_some_and(|ext| extensions.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+}
@@ -0,0 +1,109 @@
+//! `--watch`: poll files for changes and reformat them when they are saved.
+//!
+//! Polling modification times keeps this dependency-free and works the same
+//! on every platform; the interval is short enough for save-to-format use.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use super::glob::Pattern;
+use super::walk;
+
+/// Tracks the last seen modification time of every watched file.
+pub struct Watcher {
+    paths: Vec<PathBuf>,
+    excludes: Vec<Pattern>,
+    extensions: &'static [&'static str],
+    seen: HashMap<PathBuf, SystemTime>,
+}
+
+impl Watcher {
+    /// Start watching `paths`. Files that exist now are recorded as seen, so
+    /// only later saves (or newly created files) are reported.
+    pub fn new(
+        paths: Vec<PathBuf>,
+        excludes: Vec<Pattern>,
+        extensions: &'static [&'static str],
+    ) -> io::Result<Self> {
+        let mut watcher = Self {
+            paths,
+            excludes,
+            extensions,
+            seen: HashMap::new(),
+        };
+        watcher.poll()?;
+        Ok(watcher)
+    }
+
+    /// Return the files created or modified since the previous poll.
+    pub fn poll(&mut self) -> io::Result<Vec<PathBuf>> {
+        let files = walk::collect_files(&self.paths, &self.excludes, self.extensions)?;
+        let mut changed = Vec::new();
+        for path in files {
+            // A file deleted between listing and stat is simply skipped
+            let Ok(modified) = modified(&path) else {
+                continue;
+            };
+            if self.seen.insert(path.clone(), modified) != Some(modified) {
+                changed.push(path);
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Re-read the modification time of `path` after rewriting it, so the
+    /// tool's own write is not reported as a change.
+    pub fn refresh(&mut self, path: &Path) {
+        if let Ok(modified) = modified(path) {
+            self.seen.insert(path.to_path_buf(), modified);
+        }
+    }
+}
+
+fn modified(path: &Path) -> io::Result<SystemTime> {
+    fs::metadata(path)?.modified()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::time::Duration;
+
+    #[test]
+    fn test_reports_modified_and_new_files() {
+        let dir = std::env::temp_dir().join(format!("rs-sql-indent-watch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let a = dir.join("a.sql");
+        fs::write(&a, "select 1").unwrap();
+
+        let mut watcher = Watcher::new(vec![dir.clone()], Vec::new(), &["sql"]).unwrap();
+        assert!(watcher.poll().unwrap().is_empty());
+
+        let later = SystemTime::now() + Duration::from_secs(10);
+        File::options()
+            .write(true)
+            .open(&a)
+            .unwrap()
+            .set_modified(later)
+            .unwrap();
+        let b = dir.join("b.sql");
+        fs::write(&b, "select 2").unwrap();
+        assert_eq!(watcher.poll().unwrap(), vec![a.clone(), b]);
+
+        File::options()
+            .write(true)
+            .open(&a)
+            .unwrap()
+            .set_modified(later + Duration::from_secs(10))
+            .unwrap();
+        watcher.refresh(&a);
+        assert!(watcher.poll().unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,162 @@
+//! Token-level comparison of SQL: two inputs are equivalent when they lex
+//! to the same tokens, apart from whitespace and the case of keywords,
+//! including the words the formatter treats as keywords, such as `DATE` in
+//! `DATE '2024-01-01'`.
+//! Formatting must never change more than that, which `--verify` checks.
+
+use crate::config::Dialect;
+use crate::formatter;
+use crate::lexer::tokenize_spanned;
+use crate::token::{SpannedToken, Token};
+
+/// Where two token streams first differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenDifference {
+    /// Which token differs, counting from 1 and skipping whitespace
+    pub position: usize,
+    /// Index of the differing token in each stream, `None` in a stream
+    /// that ended first
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+}
+
+/// The first token, whitespace aside, at which `left` and `right` differ.
+pub fn first_difference(left: &[Token<'_>], right: &[Token<'_>]) -> Option<TokenDifference> {
+    let significant = |tokens: &[Token<'_>]| {
+        tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| !matches!(t, Token::Whitespace(_)))
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>()
+    };
+    let (left_indexes, right_indexes) = (significant(left), significant(right));
+    let mut l = left_indexes.into_iter();
+    let mut r = right_indexes.into_iter();
+    for position in 1.. {
+        match (l.next(), r.next()) {
+            (None, None) => return None,
+            (Some(a), Some(b)) if same_token(&left[a], &right[b]) => {}
+            (a, b) => {
+                return Some(TokenDifference {
+                    position,
+                    left: a,
+                    right: b,
+                });
+            }
+        }
+    }
+    unreachable!()
+}
+
+/// Keywords compare by kind; batch separators (`GO`) and identifiers the
+/// formatter may recase follow the keyword case.
+fn same_token(left: &Token<'_>, right: &Token<'_>) -> bool {
+    match (left, right) {
+        (Token::BatchSeparator(a), Token::BatchSeparator(b)) => a.eq_ignore_ascii_case(b),
+        (Token::Identifier(a), Token::Identifier(b)) if formatter::takes_keyword_case(a) => {
+            a.eq_ignore_ascii_case(b)
+        }
+        _ => left == right,
+    }
+}
+
+/// The first difference between two SQL texts, with the differing tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticDifference<'a> {
+    /// Which token differs, counting from 1 and skipping whitespace
+    pub position: usize,
+    /// The differing token of each text, `None` for a text that ended first
+    pub left: Option<SpannedToken<'a>>,
+    pub right: Option<SpannedToken<'a>>,
+}
+
+/// Whether `left` and `right` are the same SQL, differing at most in
+/// whitespace and keyword case, as formatting one gives the other. Comments
+/// and the case of identifiers count, except for the words the formatter
+/// writes in keyword case.
+pub fn semantic_equal<'a>(
+    left: &'a str,
+    right: &'a str,
+    dialect: Dialect,
+) -> Result<(), SemanticDifference<'a>> {
+    let left = tokenize_spanned(left, dialect);
+    let right = tokenize_spanned(right, dialect);
+    let tokens =
+        |spanned: &[SpannedToken<'a>]| spanned.iter().map(|t| t.token.clone()).collect::<Vec<_>>();
+    match first_difference(&tokens(&left), &tokens(&right)) {
+        None => Ok(()),
+        Some(d) => Err(SemanticDifference {
+            position: d.position,
+            left: d.left.map(|i| left[i].clone()),
+            right: d.right.map(|i| right[i].clone()),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::token::{KeywordKind, Span};
+
+    #[test]
+    fn test_whitespace_and_keyword_case_ignored() {
+        assert_eq!(
+            semantic_equal(
+                "select a,b from t",
+                "SELECT\n    a,\n    b\nFROM\n    t",
+                Dialect::Generic
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            semantic_equal("select 1\ngo", "SELECT\n    1\nGO", Dialect::Tsql),
+            Ok(())
+        );
+        assert_eq!(
+            semantic_equal(
+                "select date '2024-01-01' + interval 7 day",
+                "SELECT DATE '2024-01-01' + INTERVAL 7 DAY",
+                Dialect::Mysql
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(
+            semantic_equal("select a from t", "SELECT\n  A FROM t", Dialect::Generic),
+            Err(SemanticDifference {
+                position: 2,
+                left: Some(SpannedToken {
+                    token: Token::Identifier("a"),
+                    span: Span {
+                        start: 7,
+                        end: 8,
+                        line: 1,
+                        column: 8
+                    }
+                }),
+                right: Some(SpannedToken {
+                    token: Token::Identifier("A"),
+                    span: Span {
+                        start: 9,
+                        end: 10,
+                        line: 2,
+                        column: 3
+                    }
+                }),
+            })
+        );
+        let left = tokenize("select a from t");
+        let difference = first_difference(&left, &tokenize("select a")).unwrap();
+        assert_eq!(difference.position, 3);
+        assert_eq!(
+            difference.left.map(|i| &left[i]),
+            Some(&Token::Keyword(KeywordKind::From))
+        );
+        assert_eq!(difference.right, None);
+    }
+}
@@ -1,22 +1,50 @@
+//! Formatting options: the style, the dialect and the smaller settings of
+//! [`FormatOptions`], plus the named [`Preset`]s combining them.
+
 use std::fmt;
+use std::str::FromStr;
+
+use crate::formatter::CustomStyle;
+use crate::token::KeywordKind;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+/// The layout of the formatted SQL. See [`FormatStyle::description`].
+/// Styles implemented outside this crate run as [`FormatStyle::Custom`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
 pub enum FormatStyle {
     #[default]
     Basic,
     Streamline,
     Aligned,
     Dataops,
+    /// A style implemented outside this crate. It has no name on the command
+    /// line or in configuration files.
+    #[cfg_attr(feature = "cli", value(skip))]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomStyle),
 }
 
 impl FormatStyle {
-    pub fn from_name(name: &str) -> Self {
-        match name {
-            "basic" => FormatStyle::Basic,
-            "streamline" => FormatStyle::Streamline,
-            "aligned" => FormatStyle::Aligned,
-            "dataops" => FormatStyle::Dataops,
-            _ => FormatStyle::Basic,
+    /// Every built-in style, in the order the CLI lists them.
+    pub fn all() -> &'static [FormatStyle] {
+        &[
+            FormatStyle::Basic,
+            FormatStyle::Streamline,
+            FormatStyle::Aligned,
+            FormatStyle::Dataops,
+        ]
+    }
+
+    /// A one-line summary of the layout, e.g. for a style picker.
+    pub fn description(self) -> &'static str {
+        match self {
+            FormatStyle::Basic => "4-space indent, trailing commas",
+            FormatStyle::Streamline => "2-space indent, trailing commas",
+            FormatStyle::Aligned => "Right-aligned keywords, leading commas",
+            FormatStyle::Dataops => "4-space indent, leading commas",
+            FormatStyle::Custom(style) => style.description,
         }
     }
 }
@@ -28,14 +56,198 @@ impl fmt::Display for FormatStyle {
             FormatStyle::Streamline => write!(f, "streamline"),
             FormatStyle::Aligned => write!(f, "aligned"),
             FormatStyle::Dataops => write!(f, "dataops"),
+            FormatStyle::Custom(style) => f.write_str(style.name),
+        }
+    }
+}
+
+impl FromStr for FormatStyle {
+    type Err = String;
+
+    /// Parse a style name as written by `Display`. The error lists the
+    /// valid names.
+    fn from_str(name: &str) -> Result<Self, String> {
+        match FormatStyle::all().iter().find(|s| s.to_string() == name) {
+            Some(&style) => Ok(style),
+            None => {
+                let names: Vec<String> = FormatStyle::all().iter().map(|s| s.to_string()).collect();
+                Err(format!(
+                    "unknown style `{}`, expected one of: {}",
+                    name,
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+/// The SQL dialect, which decides how the lexer reads quotes, variables and
+/// statement terminators. `Generic` accepts what most databases share.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Dialect {
+    #[default]
+    Generic,
+    Snowflake,
+    Bigquery,
+    Tsql,
+    Oracle,
+    Spark,
+    #[cfg_attr(feature = "cli", value(alias = "postgresql"))]
+    #[cfg_attr(feature = "serde", serde(alias = "postgresql"))]
+    Postgres,
+    Mysql,
+    Sqlite,
+}
+
+/// Keywords only some dialects reserve. Elsewhere the word is read as a
+/// plain name, so a column called `top` or `qualify` formats as one.
+const DIALECT_KEYWORDS: &[(KeywordKind, &[Dialect])] = &[
+    (KeywordKind::Top, &[Dialect::Tsql]),
+    (
+        KeywordKind::Qualify,
+        &[Dialect::Snowflake, Dialect::Bigquery],
+    ),
+];
+
+impl Dialect {
+    /// Whether the dialect reads `kw` as a keyword. Keywords missing from
+    /// its dialect-specific table are reserved everywhere.
+    pub fn has_keyword(self, kw: KeywordKind) -> bool {
+        DIALECT_KEYWORDS
+            .iter()
+            .find(|(keyword, _)| *keyword == kw)
+            .is_none_or(|(_, dialects)| dialects.contains(&self))
+    }
+
+    /// Whether `` `name` `` is a quoted identifier rather than stray punctuation.
+    pub fn supports_backtick_identifiers(self) -> bool {
+        matches!(
+            self,
+            Dialect::Bigquery | Dialect::Spark | Dialect::Mysql | Dialect::Sqlite
+        )
+    }
+
+    /// Whether `[name]` is a quoted identifier rather than a subscript.
+    pub fn supports_bracket_identifiers(self) -> bool {
+        self == Dialect::Tsql
+    }
+
+    /// Whether `$$text$$` is a string literal. PostgreSQL also allows a tag
+    /// between the dollars: `$body$text$body$`.
+    pub fn supports_dollar_strings(self) -> bool {
+        matches!(self, Dialect::Snowflake | Dialect::Postgres)
+    }
+
+    /// Whether `E'text'` is a string in which a backslash escapes the next
+    /// character, quotes included.
+    pub fn supports_escape_strings(self) -> bool {
+        self == Dialect::Postgres
+    }
+
+    /// Whether a backslash escapes the next character, quotes included, in
+    /// plain `'text'` strings: `'it\'s'`.
+    pub fn supports_backslash_escapes(self) -> bool {
+        matches!(self, Dialect::Mysql | Dialect::Bigquery | Dialect::Spark)
+    }
+
+    /// Whether `BEGIN ... END` at statement level delimits a scripting block
+    /// whose statements are indented one level.
+    pub fn has_scripting_blocks(self) -> bool {
+        matches!(self, Dialect::Bigquery | Dialect::Tsql)
+    }
+}
+
+impl fmt::Display for Dialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dialect::Generic => write!(f, "generic"),
+            Dialect::Snowflake => write!(f, "snowflake"),
+            Dialect::Bigquery => write!(f, "bigquery"),
+            Dialect::Tsql => write!(f, "tsql"),
+            Dialect::Oracle => write!(f, "oracle"),
+            Dialect::Spark => write!(f, "spark"),
+            Dialect::Postgres => write!(f, "postgres"),
+            Dialect::Mysql => write!(f, "mysql"),
+            Dialect::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+impl FromStr for Dialect {
+    type Err = String;
+
+    /// Parse a dialect name as written by `Display`, or `postgresql`.
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "generic" => Ok(Dialect::Generic),
+            "snowflake" => Ok(Dialect::Snowflake),
+            "bigquery" => Ok(Dialect::Bigquery),
+            "tsql" => Ok(Dialect::Tsql),
+            "oracle" => Ok(Dialect::Oracle),
+            "spark" => Ok(Dialect::Spark),
+            "postgres" | "postgresql" => Ok(Dialect::Postgres),
+            "mysql" => Ok(Dialect::Mysql),
+            "sqlite" => Ok(Dialect::Sqlite),
+            _ => Err(format!("unknown dialect `{}`", name)),
+        }
+    }
+}
+
+/// How bind placeholders are written, for moving queries between drivers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum PlaceholderStyle {
+    /// `?`, as JDBC, ODBC and MySQL drivers take them
+    Question,
+    /// `$1`, `$2`, ..., as PostgreSQL takes them
+    Dollar,
+    /// `:name`; positional placeholders become `:p1`, `:p2`, ...
+    Colon,
+}
+
+impl fmt::Display for PlaceholderStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaceholderStyle::Question => write!(f, "question"),
+            PlaceholderStyle::Dollar => write!(f, "dollar"),
+            PlaceholderStyle::Colon => write!(f, "colon"),
         }
     }
 }
 
+impl FromStr for PlaceholderStyle {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "question" => Ok(PlaceholderStyle::Question),
+            "dollar" => Ok(PlaceholderStyle::Dollar),
+            "colon" => Ok(PlaceholderStyle::Colon),
+            _ => Err(format!("unknown placeholder style `{}`", name)),
+        }
+    }
+}
+
+/// With the `serde` feature, missing fields deserialize to their defaults,
+/// as missing keys of a configuration file do.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct FormatOptions {
     pub uppercase: bool,
     pub style: FormatStyle,
+    pub dialect: Dialect,
+    /// Spaces per indentation level. `None` keeps the style's own width.
+    /// The aligned style lines clauses up on their keywords and uses it only
+    /// for the content of procedural and template blocks.
+    pub indent_width: Option<usize>,
+    /// Rewrite bind placeholders in this style. `None` keeps them as written.
+    pub placeholders: Option<PlaceholderStyle>,
 }
 
 impl Default for FormatOptions {
@@ -43,7 +255,145 @@ impl Default for FormatOptions {
         Self {
             uppercase: true,
             style: FormatStyle::Basic,
+            dialect: Dialect::Generic,
+            indent_width: None,
+            placeholders: None,
+        }
+    }
+}
+
+impl FormatOptions {
+    /// Start building options from the defaults. Unlike a struct literal,
+    /// builder calls keep compiling as options are added.
+    pub fn builder() -> FormatOptionsBuilder {
+        FormatOptionsBuilder::default()
+    }
+}
+
+/// Widest indentation level accepted by [`validate_indent_width`].
+pub const MAX_INDENT_WIDTH: usize = 16;
+
+/// Check an indent width from any source of options: the builder, config
+/// files, environment variables and directives all go through here, so a
+/// width of 0 or one large enough to exhaust memory is rejected up front.
+pub fn validate_indent_width(width: usize) -> Result<usize, String> {
+    if !(1..=MAX_INDENT_WIDTH).contains(&width) {
+        return Err(format!(
+            "indent width must be between 1 and {}, got {}",
+            MAX_INDENT_WIDTH, width
+        ));
+    }
+    Ok(width)
+}
+
+/// Builds [`FormatOptions`], checking the values on [`build`](Self::build):
+///
+/// ```
+/// use rs_sql_indent::{FormatOptions, FormatStyle};
+///
+/// let options = FormatOptions::builder()
+///     .style(FormatStyle::Aligned)
+///     .indent_width(4)
+///     .build()
+///     .unwrap();
+/// assert_eq!(options.indent_width, Some(4));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptionsBuilder {
+    options: FormatOptions,
+}
+
+impl FormatOptionsBuilder {
+    pub fn style(mut self, style: FormatStyle) -> Self {
+        self.options.style = style;
+        self
+    }
+
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.options.dialect = dialect;
+        self
+    }
+
+    /// Uppercase (the default) or lowercase keywords.
+    pub fn uppercase(mut self, uppercase: bool) -> Self {
+        self.options.uppercase = uppercase;
+        self
+    }
+
+    /// Spaces per indentation level, from 1 to [`MAX_INDENT_WIDTH`].
+    pub fn indent_width(mut self, width: usize) -> Self {
+        self.options.indent_width = Some(width);
+        self
+    }
+
+    /// Rewrite bind placeholders (`?`, `$1`, `:name`) in `style`.
+    pub fn placeholders(mut self, style: PlaceholderStyle) -> Self {
+        self.options.placeholders = Some(style);
+        self
+    }
+
+    /// Take the style, keyword case and indent width of `preset`; later
+    /// calls override them.
+    pub fn preset(mut self, preset: &Preset) -> Self {
+        preset.apply(&mut self.options);
+        self
+    }
+
+    pub fn build(self) -> Result<FormatOptions, String> {
+        if let Some(width) = self.options.indent_width {
+            validate_indent_width(width)?;
         }
+        Ok(self.options)
+    }
+}
+
+/// A named bundle of style, keyword case and indent width approximating
+/// another formatter's output. Commas follow the style: `dataops` and
+/// `aligned` lead with them, the others trail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub style: FormatStyle,
+    pub uppercase: bool,
+    pub indent_width: Option<usize>,
+}
+
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "pgformatter",
+        description: "pgFormatter defaults: uppercase keywords, 4-space indent, trailing commas",
+        style: FormatStyle::Basic,
+        uppercase: true,
+        indent_width: Some(4),
+    },
+    Preset {
+        name: "sqlfluff",
+        description: "sqlfluff with lowercase keywords: 4-space indent, trailing commas",
+        style: FormatStyle::Basic,
+        uppercase: false,
+        indent_width: Some(4),
+    },
+    Preset {
+        name: "compact",
+        description: "Uppercase keywords, 2-space indent, trailing commas",
+        style: FormatStyle::Streamline,
+        uppercase: true,
+        indent_width: Some(2),
+    },
+];
+
+impl Preset {
+    pub fn find(name: &str) -> Option<&'static Preset> {
+        PRESETS.iter().find(|p| p.name == name)
+    }
+
+    /// Overwrite the style, keyword case and indent width of `options`;
+    /// the dialect is left alone.
+    pub fn apply(&self, options: &mut FormatOptions) {
+        options.style = self.style;
+        options.uppercase = self.uppercase;
+        options.indent_width = self.indent_width;
     }
 }
 
@@ -56,6 +406,8 @@ mod tests {
         let opts = FormatOptions::default();
         assert!(opts.uppercase);
         assert_eq!(opts.style, FormatStyle::Basic);
+        assert_eq!(opts.dialect, Dialect::Generic);
+        assert_eq!(opts.indent_width, None);
     }
 
     #[test]
@@ -80,33 +432,147 @@ mod tests {
     }
 
     #[test]
-    fn test_format_style_from_name_valid() {
-        assert_eq!(FormatStyle::from_name("basic"), FormatStyle::Basic);
+    fn test_format_style_from_str() {
+        for &style in FormatStyle::all() {
+            assert_eq!(style.to_string().parse(), Ok(style));
+        }
         assert_eq!(
-            FormatStyle::from_name("streamline"),
-            FormatStyle::Streamline
+            "BASIC".parse::<FormatStyle>(),
+            Err(
+                "unknown style `BASIC`, expected one of: basic, streamline, aligned, dataops"
+                    .to_string()
+            )
         );
-        assert_eq!(FormatStyle::from_name("aligned"), FormatStyle::Aligned);
-        assert_eq!(FormatStyle::from_name("dataops"), FormatStyle::Dataops);
+        assert!("".parse::<FormatStyle>().is_err());
     }
 
     #[test]
-    fn test_format_style_from_name_unknown_falls_back_to_basic() {
-        assert_eq!(FormatStyle::from_name("unknown"), FormatStyle::Basic);
-        assert_eq!(FormatStyle::from_name(""), FormatStyle::Basic);
-        assert_eq!(FormatStyle::from_name("Basic"), FormatStyle::Basic);
-        assert_eq!(FormatStyle::from_name("BASIC"), FormatStyle::Basic);
+    fn test_format_style_all() {
+        assert_eq!(FormatStyle::all().len(), 4);
+        assert_eq!(FormatStyle::all()[0], FormatStyle::default());
+        assert!(
+            FormatStyle::all()
+                .iter()
+                .all(|s| !s.description().is_empty())
+        );
     }
 
     #[test]
-    fn test_format_style_from_name_display_roundtrip() {
-        for style in [
-            FormatStyle::Basic,
-            FormatStyle::Streamline,
-            FormatStyle::Aligned,
-            FormatStyle::Dataops,
+    fn test_preset_apply_keeps_dialect() {
+        let mut options = FormatOptions {
+            dialect: Dialect::Postgres,
+            ..FormatOptions::default()
+        };
+        Preset::find("sqlfluff").unwrap().apply(&mut options);
+        assert_eq!(
+            options,
+            FormatOptions {
+                uppercase: false,
+                style: FormatStyle::Basic,
+                dialect: Dialect::Postgres,
+                indent_width: Some(4),
+                placeholders: None,
+            }
+        );
+        assert!(Preset::find("prettier").is_none());
+    }
+
+    #[test]
+    fn test_builder() {
+        assert_eq!(
+            FormatOptions::builder().build(),
+            Ok(FormatOptions::default())
+        );
+        assert_eq!(
+            FormatOptions::builder()
+                .preset(Preset::find("compact").unwrap())
+                .dialect(Dialect::Snowflake)
+                .uppercase(false)
+                .placeholders(PlaceholderStyle::Dollar)
+                .build(),
+            Ok(FormatOptions {
+                uppercase: false,
+                style: FormatStyle::Streamline,
+                dialect: Dialect::Snowflake,
+                indent_width: Some(2),
+                placeholders: Some(PlaceholderStyle::Dollar),
+            })
+        );
+        assert_eq!(
+            FormatOptions::builder().indent_width(0).build(),
+            Err("indent width must be between 1 and 16, got 0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_indent_width() {
+        assert_eq!(validate_indent_width(1), Ok(1));
+        assert_eq!(validate_indent_width(MAX_INDENT_WIDTH), Ok(16));
+        assert!(validate_indent_width(0).is_err());
+        assert!(validate_indent_width(100_000_000_000).is_err());
+        for preset in PRESETS {
+            if let Some(width) = preset.indent_width {
+                assert_eq!(validate_indent_width(width), Ok(width), "{}", preset.name);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dialect_keywords() {
+        assert!(Dialect::Tsql.has_keyword(KeywordKind::Top));
+        assert!(!Dialect::Generic.has_keyword(KeywordKind::Top));
+        assert!(Dialect::Snowflake.has_keyword(KeywordKind::Qualify));
+        assert!(Dialect::Bigquery.has_keyword(KeywordKind::Qualify));
+        assert!(!Dialect::Postgres.has_keyword(KeywordKind::Qualify));
+        assert!(Dialect::Postgres.has_keyword(KeywordKind::Select));
+    }
+
+    #[test]
+    fn test_dialect_from_str_display_roundtrip() {
+        for dialect in [
+            Dialect::Generic,
+            Dialect::Snowflake,
+            Dialect::Bigquery,
+            Dialect::Tsql,
+            Dialect::Oracle,
+            Dialect::Spark,
+            Dialect::Postgres,
+            Dialect::Mysql,
+            Dialect::Sqlite,
         ] {
-            assert_eq!(FormatStyle::from_name(&style.to_string()), style);
+            assert_eq!(dialect.to_string().parse(), Ok(dialect));
         }
+        assert_eq!("postgresql".parse(), Ok(Dialect::Postgres));
+        assert_eq!(
+            "unknown".parse::<Dialect>(),
+            Err("unknown dialect `unknown`".to_string())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_names_match_display() {
+        use serde::Deserialize;
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{Error, MapDeserializer};
+
+        for style in [FormatStyle::Dataops, FormatStyle::Aligned] {
+            let name = style.to_string();
+            let parsed = FormatStyle::deserialize(name.as_str().into_deserializer());
+            assert_eq!(parsed, Ok::<_, Error>(style));
+        }
+        let parsed = Dialect::deserialize("postgresql".into_deserializer());
+        assert_eq!(parsed, Ok::<_, Error>(Dialect::Postgres));
+        let parsed = PlaceholderStyle::deserialize("dollar".into_deserializer());
+        assert_eq!(parsed, Ok::<_, Error>(PlaceholderStyle::Dollar));
+
+        let map = MapDeserializer::<_, Error>::new([("dialect", "mysql")].into_iter());
+        assert_eq!(
+            FormatOptions::deserialize(map),
+            Ok(FormatOptions {
+                dialect: Dialect::Mysql,
+                ..FormatOptions::default()
+            })
+        );
     }
 }
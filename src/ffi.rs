@@ -0,0 +1,187 @@
+//! C bindings, built with the `ffi` feature into the crate's `cdylib`:
+//!
+//! ```c
+//! char *rs_sql_indent_format(const char *input, size_t input_len,
+//!                            const char *style, const char *dialect,
+//!                            bool uppercase, size_t *output_len);
+//! void rs_sql_indent_free(char *output, size_t output_len);
+//! ```
+//!
+//! Strings go in as pointer and length and come back NUL-terminated, with
+//! their length (the NUL not counted) written to `output_len`. The output
+//! can hold NUL bytes of the input, so that length, not `strlen`, is what
+//! `rs_sql_indent_free` must be given.
+
+use std::ffi::{CStr, c_char};
+use std::{ptr, slice};
+
+use crate::config::{Dialect, FormatOptions, FormatStyle};
+use crate::format_sql;
+
+/// Format the `input_len` bytes of UTF-8 SQL at `input`. `style` and
+/// `dialect` are NUL-terminated names as for `--style` and `--dialect`, or
+/// NULL for the defaults. Returns NULL when the input is not UTF-8, a name
+/// is unknown or `output_len` is NULL; the output must be released with
+/// [`rs_sql_indent_free`] and the length written to `output_len`.
+///
+/// # Safety
+///
+/// `input` must point to `input_len` readable bytes, `style` and `dialect`
+/// must each be NULL or a NUL-terminated string, and `output_len` must be
+/// NULL or writable.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_sql_indent_format(
+    input: *const c_char,
+    input_len: usize,
+    style: *const c_char,
+    dialect: *const c_char,
+    uppercase: bool,
+    output_len: *mut usize,
+) -> *mut c_char {
+    if output_len.is_null() {
+        return ptr::null_mut();
+    }
+    let bytes = if input_len == 0 {
+        &[][..]
+    } else {
+        // SAFETY: the caller guarantees `input_len` readable bytes
+        unsafe { slice::from_raw_parts(input.cast::<u8>(), input_len) }
+    };
+    let Ok(input) = std::str::from_utf8(bytes) else {
+        return ptr::null_mut();
+    };
+    // SAFETY: the caller guarantees NULL or a NUL-terminated string
+    let name = |p: *const c_char| (!p.is_null()).then(|| unsafe { CStr::from_ptr(p) });
+    let style = match name(style).map(|s| s.to_string_lossy().parse()) {
+        None => FormatStyle::default(),
+        Some(Ok(style)) => style,
+        Some(Err(_)) => return ptr::null_mut(),
+    };
+    let dialect = match name(dialect).map(|s| s.to_string_lossy().parse()) {
+        None => Dialect::default(),
+        Some(Ok(dialect)) => dialect,
+        Some(Err(_)) => return ptr::null_mut(),
+    };
+    let options = FormatOptions {
+        uppercase,
+        style,
+        dialect,
+        ..FormatOptions::default()
+    };
+    let mut output = format_sql(input, &options).into_bytes();
+    // SAFETY: checked for NULL above, and the caller guarantees writable
+    unsafe { *output_len = output.len() };
+    output.push(0);
+    Box::into_raw(output.into_boxed_slice()).cast::<c_char>()
+}
+
+/// Release output of [`rs_sql_indent_format`]. NULL is ignored.
+///
+/// # Safety
+///
+/// `output` must be NULL or returned by [`rs_sql_indent_format`] along with
+/// the `output_len` it wrote, and not freed before.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rs_sql_indent_free(output: *mut c_char, output_len: usize) {
+    if output.is_null() {
+        return;
+    }
+    // SAFETY: allocated as a boxed slice of `output_len` bytes plus the NUL
+    drop(unsafe {
+        Box::from_raw(ptr::slice_from_raw_parts_mut(
+            output.cast::<u8>(),
+            output_len + 1,
+        ))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_and_free() {
+        let input = "select a from t";
+        let mut len = 0;
+        let output = unsafe {
+            rs_sql_indent_format(
+                input.as_ptr().cast(),
+                input.len(),
+                c"streamline".as_ptr(),
+                ptr::null(),
+                false,
+                &mut len,
+            )
+        };
+        let text = unsafe { CStr::from_ptr(output) }.to_str().unwrap();
+        assert_eq!(text, "select\n  a\nfrom\n  t");
+        assert_eq!(len, text.len());
+        unsafe { rs_sql_indent_free(output, len) };
+    }
+
+    #[test]
+    fn test_unknown_style() {
+        let output = unsafe {
+            rs_sql_indent_format(
+                c"select 1".as_ptr(),
+                8,
+                c"fancy".as_ptr(),
+                ptr::null(),
+                true,
+                &mut 0,
+            )
+        };
+        assert!(output.is_null());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let input = b"select \xff";
+        let output = unsafe {
+            rs_sql_indent_format(
+                input.as_ptr().cast(),
+                input.len(),
+                ptr::null(),
+                ptr::null(),
+                true,
+                &mut 0,
+            )
+        };
+        assert!(output.is_null());
+        unsafe { rs_sql_indent_free(output, 0) };
+    }
+
+    #[test]
+    fn test_requires_output_len() {
+        let output = unsafe {
+            rs_sql_indent_format(
+                c"select 1".as_ptr(),
+                8,
+                ptr::null(),
+                ptr::null(),
+                true,
+                ptr::null_mut(),
+            )
+        };
+        assert!(output.is_null());
+    }
+
+    #[test]
+    fn test_embedded_nul() {
+        let input = "select '\0'";
+        let mut len = 0;
+        let output = unsafe {
+            rs_sql_indent_format(
+                input.as_ptr().cast(),
+                input.len(),
+                ptr::null(),
+                ptr::null(),
+                true,
+                &mut len,
+            )
+        };
+        let bytes = unsafe { slice::from_raw_parts(output.cast::<u8>(), len) };
+        assert_eq!(bytes, b"SELECT\n    '\0'");
+        unsafe { rs_sql_indent_free(output, len) };
+    }
+}
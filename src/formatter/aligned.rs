@@ -12,6 +12,7 @@ struct AlignedFormatter<'a> {
     between_depth: usize,
     in_cte_header: bool,
     after_leading_comma: bool,
+    after_own_line: bool,
 }
 
 impl<'a> AlignedFormatter<'a> {
@@ -23,6 +24,7 @@ impl<'a> AlignedFormatter<'a> {
             between_depth: 0,
             in_cte_header: false,
             after_leading_comma: false,
+            after_own_line: false,
         }
     }
 
@@ -38,8 +40,15 @@ impl<'a> AlignedFormatter<'a> {
         }
     }
 
+    /// Columns that procedural and template blocks shift their content by.
+    /// Clauses line up on their keywords, so this is the only indentation
+    /// `FormatOptions::indent_width` changes.
+    fn block_offset(&self) -> usize {
+        self.base.indent_width(2) * self.base.block_depth()
+    }
+
     fn write_padding(&mut self, n: usize) {
-        for _ in 0..n {
+        for _ in 0..n + self.block_offset() {
             self.base.output.push(' ');
         }
     }
@@ -47,6 +56,8 @@ impl<'a> AlignedFormatter<'a> {
     fn write_keyword_on_newline(&mut self, kw: KeywordKind) {
         let kw_str = self.base.keyword_str(kw);
         let padding = self.keyword_padding(kw);
+        self.after_own_line = false;
+        self.after_leading_comma = false;
         if !self.base.is_first_token {
             self.base.output.push('\n');
         }
@@ -55,7 +66,19 @@ impl<'a> AlignedFormatter<'a> {
         self.base.is_first_token = false;
     }
 
+    /// Continue on a fresh line aligned with the clause content column.
+    fn try_write_content_newline(&mut self) -> bool {
+        if !self.after_own_line {
+            return false;
+        }
+        self.after_own_line = false;
+        self.base.output.push('\n');
+        self.write_padding(self.base_col + 7);
+        true
+    }
+
     fn write_leading_comma(&mut self) {
+        self.after_own_line = false;
         self.base.output.push('\n');
         self.write_padding(self.base_col + 7);
         self.base.output.push_str(", ");
@@ -132,7 +155,10 @@ impl<'a> AlignedFormatter<'a> {
         self.write_keyword_on_newline(kw);
         self.base.clause_context = match kw {
             KeywordKind::GroupBy => ClauseContext::GroupBy,
-            KeywordKind::OrderBy => ClauseContext::OrderBy,
+            KeywordKind::OrderBy
+            | KeywordKind::DistributeBy
+            | KeywordKind::ClusterBy
+            | KeywordKind::SortBy => ClauseContext::OrderBy,
             _ => ClauseContext::Other,
         };
     }
@@ -155,7 +181,7 @@ impl<'a> AlignedFormatter<'a> {
         if kw == KeywordKind::Between {
             self.between_depth += 1;
         }
-        if self.after_leading_comma {
+        if self.after_leading_comma || self.try_write_content_newline() {
             self.after_leading_comma = false;
             self.base.output.push_str(kw_str);
             self.base.is_first_token = false;
@@ -298,7 +324,7 @@ impl<'a> AlignedFormatter<'a> {
             self.base.is_first_token = false;
             return;
         }
-        if self.after_leading_comma {
+        if self.try_write_content_newline() || self.after_leading_comma {
             self.after_leading_comma = false;
             self.base.output.push_str(text);
             self.base.is_first_token = false;
@@ -321,6 +347,38 @@ impl<'a> SqlFormatter<'a> for AlignedFormatter<'a> {
         &mut self.base
     }
 
+    fn indent_depth(&self) -> usize {
+        self.base_stack.len()
+    }
+
+    fn on_own_line(&mut self) {
+        self.after_own_line = true;
+    }
+
+    fn format_verbatim_block(&mut self, text: &str) {
+        if self.after_leading_comma {
+            // Nothing can follow a leading comma on its line, so it trails
+            // the previous item instead of standing alone
+            let line_start = self.base.output.rfind('\n').unwrap_or(0);
+            self.base.output.truncate(line_start);
+            self.base.output.push(',');
+            self.after_leading_comma = false;
+        }
+        self.base.start_own_line();
+        self.base.output.push_str(text);
+        self.base.is_first_token = false;
+        self.on_own_line();
+    }
+
+    fn on_statement_end(&mut self) {
+        self.base_col = 0;
+        self.after_own_line = false;
+    }
+
+    fn block_indent(&self) -> usize {
+        self.base_col + self.block_offset()
+    }
+
     fn finalize_output(&mut self) -> String {
         let result: String = self.base.output.lines().collect::<Vec<_>>().join("\n");
         let trimmed = result.trim_end().to_string();
@@ -358,11 +416,24 @@ impl<'a> SqlFormatter<'a> for AlignedFormatter<'a> {
     }
 }
 
-pub(crate) fn format<'a>(tokens: &'a [Token<'a>], options: &'a FormatOptions) -> String {
+/// Format `tokens` into `output`, which must be empty.
+pub(crate) fn format<'a>(
+    tokens: &'a [Token<'a>],
+    options: &'a FormatOptions,
+    output: String,
+) -> String {
     let mut formatter = AlignedFormatter::new(tokens, options);
+    formatter.base.output = output;
     formatter.format()
 }
 
+pub(crate) fn explain<'a>(
+    tokens: &'a [Token<'a>],
+    options: &'a FormatOptions,
+) -> Vec<super::ExplainedLine> {
+    AlignedFormatter::new(tokens, options).explain()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::config::{FormatOptions, FormatStyle};
@@ -440,4 +511,73 @@ mod tests {
         let result = fmt("select 1");
         assert!(!result.ends_with('\n'));
     }
+
+    #[test]
+    fn test_directive_comment_on_own_line() {
+        let result = fmt("--changeset alice:1\nselect id from users");
+        assert_eq!(result, "--changeset alice:1\nSELECT id\n  FROM users");
+    }
+
+    #[test]
+    fn test_go_template_control_on_own_line() {
+        let result = fmt(
+            "select id from {{ .Table }} where a = 1 {{ if .Active }} and active = true {{ end }}",
+        );
+        assert_eq!(
+            result,
+            "SELECT id\n  FROM {{ .Table }}\n WHERE a = 1\n{{ if .Active }}\n     AND active = TRUE\n{{ end }}"
+        );
+    }
+
+    #[test]
+    fn test_keyword_after_unformatted_region() {
+        assert_eq!(
+            fmt("select a, -- sql-indent: off\n  b,    c\n-- sql-indent: on\nfrom t"),
+            "SELECT a,\n-- sql-indent: off\n  b,    c\n-- sql-indent: on\n  FROM t"
+        );
+    }
+
+    #[test]
+    fn test_jinja_block_indents_content() {
+        let result = fmt(
+            "select id from events {% if is_incremental() %} where ts > (select max(ts) from {{ this }}) {% endif %}",
+        );
+        assert_eq!(
+            result,
+            "SELECT id\n  FROM events\n{% if is_incremental() %}\n   WHERE ts > (\n    SELECT max(ts)\n      FROM {{ this }}\n    )\n{% endif %}"
+        );
+    }
+
+    #[test]
+    fn test_bigquery_script_block() {
+        let options = FormatOptions {
+            style: FormatStyle::Aligned,
+            dialect: crate::config::Dialect::Bigquery,
+            ..FormatOptions::default()
+        };
+        let tokens = crate::lexer::tokenize_with_dialect(
+            "begin select a, b from t where x = 1; exception when error then select 1; end;",
+            options.dialect,
+        );
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "BEGIN\n  SELECT a\n         , b\n    FROM t\n   WHERE x = 1;\n\nEXCEPTION WHEN ERROR THEN\n  SELECT 1;\n\nEND;"
+        );
+    }
+
+    #[test]
+    fn test_indent_width_shifts_blocks() {
+        let options = FormatOptions {
+            style: FormatStyle::Aligned,
+            dialect: crate::config::Dialect::Bigquery,
+            indent_width: Some(4),
+            ..FormatOptions::default()
+        };
+        let tokens =
+            crate::lexer::tokenize_with_dialect("begin select a, b from t; end;", options.dialect);
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "BEGIN\n    SELECT a\n           , b\n      FROM t;\n\nEND;"
+        );
+    }
 }
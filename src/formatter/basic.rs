@@ -26,9 +26,8 @@ impl<'a> BasicFormatter<'a> {
     }
 
     fn write_indent(&mut self, depth: usize) {
-        for _ in 0..depth {
-            self.base.output.push_str("    ");
-        }
+        let width = (depth + self.base.block_depth()) * self.base.indent_width(4);
+        self.base.output.extend(std::iter::repeat_n(' ', width));
     }
 
     fn write_newline_at(&mut self, depth: usize) {
@@ -193,7 +192,7 @@ impl<'a> BasicFormatter<'a> {
 
         if kw == KeywordKind::GroupBy {
             self.base.clause_context = ClauseContext::GroupBy;
-        } else if kw == KeywordKind::OrderBy {
+        } else if kw.is_order_modifier() {
             self.base.clause_context = ClauseContext::OrderBy;
         }
 
@@ -295,10 +294,17 @@ impl<'a> BasicFormatter<'a> {
     ) {
         let next = filtered.get(idx + 1).copied();
         let is_subquery = matches!(next, Some(Token::Keyword(kw)) if kw.is_clause_starter());
+        // `PIVOT (...)` keeps its parenthesis on the keyword's line
+        let after_pivot = matches!(
+            prev_token,
+            Some(Token::Keyword(KeywordKind::Pivot | KeywordKind::Unpivot))
+        );
 
         if self.needs_indent_newline {
             self.needs_indent_newline = false;
-            self.write_newline_at(self.indent_depth);
+            if !after_pivot {
+                self.write_newline_at(self.indent_depth);
+            }
         }
         if self.needs_space_only {
             self.needs_space_only = false;
@@ -383,6 +389,8 @@ impl<'a> BasicFormatter<'a> {
 
         self.base.output.push('\n');
         self.base.output.push('\n');
+        // Statements inside an open block keep its indentation
+        self.write_indent(0);
 
         self.indent_depth = 0;
         self.base.clause_context = ClauseContext::None;
@@ -448,6 +456,10 @@ impl<'a> SqlFormatter<'a> for BasicFormatter<'a> {
         &mut self.base
     }
 
+    fn indent_depth(&self) -> usize {
+        self.indent_depth
+    }
+
     fn on_comment(&mut self) {
         self.clear_pending_state();
     }
@@ -456,6 +468,19 @@ impl<'a> SqlFormatter<'a> for BasicFormatter<'a> {
         self.after_comma_newline = false;
     }
 
+    fn on_statement_end(&mut self) {
+        self.indent_depth = 0;
+    }
+
+    fn on_own_line(&mut self) {
+        self.clear_pending_state();
+        self.needs_indent_newline = true;
+    }
+
+    fn block_indent(&self) -> usize {
+        (self.indent_depth + self.base.block_depth()) * self.base.indent_width(4)
+    }
+
     fn format_keyword(&mut self, kw: KeywordKind, prev_token: Option<&Token<'a>>) {
         self.do_format_keyword(kw, prev_token);
     }
@@ -486,11 +511,24 @@ impl<'a> SqlFormatter<'a> for BasicFormatter<'a> {
     }
 }
 
-pub(crate) fn format<'a>(tokens: &'a [Token<'a>], options: &'a FormatOptions) -> String {
+/// Format `tokens` into `output`, which must be empty.
+pub(crate) fn format<'a>(
+    tokens: &'a [Token<'a>],
+    options: &'a FormatOptions,
+    output: String,
+) -> String {
     let mut formatter = BasicFormatter::new(tokens, options);
+    formatter.base.output = output;
     formatter.format()
 }
 
+pub(crate) fn explain<'a>(
+    tokens: &'a [Token<'a>],
+    options: &'a FormatOptions,
+) -> Vec<super::ExplainedLine> {
+    BasicFormatter::new(tokens, options).explain()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::config::FormatOptions;
@@ -502,6 +540,72 @@ mod tests {
         format_tokens(&tokens, &FormatOptions::default())
     }
 
+    #[test]
+    fn test_postgres_json_operators() {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Postgres,
+            ..FormatOptions::default()
+        };
+        let sql =
+            "select doc#>>'{a,b}', doc#>'{a}' from t where doc@>'{}' and tags&&other and doc?|keys";
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "SELECT\n    doc#>>'{a,b}',\n    doc#>'{a}'\nFROM\n    t\nWHERE\n    doc @> '{}'\n    \
+             AND tags && other\n    AND doc ?| keys"
+        );
+    }
+
+    #[test]
+    fn test_snowflake_path_stays_attached() {
+        let tokens = crate::lexer::tokenize_with_dialect(
+            "select col:path.field::string, v : \"Key\" from t",
+            crate::config::Dialect::Snowflake,
+        );
+        assert_eq!(
+            format_tokens(&tokens, &FormatOptions::default()),
+            "SELECT\n    col:path.field::string,\n    v:\"Key\"\nFROM\n    t"
+        );
+    }
+
+    #[test]
+    fn test_snowflake_qualify_clause() {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Snowflake,
+            ..FormatOptions::default()
+        };
+        let sql = "select a from t where x = 1 qualify n = 1 order by a";
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "SELECT\n    a\nFROM\n    t\nWHERE\n    x = 1\nQUALIFY\n    n = 1\nORDER BY\n    a"
+        );
+    }
+
+    #[test]
+    fn test_unicode_escape_strings_stay_whole() {
+        assert_eq!(
+            fmt("select U&'d\\0061ta' as a from t where b = u&'x!0061' uescape '!'"),
+            "SELECT\n    U&'d\\0061ta' AS a\nFROM\n    t\nWHERE\n    b = u&'x!0061' uescape '!'"
+        );
+    }
+
+    #[test]
+    fn test_scientific_numbers_stay_attached() {
+        assert_eq!(
+            fmt("select 1e10 * x, 2.5E-3 from t"),
+            "SELECT\n    1e10 * x,\n    2.5E-3\nFROM\n    t"
+        );
+    }
+
+    #[test]
+    fn test_hex_and_bit_literals_stay_attached() {
+        assert_eq!(
+            fmt("update t set flags = b'0101' | 0x1F where id = x'AB'"),
+            "UPDATE\n    t\nSET\n    flags = b'0101' | 0x1F\nWHERE\n    id = x'AB'"
+        );
+    }
+
     #[test]
     fn test_select_columns() {
         let result = fmt("select velocity, color from rockets");
@@ -846,4 +950,382 @@ FROM
             "SELECT\n    *\nFROM\n    t\nWHERE\n    id IN ('a', 'b', 'c')"
         );
     }
+
+    #[test]
+    fn test_liquibase_directives_on_own_lines() {
+        let result = fmt(
+            "--liquibase formatted sql\n--changeset alice:1\ncreate table users (id int);\n--rollback drop table users;",
+        );
+        assert_eq!(
+            result,
+            "--liquibase formatted sql\n--changeset alice:1\nCREATE TABLE users (\n    id int\n);\n\n--rollback drop table users;"
+        );
+    }
+
+    #[test]
+    fn test_flyway_placeholder_preserved() {
+        let result = fmt("select * from ${schema}.users where tenant = ${tenant}");
+        assert_eq!(
+            result,
+            "SELECT\n    *\nFROM\n    ${schema}.users\nWHERE\n    tenant = ${tenant}"
+        );
+    }
+
+    #[test]
+    fn test_erb_tag_preserved() {
+        let result =
+            fmt("select * from users where id in (<%= ids.join(', ') %>) and org = <%= org_id %>");
+        assert_eq!(
+            result,
+            "SELECT\n    *\nFROM\n    users\nWHERE\n    id IN (<%= ids.join(', ') %>)\n    AND org = <%= org_id %>"
+        );
+    }
+
+    #[test]
+    fn test_go_template_control_on_own_line() {
+        let result = fmt(
+            "select id from {{ .Table }} where a = 1 {{- if .Active }} and active = true {{ end -}} order by id",
+        );
+        assert_eq!(
+            result,
+            "SELECT\n    id\nFROM\n    {{ .Table }}\nWHERE\n    a = 1\n    {{- if .Active }}\n        AND active = TRUE\n    {{ end -}}\nORDER BY\n    id"
+        );
+    }
+
+    #[test]
+    fn test_go_template_control_in_select_list() {
+        let result = fmt("select id, {{ if .Full }} name, {{ end }} email from users");
+        assert_eq!(
+            result,
+            "SELECT\n    id,\n    {{ if .Full }}\n        name,\n    {{ end }}\n    email\nFROM\n    users"
+        );
+    }
+
+    #[test]
+    fn test_handlebars_section_tags_aligned() {
+        let result = fmt(
+            "select id from users where org = {{org}} {{#if active}} and active = true {{else}} and deleted_at is null {{/if}}",
+        );
+        assert_eq!(
+            result,
+            r#"SELECT
+    id
+FROM
+    users
+WHERE
+    org = {{org}}
+    {{#if active}}
+        AND active = TRUE
+    {{else}}
+        AND deleted_at IS NULL
+    {{/if}}"#
+        );
+    }
+
+    #[test]
+    fn test_handlebars_close_tag_uses_opening_column() {
+        let result = fmt("select id from users {{#if filter}} where a = 1 {{/if}}");
+        assert_eq!(
+            result,
+            "SELECT\n    id\nFROM\n    users\n    {{#if filter}}\n    WHERE\n        a = 1\n    {{/if}}"
+        );
+    }
+
+    #[test]
+    fn test_jinja_for_block_indents_content() {
+        let result =
+            fmt("select {% for col in cols %} {{ col }}, {% endfor %} id from {{ ref('users') }}");
+        assert_eq!(
+            result,
+            "SELECT\n    {% for col in cols %}\n        {{ col }},\n    {% endfor %}\n    id\nFROM\n    {{ ref('users') }}"
+        );
+    }
+
+    #[test]
+    fn test_jinja_set_and_comment_on_own_lines() {
+        let result =
+            fmt("{% set limit = 10 %} {# keep it small #} select id from users limit {{ limit }}");
+        assert_eq!(
+            result,
+            "{% set limit = 10 %}\n{# keep it small #}\nSELECT\n    id\nFROM\n    users\nLIMIT {{ limit }}"
+        );
+    }
+
+    #[test]
+    fn test_jinja_if_around_clause() {
+        let result =
+            fmt("select id from t {% if filter %} where a = 1 {% else %} where a = 2 {% endif %}");
+        assert_eq!(
+            result,
+            r#"SELECT
+    id
+FROM
+    t
+    {% if filter %}
+    WHERE
+        a = 1
+    {% else %}
+    WHERE
+        a = 2
+    {% endif %}"#
+        );
+    }
+
+    #[test]
+    fn test_jinja_blocks_inside_condition() {
+        let result =
+            fmt("select a, {# why #} b from t where {% if x %} y = 1 {% else %} y = 2 {% endif %}");
+        assert_eq!(
+            result,
+            "SELECT\n    a,\n    {# why #}\n    b\nFROM\n    t\nWHERE\n    {% if x %}\n        y = 1\n    \
+             {% else %}\n        y = 2\n    {% endif %}"
+        );
+    }
+
+    #[test]
+    fn test_duckdb_star_modifiers() {
+        let result = fmt("select * exclude (secret), t.* replace (lower(name) as name) from t");
+        assert_eq!(
+            result,
+            "SELECT\n    * EXCLUDE (secret),\n    t.* REPLACE (lower(name) AS name)\nFROM\n    t"
+        );
+    }
+
+    #[test]
+    fn test_duckdb_from_first() {
+        let result = fmt("from users select id where active");
+        assert_eq!(result, "FROM\n    users\nSELECT\n    id\nWHERE\n    active");
+    }
+
+    #[test]
+    fn test_duckdb_pivot_statement() {
+        let result = fmt("pivot cities on year using sum(population) group by country");
+        assert_eq!(
+            result,
+            "PIVOT\n    cities\n    ON year USING sum(population)\nGROUP BY\n    country"
+        );
+    }
+
+    #[test]
+    fn test_spark_lateral_view_and_distribute_by() {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Spark,
+            ..FormatOptions::default()
+        };
+        let tokens = crate::lexer::tokenize_with_dialect(
+            "select `user id`, item from t lateral view explode(t.items) e as item distribute by a, b sort by b",
+            options.dialect,
+        );
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "SELECT\n    `user id`,\n    item\nFROM\n    t\nLATERAL VIEW explode(t.items) e AS item\nDISTRIBUTE BY\n    a,\n    b\nSORT BY\n    b"
+        );
+    }
+
+    fn fmt_snowflake(sql: &str) -> String {
+        let tokens = crate::lexer::tokenize_with_dialect(sql, crate::config::Dialect::Snowflake);
+        format_tokens(&tokens, &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_snowflake_scripting_block_verbatim() {
+        let result = fmt_snowflake(
+            "declare\n  n int default 0;\nbegin\n  n := 1;\n  return n;\nend;\nselect n from t;",
+        );
+        assert_eq!(
+            result,
+            "declare\n  n int default 0;\nbegin\n  n := 1;\n  return n;\nend;\n\nSELECT\n    n\nFROM\n    t;"
+        );
+    }
+
+    #[test]
+    fn test_snowflake_execute_immediate() {
+        let result = fmt_snowflake("execute immediate $$ select   1 $$;");
+        assert_eq!(result, "execute immediate $$ select   1 $$;");
+    }
+
+    #[test]
+    fn test_snowflake_lateral_flatten_and_ilike() {
+        let result = fmt_snowflake(
+            "select f.value::string from t, lateral flatten(input => t.tags) f where f.value ilike '%a%'",
+        );
+        assert_eq!(
+            result,
+            "SELECT\n    f.value::string\nFROM\n    t, LATERAL flatten(input => t.tags) f\nWHERE\n    f.value ILIKE '%a%'"
+        );
+    }
+
+    fn fmt_bigquery(sql: &str) -> String {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Bigquery,
+            ..FormatOptions::default()
+        };
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        format_tokens(&tokens, &options)
+    }
+
+    #[test]
+    fn test_bigquery_script_block() {
+        let result = fmt_bigquery(
+            "declare x int64 default 0; begin set x = (select count(*) from t); call my_proc(x); exception when error then select @@error.message; end;",
+        );
+        assert_eq!(
+            result,
+            "DECLARE x int64 DEFAULT 0;\n\nBEGIN\n    SET x = (\n        SELECT\n            count(*)\n        FROM\n            t\n        );\n\n    CALL my_proc(x);\n\nEXCEPTION WHEN ERROR THEN\n    SELECT\n        @@error.message;\n\nEND;"
+        );
+    }
+
+    #[test]
+    fn test_bigquery_select_except_and_backticks() {
+        let result =
+            fmt_bigquery("select * except(secret), name from `my-project.dataset.users` u");
+        assert_eq!(
+            result,
+            "SELECT\n    * EXCEPT (secret),\n    name\nFROM\n    `my-project.dataset.users` u"
+        );
+    }
+
+    #[test]
+    fn test_mysql_backtick_identifiers() {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Mysql,
+            ..FormatOptions::default()
+        };
+        let sql = "select `order id`, `a``b` from `shop`.`orders` where `order id` > 1";
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "SELECT\n    `order id`,\n    `a``b`\nFROM\n    `shop`.`orders`\nWHERE\n    `order id` > 1"
+        );
+    }
+
+    #[test]
+    fn test_mysql_backslash_escaped_quote() {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Mysql,
+            ..FormatOptions::default()
+        };
+        let sql = r"select 'it\'s; fine' as a, b from t";
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "SELECT\n    'it\\'s; fine' AS a,\n    b\nFROM\n    t"
+        );
+    }
+
+    #[test]
+    fn test_bigquery_begin_transaction_is_not_block() {
+        let result = fmt_bigquery("begin transaction; select 1; commit transaction;");
+        assert_eq!(
+            result,
+            "BEGIN TRANSACTION;\n\nSELECT\n    1;\n\nCOMMIT TRANSACTION;"
+        );
+    }
+
+    #[test]
+    fn test_postgres_dollar_quoted_body_is_kept() {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Postgres,
+            ..FormatOptions::default()
+        };
+        let sql = "select $$it's  here$$ as s, $fn$\n  select   1;\n$fn$ as body from t";
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "SELECT\n    $$it's  here$$ AS s,\n    $fn$\n  select   1;\n$fn$ AS body\nFROM\n    t"
+        );
+    }
+
+    fn fmt_tsql(sql: &str) -> String {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Tsql,
+            ..FormatOptions::default()
+        };
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        format_tokens(&tokens, &options)
+    }
+
+    #[test]
+    fn test_tsql_go_separates_batches() {
+        let result = fmt_tsql("create table t (id int)\ngo\nselect id from t\nGO 2\n");
+        assert_eq!(
+            result,
+            "CREATE TABLE t (\n    id int\n)\nGO\n\nSELECT\n    id\nFROM\n    t\nGO 2"
+        );
+    }
+
+    #[test]
+    fn test_tsql_go_resets_unclosed_state() {
+        let result = fmt_tsql("select (a\ngo\nbegin\ngo\nselect b from t where x = 1\n");
+        assert_eq!(
+            result,
+            "SELECT\n     (a\nGO\n\nBEGIN\nGO\n\nSELECT\n    b\nFROM\n    t\nWHERE\n    x = 1"
+        );
+    }
+
+    #[test]
+    fn test_mysql_delimiter_keeps_procedure_body() {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Mysql,
+            ..FormatOptions::default()
+        };
+        let sql =
+            "DELIMITER $$\ncreate procedure p()\nbegin\n  select 1;\nend$$\ndelimiter ;\nselect 2;";
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        assert_eq!(
+            format_tokens(&tokens, &options),
+            "DELIMITER $$\n\ncreate procedure p()\nbegin\n  select 1;\nend$$\nDELIMITER ;\n\n\
+             SELECT\n    2;"
+        );
+    }
+
+    #[test]
+    fn test_tsql_top_and_nolock() {
+        let result = fmt_tsql("select top (10) id, name from users with (nolock) where id = @id");
+        assert_eq!(
+            result,
+            "SELECT\n    TOP (10) id,\n    name\nFROM\n    users WITH (nolock)\nWHERE\n    id = @id"
+        );
+    }
+
+    #[test]
+    fn test_tsql_bracket_identifiers() {
+        let result =
+            fmt_tsql("select [Order ID], [a]]b] from [dbo].[Order Details] where [Order ID] > 1");
+        assert_eq!(
+            result,
+            "SELECT\n    [Order ID],\n    [a]]b]\nFROM\n    [dbo].[Order Details]\nWHERE\n    [Order ID] > 1"
+        );
+    }
+
+    #[test]
+    fn test_tsql_begin_end_blocks() {
+        let result = fmt_tsql(
+            "if @x > 0 begin set nocount on; select case when a = 1 then 'y' else 'n' end from t end else begin try select 1 end try begin catch select error_message() end catch",
+        );
+        assert_eq!(
+            result,
+            "IF @x > 0\nBEGIN\n    SET nocount ON;\n\n    SELECT\n        CASE WHEN a = 1 THEN 'y' ELSE 'n' END\n    FROM\n        t\nEND\nELSE\nBEGIN TRY\n    SELECT\n        1\nEND TRY\nBEGIN CATCH\n    SELECT\n        error_message()\nEND CATCH"
+        );
+    }
+
+    fn fmt_oracle(sql: &str) -> String {
+        let options = FormatOptions {
+            dialect: crate::config::Dialect::Oracle,
+            ..FormatOptions::default()
+        };
+        let tokens = crate::lexer::tokenize_with_dialect(sql, options.dialect);
+        format_tokens(&tokens, &options)
+    }
+
+    #[test]
+    fn test_oracle_sqlplus_script() {
+        let result = fmt_oracle(
+            "create or replace procedure p as\nbegin\n  update t set x = 1;\nend;\n/\nselect a.x, q'[it's]' from a, b where a.id = b.id(+);\n",
+        );
+        assert_eq!(
+            result,
+            "create or replace procedure p as\nbegin\n  update t set x = 1;\nend;\n/\n\nSELECT\n    a.x,\n    q'[it's]'\nFROM\n    a, b\nWHERE\n    a.id = b.id(+);"
+        );
+    }
 }
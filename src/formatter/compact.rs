@@ -0,0 +1,102 @@
+//! Compact renderer: the inverse of formatting. Writes a query on a single
+//! line with only the whitespace needed to keep the token stream intact.
+
+use crate::config::FormatOptions;
+use crate::token::Token;
+
+use super::needs_space_before;
+
+pub(crate) fn minify(tokens: &[Token<'_>], options: &FormatOptions) -> String {
+    let mut output = String::new();
+    let mut prev: Option<&Token<'_>> = None;
+
+    for token in tokens {
+        let text = match token {
+            // Comments would swallow the rest of the line or only add bytes
+            Token::Whitespace(_) | Token::LineComment(_) | Token::BlockComment(_) => continue,
+            Token::Keyword(kw) if options.uppercase => kw.as_str().to_string(),
+            Token::Keyword(kw) => kw.as_str().to_lowercase(),
+            Token::Identifier(text)
+            | Token::NumberLiteral(text)
+            | Token::Operator(text)
+            | Token::Error(text)
+            | Token::TemplateTag(text)
+            | Token::Placeholder(text)
+            | Token::DollarString(text)
+            | Token::PrefixedString(text) => text.to_string(),
+            Token::QuotedIdentifier(name) => format!("\"{}\"", name),
+            Token::BacktickIdentifier(name) => format!("`{}`", name),
+            Token::BracketIdentifier(name) => format!("[{}]", name),
+            Token::StringLiteral(val) => format!("'{}'", val),
+            Token::TemplateVariable(content) => format!("{{{{{}}}}}", content),
+            Token::Comma => ",".to_string(),
+            Token::Semicolon => ";".to_string(),
+            Token::Dot => ".".to_string(),
+            Token::OpenParen => "(".to_string(),
+            Token::CloseParen => ")".to_string(),
+            // Line-oriented tokens keep lines of their own
+            Token::TemplateBlock(text)
+            | Token::ProceduralBlock(text)
+            | Token::BatchSeparator(text)
+            | Token::Unformatted(text) => {
+                if !output.is_empty() {
+                    output.push('\n');
+                }
+                output.push_str(text);
+                output.push('\n');
+                prev = None;
+                continue;
+            }
+        };
+
+        let space = needs_space_before(token, prev)
+            && !matches!(prev, Some(Token::Comma))
+            && *token != Token::OpenParen;
+        if space {
+            output.push(' ');
+        }
+        output.push_str(&text);
+        prev = Some(token);
+    }
+
+    let trimmed = output.trim_end().len();
+    output.truncate(trimmed);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::FormatOptions;
+    use crate::formatter::minify_tokens;
+    use crate::lexer::tokenize;
+
+    fn minify(sql: &str) -> String {
+        minify_tokens(&tokenize(sql), &FormatOptions::default())
+    }
+
+    #[test]
+    fn test_single_line() {
+        assert_eq!(
+            minify(
+                "select a,\n       count(*)  as n -- total\nfrom t\nwhere b in (1, 2)\ngroup by a;"
+            ),
+            "SELECT a,count(*) AS n FROM t WHERE b IN(1,2) GROUP BY a;"
+        );
+    }
+
+    #[test]
+    fn test_keeps_operator_spacing() {
+        assert_eq!(
+            minify("select a - -1, x::int, j->>'k' from t"),
+            "SELECT a - - 1,x::int,j->>'k' FROM t"
+        );
+    }
+
+    #[test]
+    fn test_literals_and_identifiers() {
+        assert_eq!(
+            minify("select 'it''s', \"Mixed Case\" from {{ ref('t') }}"),
+            "SELECT 'it''s',\"Mixed Case\" FROM {{ ref('t') }}"
+        );
+    }
+}
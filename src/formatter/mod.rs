@@ -1,13 +1,19 @@
 mod aligned;
 mod basic;
+mod compact;
 mod dataops;
 mod streamline;
 
-use crate::config::{FormatOptions, FormatStyle};
-use crate::token::{KeywordKind, Token};
+use std::fmt;
 
+use crate::config::{Dialect, FormatOptions, FormatStyle};
+use crate::params;
+use crate::token::{BlockControl, KeywordKind, Token};
+
+/// The clause a formatter is in, which decides how list items and
+/// operands are laid out.
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub(crate) enum ClauseContext {
+pub enum ClauseContext {
     None,
     Select,
     From,
@@ -15,6 +21,7 @@ pub(crate) enum ClauseContext {
     Set,
     Values,
     Having,
+    Qualify,
     GroupBy,
     OrderBy,
     Join,
@@ -23,11 +30,48 @@ pub(crate) enum ClauseContext {
     Other,
 }
 
-pub(crate) fn is_single_value_clause(kw: KeywordKind) -> bool {
+impl ClauseContext {
+    /// Label used by [`explain_tokens`].
+    pub fn name(self) -> &'static str {
+        match self {
+            ClauseContext::None => "-",
+            ClauseContext::Select => "SELECT",
+            ClauseContext::From => "FROM",
+            ClauseContext::Where => "WHERE",
+            ClauseContext::Set => "SET",
+            ClauseContext::Values => "VALUES",
+            ClauseContext::Having => "HAVING",
+            ClauseContext::Qualify => "QUALIFY",
+            ClauseContext::GroupBy => "GROUP BY",
+            ClauseContext::OrderBy => "ORDER BY",
+            ClauseContext::Join => "JOIN",
+            ClauseContext::Ddl => "DDL",
+            ClauseContext::Cte => "CTE",
+            ClauseContext::Other => "OTHER",
+        }
+    }
+}
+
+/// A line of formatted output with the formatter state it was written
+/// under, for debugging layouts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExplainedLine {
+    pub text: String,
+    /// Clause the formatter was in: `SELECT`, `FROM`, `GROUP BY`, ... or
+    /// `-` between statements
+    pub clause: &'static str,
+    /// The style's indentation depth (for `aligned`, the subquery nesting)
+    pub depth: usize,
+}
+
+/// Clauses taking a single value, kept on the keyword's line: `LIMIT 10`.
+pub fn is_single_value_clause(kw: KeywordKind) -> bool {
     matches!(kw, KeywordKind::Limit | KeywordKind::Offset)
 }
 
-pub(crate) fn clause_context_from_keyword(kw: KeywordKind) -> ClauseContext {
+/// The clause `kw` opens. Keywords without a context of their own give
+/// [`ClauseContext::Other`].
+pub fn clause_context_from_keyword(kw: KeywordKind) -> ClauseContext {
     match kw {
         KeywordKind::Select => ClauseContext::Select,
         KeywordKind::From => ClauseContext::From,
@@ -35,22 +79,28 @@ pub(crate) fn clause_context_from_keyword(kw: KeywordKind) -> ClauseContext {
         KeywordKind::Set => ClauseContext::Set,
         KeywordKind::Values => ClauseContext::Values,
         KeywordKind::Having => ClauseContext::Having,
+        KeywordKind::Qualify => ClauseContext::Qualify,
         _ => ClauseContext::Other,
     }
 }
 
-pub(crate) fn needs_space_before(token: &Token<'_>, prev: Option<&Token<'_>>) -> bool {
+/// Whether `token` is separated from `prev` by a space when both are on
+/// the same line: not after `(` or `.`, not before `)`, `,` or `;`, and not
+/// around `::`, the JSON arrows and the `:` of Snowflake paths.
+pub fn needs_space_before(token: &Token<'_>, prev: Option<&Token<'_>>) -> bool {
     let Some(prev_token) = prev else {
         return false;
     };
 
+    // Casts and JSON path lookups bind tightly: a::int, doc->'k', doc#>>'{a,b}',
+    // v:path.field
     if let Token::Operator(op) = token
-        && (*op == "::" || *op == "->" || *op == "->>")
+        && matches!(*op, "::" | ":" | "->" | "->>" | "#>" | "#>>" | "(+)")
     {
         return false;
     }
     if let Token::Operator(op) = prev_token
-        && (*op == "::" || *op == "->" || *op == "->>")
+        && matches!(*op, "::" | ":" | "->" | "->>" | "#>" | "#>>")
     {
         return false;
     }
@@ -63,23 +113,161 @@ pub(crate) fn needs_space_before(token: &Token<'_>, prev: Option<&Token<'_>>) ->
             | (_, Token::Dot)
             | (_, Token::Comma)
             | (_, Token::Semicolon)
+            | (Token::Semicolon, _)
     )
 }
 
-pub(crate) struct FormatterBase<'a> {
-    pub(crate) tokens: &'a [Token<'a>],
-    pub(crate) options: &'a FormatOptions,
-    pub(crate) paren_depth: usize,
-    pub(crate) is_subquery_paren: Vec<bool>,
-    pub(crate) inline_paren_depth: usize,
-    pub(crate) clause_context: ClauseContext,
-    pub(crate) is_first_token: bool,
-    pub(crate) prev_was_ddl_starter: bool,
-    pub(crate) output: String,
+/// `SELECT * EXCEPT (col)`, `* EXCLUDE (col)`, `* REPLACE (expr AS col)`:
+/// a column list modifier rather than a set operation or statement.
+fn is_column_modifier(kw: KeywordKind, prev: Option<&Token<'_>>) -> bool {
+    matches!(
+        kw,
+        KeywordKind::Except | KeywordKind::Exclude | KeywordKind::Replace
+    ) && matches!(prev, Some(Token::Operator("*")))
+}
+
+/// Whether `token` is the word `word`, matched case-insensitively
+/// whether it was lexed as a keyword or a plain identifier.
+fn is_word(token: Option<&&Token<'_>>, word: &str) -> bool {
+    match token {
+        Some(Token::Keyword(kw)) => kw.as_str().eq_ignore_ascii_case(word),
+        Some(Token::Identifier(name)) => name.eq_ignore_ascii_case(word),
+        _ => false,
+    }
+}
+
+const LITERAL_TYPES: &[&str] = &["DATE", "TIME", "TIMESTAMP", "TIMESTAMPTZ", "DATETIME"];
+const INTERVAL_UNITS: &[&str] = &["YEAR", "MONTH", "WEEK", "DAY", "HOUR", "MINUTE", "SECOND"];
+
+/// Whether the walk may give `word`, lexed as an identifier, keyword case:
+/// the words of typed literals and of scripting block boundaries such as
+/// `EXCEPTION WHEN ERROR THEN` and `BEGIN TRY`. Comparing formatted output
+/// with its input ignores their case.
+pub(crate) fn takes_keyword_case(word: &str) -> bool {
+    const BLOCK_WORDS: &[&str] = &["EXCEPTION", "ERROR", "TRY", "CATCH"];
+    [
+        LITERAL_TYPES,
+        INTERVAL_UNITS,
+        BLOCK_WORDS,
+        &["INTERVAL", "TO"],
+    ]
+    .iter()
+    .any(|words| words.iter().any(|w| word.eq_ignore_ascii_case(w)))
+}
+
+/// Whether the word at `i` belongs to a typed literal and so takes keyword
+/// case, though it may name a column elsewhere: the type of `DATE
+/// '2024-01-01'` or `INTERVAL '1 day'`, or the units of `INTERVAL '2' HOUR`,
+/// MySQL's `INTERVAL 7 DAY` and `INTERVAL '1:30' HOUR TO MINUTE`.
+fn is_typed_literal_word(tokens: &[&Token<'_>], i: usize) -> bool {
+    const TYPES: &[&str] = LITERAL_TYPES;
+    const UNITS: &[&str] = INTERVAL_UNITS;
+    let Token::Identifier(word) = tokens[i] else {
+        return false;
+    };
+    let next = tokens.get(i + 1);
+    let before = |n: usize| i.checked_sub(n).and_then(|j| tokens.get(j));
+    let is_unit = |token: Option<&&Token<'_>>| UNITS.iter().any(|unit| is_word(token, unit));
+    if TYPES.iter().any(|ty| word.eq_ignore_ascii_case(ty)) {
+        return matches!(next, Some(Token::StringLiteral(_)));
+    }
+    if word.eq_ignore_ascii_case("INTERVAL") {
+        return matches!(
+            next,
+            Some(Token::StringLiteral(_) | Token::NumberLiteral(_))
+        );
+    }
+    if word.eq_ignore_ascii_case("TO") {
+        return i >= 1 && is_unit(next) && is_typed_literal_word(tokens, i - 1);
+    }
+    if !UNITS.iter().any(|unit| word.eq_ignore_ascii_case(unit)) {
+        return false;
+    }
+    match before(1) {
+        Some(Token::StringLiteral(_) | Token::NumberLiteral(_)) => {
+            is_word(before(2), "INTERVAL") && is_typed_literal_word(tokens, i - 2)
+        }
+        Some(Token::Identifier(to)) if to.eq_ignore_ascii_case("TO") => {
+            is_typed_literal_word(tokens, i - 1)
+        }
+        _ => false,
+    }
+}
+
+/// Recognize the boundaries of a scripting block: `BEGIN`, `BEGIN TRY`,
+/// `EXCEPTION WHEN ERROR THEN`, `END`, `END CATCH`, ... Returns the role of
+/// the boundary and how many tokens it spans.
+fn scripting_block_control(
+    filtered: &[&Token<'_>],
+    idx: usize,
+    open_blocks: usize,
+    case_depth: usize,
+) -> Option<(BlockControl, usize)> {
+    let next = filtered.get(idx + 1);
+    let try_catch = usize::from(is_word(next, "TRY") || is_word(next, "CATCH"));
+    match filtered[idx] {
+        Token::Keyword(KeywordKind::Begin) => {
+            let is_transaction = next.is_none()
+                || matches!(
+                    next,
+                    Some(Token::Semicolon | Token::Keyword(KeywordKind::Transaction))
+                )
+                || is_word(next, "TRAN")
+                || is_word(next, "DISTRIBUTED");
+            (!is_transaction).then_some((BlockControl::Open, 1 + try_catch))
+        }
+        Token::Keyword(KeywordKind::End) if open_blocks > 0 && case_depth == 0 => {
+            // END IF / END LOOP / ... close statements, not the BEGIN block
+            let closes_statement = ["IF", "LOOP", "WHILE", "REPEAT", "FOR"]
+                .iter()
+                .any(|w| is_word(next, w));
+            (!closes_statement).then_some((BlockControl::Close, 1 + try_catch))
+        }
+        Token::Identifier(word)
+            if open_blocks > 0
+                && word.eq_ignore_ascii_case("EXCEPTION")
+                && is_word(next, "WHEN")
+                && is_word(filtered.get(idx + 2), "ERROR")
+                && is_word(filtered.get(idx + 3), "THEN") =>
+        {
+            Some((BlockControl::Continue, 4))
+        }
+        _ => None,
+    }
+}
+
+/// `FROM t WITH (NOLOCK)`, `CREATE TABLE ... WITH (fillfactor = 70)`:
+/// `WITH` directly followed by a parenthesized option list is not a CTE.
+fn is_with_options(kw: KeywordKind, next: Option<&&Token<'_>>) -> bool {
+    kw == KeywordKind::With && matches!(next, Some(Token::OpenParen))
+}
+
+/// State shared by every style: the input, the output written so far and
+/// where the walk is. The walk in [`SqlFormatter::format`] keeps
+/// `clause_context`, `is_first_token`, `block_indents` and `output` up to
+/// date; the parenthesis fields are the style's own to maintain, though a
+/// batch separator clears them.
+pub struct FormatterBase<'a> {
+    pub tokens: &'a [Token<'a>],
+    pub options: &'a FormatOptions,
+    pub paren_depth: usize,
+    /// For each open parenthesis, whether it holds a subquery
+    pub is_subquery_paren: Vec<bool>,
+    /// Depth of parentheses written on one line, like function arguments
+    pub inline_paren_depth: usize,
+    pub clause_context: ClauseContext,
+    /// Whether nothing has been written on the current statement yet
+    pub is_first_token: bool,
+    pub prev_was_ddl_starter: bool,
+    /// Indentation of the open template or scripting blocks
+    pub block_indents: Vec<usize>,
+    pub output: String,
+    /// Output length, clause and depth after each token, when explaining
+    pub(crate) trace: Option<Vec<(usize, ClauseContext, usize)>>,
 }
 
 impl<'a> FormatterBase<'a> {
-    pub(crate) fn new(tokens: &'a [Token<'a>], options: &'a FormatOptions) -> Self {
+    pub fn new(tokens: &'a [Token<'a>], options: &'a FormatOptions) -> Self {
         Self {
             tokens,
             options,
@@ -89,15 +277,38 @@ impl<'a> FormatterBase<'a> {
             clause_context: ClauseContext::None,
             is_first_token: true,
             prev_was_ddl_starter: false,
+            block_indents: Vec::new(),
             output: String::new(),
+            trace: None,
         }
     }
 
-    pub(crate) fn is_inline(&self) -> bool {
+    pub fn is_inline(&self) -> bool {
         self.inline_paren_depth > 0
     }
 
-    pub(crate) fn keyword_str(&self, kw: KeywordKind) -> String {
+    /// Move the write position to the start of a fresh line, dropping any
+    /// trailing indentation left behind on the current one.
+    pub fn start_own_line(&mut self) {
+        let trimmed = self.output.trim_end_matches(' ').len();
+        self.output.truncate(trimmed);
+        if !self.output.is_empty() && !self.output.ends_with('\n') {
+            self.output.push('\n');
+        }
+    }
+
+    /// Number of blocks (`{% if %}`, `{{#each}}`, a scripting `BEGIN`, ...)
+    /// enclosing the current position. SQL inside is indented one level per block.
+    pub fn block_depth(&self) -> usize {
+        self.block_indents.len()
+    }
+
+    /// Spaces per indentation level, honoring `FormatOptions::indent_width`.
+    pub fn indent_width(&self, style_default: usize) -> usize {
+        self.options.indent_width.unwrap_or(style_default)
+    }
+
+    pub fn keyword_str(&self, kw: KeywordKind) -> String {
         if self.options.uppercase {
             kw.as_str().to_string()
         } else {
@@ -106,7 +317,99 @@ impl<'a> FormatterBase<'a> {
     }
 }
 
-pub(crate) trait SqlFormatter<'a> {
+/// A style implemented outside this crate, run through
+/// [`FormatStyle::Custom`]: `new` builds its [`SqlFormatter`] around the
+/// shared state, and [`format_tokens`] and [`explain_tokens`] walk it like
+/// a built-in style. Styles compare equal when their names do.
+#[derive(Clone, Copy)]
+pub struct CustomStyle {
+    pub name: &'static str,
+    /// A one-line summary, as [`FormatStyle::description`] gives
+    pub description: &'static str,
+    pub new: for<'a> fn(FormatterBase<'a>) -> Box<dyn SqlFormatter<'a> + 'a>,
+}
+
+impl fmt::Debug for CustomStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomStyle")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for CustomStyle {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for CustomStyle {}
+
+/// A formatting style, as hooks called by a shared walk over the tokens.
+///
+/// [`format`](Self::format) handles what every style does alike, such as
+/// comments, template tags, scripting blocks and batch separators, and
+/// calls the required methods for keywords, punctuation and values. A style
+/// implemented outside this crate is run the same way as the built-in ones,
+/// through a [`CustomStyle`]:
+///
+/// ```
+/// use rs_sql_indent::formatter::{CustomStyle, FormatterBase, SqlFormatter, needs_space_before};
+/// use rs_sql_indent::{FormatOptions, FormatStyle, KeywordKind, Token, format_sql};
+///
+/// /// Everything on one line.
+/// struct OneLine<'a> {
+///     base: FormatterBase<'a>,
+/// }
+///
+/// impl<'a> SqlFormatter<'a> for OneLine<'a> {
+///     fn base(&self) -> &FormatterBase<'a> {
+///         &self.base
+///     }
+///     fn base_mut(&mut self) -> &mut FormatterBase<'a> {
+///         &mut self.base
+///     }
+///     fn format_keyword(&mut self, kw: KeywordKind, prev: Option<&Token<'a>>) {
+///         let text = self.base.keyword_str(kw);
+///         self.format_value(&text, prev, &Token::Keyword(kw));
+///     }
+///     fn format_comma(&mut self) {
+///         self.base.output.push(',');
+///     }
+///     fn format_open_paren(&mut self, _: &[&Token<'a>], _: usize, prev: Option<&Token<'a>>) {
+///         self.format_value("(", prev, &Token::OpenParen);
+///     }
+///     fn format_close_paren(&mut self) {
+///         self.base.output.push(')');
+///     }
+///     fn format_semicolon(&mut self) {
+///         self.base.output.push(';');
+///     }
+///     fn format_value(&mut self, text: &str, prev: Option<&Token<'a>>, token: &Token<'a>) {
+///         if !self.base.is_first_token && needs_space_before(token, prev) {
+///             self.base.output.push(' ');
+///         }
+///         self.base.output.push_str(text);
+///         self.base.is_first_token = false;
+///     }
+/// }
+///
+/// const ONE_LINE: CustomStyle = CustomStyle {
+///     name: "one-line",
+///     description: "Everything on one line",
+///     new: |base| Box::new(OneLine { base }),
+/// };
+///
+/// let options = FormatOptions::builder()
+///     .style(FormatStyle::Custom(ONE_LINE))
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     format_sql("select a,  count(*)\nfrom t", &options),
+///     "SELECT a, count (*) FROM t"
+/// );
+/// ```
+pub trait SqlFormatter<'a> {
     fn base(&self) -> &FormatterBase<'a>;
     fn base_mut(&mut self) -> &mut FormatterBase<'a>;
 
@@ -125,6 +428,124 @@ pub(crate) trait SqlFormatter<'a> {
     fn on_comment(&mut self) {}
     fn on_dot(&mut self) {}
 
+    /// Indentation depth reported by [`explain_tokens`].
+    fn indent_depth(&self) -> usize {
+        self.base().paren_depth
+    }
+
+    /// Called when a statement ends without a semicolon (e.g. at a batch
+    /// separator). Indentation state must be reset for the next statement.
+    fn on_statement_end(&mut self) {}
+
+    /// Called after a block control line or verbatim block was written on
+    /// its own line. The formatter must make sure the next token starts on a
+    /// new line.
+    fn on_own_line(&mut self) {}
+
+    /// Column at which a block control line opened at the current
+    /// position should be written.
+    fn block_indent(&self) -> usize {
+        0
+    }
+
+    fn format_block_control(&mut self, text: &str, control: BlockControl) {
+        let current = self.block_indent();
+        let base = self.base_mut();
+        // Tags of the same block share the column of the opening tag
+        let indent = match control {
+            BlockControl::Open => {
+                base.block_indents.push(current);
+                current
+            }
+            BlockControl::Continue => base.block_indents.last().copied().unwrap_or(current),
+            BlockControl::Close => base.block_indents.pop().unwrap_or(current),
+            BlockControl::Standalone => current,
+        };
+        base.start_own_line();
+        base.output.push_str(&" ".repeat(indent));
+        base.output.push_str(text);
+        base.is_first_token = false;
+        self.on_own_line();
+    }
+
+    /// Emit a block that must not be reformatted (e.g. a procedural body)
+    /// starting on a fresh line, exactly as written.
+    fn format_verbatim_block(&mut self, text: &str) {
+        let base = self.base_mut();
+        base.start_own_line();
+        base.output.push_str(text);
+        base.is_first_token = false;
+        self.on_own_line();
+    }
+
+    /// Emit a batch separator (T-SQL `GO`) on its own line, ending the
+    /// current statement. Parentheses and blocks left open do not carry
+    /// over into the next batch.
+    fn format_batch_separator(&mut self, text: &str) {
+        let base = self.base_mut();
+        base.paren_depth = 0;
+        base.is_subquery_paren.clear();
+        base.inline_paren_depth = 0;
+        base.block_indents.clear();
+        let trimmed = base.output.trim_end().len();
+        base.output.truncate(trimmed);
+        if !base.output.is_empty() {
+            base.output.push('\n');
+        }
+        base.output.push_str(text);
+        base.output.push_str("\n\n");
+        base.clause_context = ClauseContext::None;
+        base.prev_was_ddl_starter = false;
+        base.is_first_token = true;
+        self.on_comment();
+        self.on_statement_end();
+    }
+
+    /// Emit `text` verbatim on a line of its own. The next token starts a
+    /// fresh line as if a new statement began.
+    fn format_own_line(&mut self, text: &str) {
+        let base = self.base_mut();
+        if !base.output.is_empty() && !base.output.ends_with('\n') {
+            base.output.push('\n');
+        }
+        base.output.push_str(text);
+        base.output.push('\n');
+        base.is_first_token = true;
+        self.on_comment();
+    }
+
+    fn record_trace(&mut self) {
+        let depth = self.indent_depth();
+        let base = self.base_mut();
+        if let Some(trace) = &mut base.trace {
+            trace.push((base.output.len(), base.clause_context, depth));
+        }
+    }
+
+    /// Format with tracing on and pair every output line with the state
+    /// after the first token written onto it.
+    fn explain(&mut self) -> Vec<ExplainedLine> {
+        self.base_mut().trace = Some(Vec::new());
+        let output = self.format();
+        let trace = self.base_mut().trace.take().unwrap_or_default();
+
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        for line in output.split('\n') {
+            let (clause, depth) = trace
+                .iter()
+                .find(|(len, _, _)| *len > line_start)
+                .map_or(("-", 0), |&(_, clause, depth)| (clause.name(), depth));
+            lines.push(ExplainedLine {
+                text: line.to_string(),
+                clause,
+                depth,
+            });
+            line_start += line.len() + 1;
+        }
+        lines
+    }
+
     fn finalize_output(&mut self) -> String {
         let output = &mut self.base_mut().output;
         let trimmed = output.trim_end().len();
@@ -140,17 +561,75 @@ pub(crate) trait SqlFormatter<'a> {
             .filter(|t| !matches!(t, Token::Whitespace(_)))
             .collect();
 
+        let dialect = self.base().options.dialect;
+        let scripting = dialect.has_scripting_blocks();
+        // T-SQL opens blocks mid-statement: `IF @x > 0 BEGIN ... END`
+        let blocks_mid_statement = dialect == Dialect::Tsql;
+        let mut scripting_blocks = 0;
+        let mut case_depth = 0;
+        let mut statement_start = true;
+        let mut set_statement = false;
+
         let mut i = 0;
         let mut prev_token: Option<&Token<'a>> = None;
 
         while i < filtered.len() {
             let token = filtered[i];
 
+            if scripting
+                && (statement_start || blocks_mid_statement)
+                && let Some((control, len)) =
+                    scripting_block_control(&filtered, i, scripting_blocks, case_depth)
+            {
+                let words: Vec<String> = filtered[i..i + len]
+                    .iter()
+                    .map(|t| match t {
+                        Token::Keyword(kw) => self.base().keyword_str(*kw),
+                        Token::Identifier(word) if self.base().options.uppercase => {
+                            word.to_uppercase()
+                        }
+                        Token::Identifier(word) => word.to_lowercase(),
+                        _ => String::new(),
+                    })
+                    .collect();
+                match control {
+                    BlockControl::Open => scripting_blocks += 1,
+                    BlockControl::Close => scripting_blocks -= 1,
+                    _ => {}
+                }
+                // Block boundaries in a script also end the statement before them
+                self.base_mut().clause_context = ClauseContext::None;
+                self.on_statement_end();
+                self.format_block_control(&words.join(" "), control);
+                statement_start = true;
+                set_statement = false;
+                prev_token = Some(filtered[i + len - 1]);
+                i += len;
+                self.record_trace();
+                continue;
+            }
+
+            match token {
+                Token::Keyword(KeywordKind::Case) => case_depth += 1,
+                Token::Keyword(KeywordKind::End) => case_depth = case_depth.saturating_sub(1),
+                _ => {}
+            }
+
             match token {
                 Token::Keyword(kw) => {
                     if matches!(prev_token, Some(Token::Dot)) {
                         let name = kw.as_str().to_lowercase();
                         self.format_value(&name, prev_token, token);
+                    } else if is_column_modifier(*kw, prev_token)
+                        || is_with_options(*kw, filtered.get(i + 1))
+                        || (scripting && statement_start && *kw == KeywordKind::Set)
+                        || (set_statement && *kw == KeywordKind::On)
+                    {
+                        // `* EXCLUDE (col)`, `WITH (NOLOCK)` and scripting
+                        // `SET x = ...` / `SET NOCOUNT ON` stay on one line
+                        set_statement |= *kw == KeywordKind::Set;
+                        let text = self.base().keyword_str(*kw);
+                        self.format_value(&text, prev_token, token);
                     } else {
                         self.format_keyword(*kw, prev_token);
                     }
@@ -167,6 +646,9 @@ pub(crate) trait SqlFormatter<'a> {
                 Token::Semicolon => {
                     self.format_semicolon();
                 }
+                Token::LineComment(text) if token.is_directive_comment() => {
+                    self.format_own_line(&format!("--{}", text));
+                }
                 Token::LineComment(text) => {
                     if !self.base().is_first_token {
                         self.base_mut().output.push(' ');
@@ -191,6 +673,14 @@ pub(crate) trait SqlFormatter<'a> {
                     self.base_mut().is_first_token = false;
                     self.on_dot();
                 }
+                Token::Identifier(name) if is_typed_literal_word(&filtered, i) => {
+                    let text = if self.base().options.uppercase {
+                        name.to_uppercase()
+                    } else {
+                        name.to_lowercase()
+                    };
+                    self.format_value(&text, prev_token, token);
+                }
                 Token::Identifier(name) => {
                     self.format_value(name, prev_token, token);
                 }
@@ -198,6 +688,14 @@ pub(crate) trait SqlFormatter<'a> {
                     let quoted = format!("\"{}\"", name);
                     self.format_value(&quoted, prev_token, token);
                 }
+                Token::BacktickIdentifier(name) => {
+                    let quoted = format!("`{}`", name);
+                    self.format_value(&quoted, prev_token, token);
+                }
+                Token::BracketIdentifier(name) => {
+                    let quoted = format!("[{}]", name);
+                    self.format_value(&quoted, prev_token, token);
+                }
                 Token::StringLiteral(val) => {
                     let literal = format!("'{}'", val);
                     self.format_value(&literal, prev_token, token);
@@ -205,37 +703,239 @@ pub(crate) trait SqlFormatter<'a> {
                 Token::NumberLiteral(val) => {
                     self.format_value(val, prev_token, token);
                 }
-                Token::Operator(op) => {
+                Token::Operator(op) | Token::Error(op) => {
                     self.format_value(op, prev_token, token);
                 }
+                Token::TemplateVariable(content) if token.is_template_control() => {
+                    let text = format!("{{{{{}}}}}", content);
+                    let control = token.template_control().unwrap_or(BlockControl::Standalone);
+                    self.format_block_control(&text, control);
+                }
                 Token::TemplateVariable(content) => {
                     let text = format!("{{{{{}}}}}", content);
                     self.format_value(&text, prev_token, token);
                 }
+                Token::TemplateBlock(text) => {
+                    let control = token.template_control().unwrap_or(BlockControl::Standalone);
+                    self.format_block_control(text, control);
+                }
+                Token::BatchSeparator(text) => {
+                    let mut text = if self.base().options.uppercase {
+                        text.to_uppercase()
+                    } else {
+                        text.to_lowercase()
+                    };
+                    let after_comment = i > 0 && matches!(filtered[i - 1], Token::LineComment(_));
+                    if dialect == Dialect::Mysql
+                        && delimiter_command(&text).is_none()
+                        && !after_comment
+                    {
+                        // A custom terminator, like the `$$` of `END$$`,
+                        // stays on the line of the statement it ends: that
+                        // line goes out with it
+                        let output = &mut self.base_mut().output;
+                        let trimmed = output.trim_end().len();
+                        output.truncate(trimmed);
+                        let line_start = output.rfind('\n').map_or(0, |n| n + 1);
+                        text.insert_str(0, &output.split_off(line_start));
+                    }
+                    self.format_batch_separator(&text);
+                }
+                Token::ProceduralBlock(text) | Token::Unformatted(text) => {
+                    self.format_verbatim_block(text);
+                }
+                Token::TemplateTag(text)
+                | Token::Placeholder(text)
+                | Token::DollarString(text)
+                | Token::PrefixedString(text) => {
+                    self.format_value(text, prev_token, token);
+                }
                 Token::Whitespace(_) => {}
             }
 
+            if !matches!(
+                token,
+                Token::LineComment(_) | Token::BlockComment(_) | Token::Unformatted(_)
+            ) {
+                statement_start = matches!(token, Token::Semicolon | Token::BatchSeparator(_));
+                set_statement &= !statement_start;
+            }
             prev_token = Some(token);
             i += 1;
+            self.record_trace();
         }
 
         self.finalize_output()
     }
 }
 
+/// Whether lexing or formatting these statements could change once more
+/// input follows them: a procedural or template block opened in them is
+/// not closed yet, a `$$` or `{{` is unterminated, or a MySQL `DELIMITER`
+/// is still in effect. Callers formatting a statement at a time must keep
+/// such statements together with the input that follows them.
+pub(crate) fn needs_lookahead(tokens: &[Token<'_>], dialect: Dialect) -> bool {
+    let filtered: Vec<&Token<'_>> = tokens
+        .iter()
+        .filter(|t| !matches!(t, Token::Whitespace(_)))
+        .collect();
+    // Block depths are tracked as the walk in `SqlFormatter::format` does
+    let scripting = dialect.has_scripting_blocks();
+    let mut scripting_blocks = 0;
+    let mut template_blocks = 0usize;
+    let mut case_depth = 0usize;
+    let mut delimiter_set = false;
+    let mut at_statement_start = true;
+
+    let mut i = 0;
+    while i < filtered.len() {
+        let token = filtered[i];
+        if scripting
+            && (at_statement_start || dialect == Dialect::Tsql)
+            && let Some((control, len)) =
+                scripting_block_control(&filtered, i, scripting_blocks, case_depth)
+        {
+            match control {
+                BlockControl::Open => scripting_blocks += 1,
+                BlockControl::Close => scripting_blocks -= 1,
+                _ => {}
+            }
+            at_statement_start = true;
+            i += len;
+            continue;
+        }
+
+        match token {
+            Token::LineComment(_) | Token::BlockComment(_) | Token::Unformatted(_) => {
+                i += 1;
+                continue;
+            }
+            Token::Keyword(KeywordKind::Case) => case_depth += 1,
+            Token::Keyword(KeywordKind::End) => case_depth = case_depth.saturating_sub(1),
+            // A MySQL `DELIMITER` command changes where later statements end
+            Token::BatchSeparator(text) => {
+                if let Some(delimiter) = delimiter_command(text) {
+                    delimiter_set = delimiter != ";";
+                }
+            }
+            // An unterminated `$$` or `{{` lexes as operators until it closes
+            Token::Operator(op) | Token::Error(op) if op.starts_with(['$', '{']) => return true,
+            // The lexer reads a closed procedural block as one token
+            _ if at_statement_start && opens_procedural_block(&filtered[i..], dialect) => {
+                return true;
+            }
+            _ => {}
+        }
+        match token.template_control() {
+            Some(BlockControl::Open) => template_blocks += 1,
+            Some(BlockControl::Close) => template_blocks = template_blocks.saturating_sub(1),
+            _ => {}
+        }
+        at_statement_start = matches!(token, Token::Semicolon | Token::BatchSeparator(_));
+        i += 1;
+    }
+    scripting_blocks > 0 || template_blocks > 0 || delimiter_set
+}
+
+/// Whether the statement starting `tokens` opens an Oracle PL/SQL unit or a
+/// Snowflake Scripting block, which the lexer passes through whole once it
+/// has read the block's end. `BEGIN;`, `BEGIN TRANSACTION` and `CREATE
+/// TABLE` do not open one.
+fn opens_procedural_block(tokens: &[&Token<'_>], dialect: Dialect) -> bool {
+    let next = tokens.get(1);
+    match (dialect, tokens.first()) {
+        (Dialect::Oracle | Dialect::Snowflake, Some(Token::Keyword(KeywordKind::Declare))) => true,
+        (Dialect::Oracle, Some(Token::Keyword(KeywordKind::Begin))) => true,
+        (Dialect::Snowflake, Some(Token::Keyword(KeywordKind::Begin))) => {
+            matches!(next, Some(Token::Keyword(_) | Token::Identifier(_)))
+                && !["TRANSACTION", "WORK", "NAME"]
+                    .iter()
+                    .any(|w| is_word(next, w))
+        }
+        (Dialect::Oracle, Some(Token::Keyword(KeywordKind::Create))) => {
+            let kind = tokens[1..].iter().find(|t| {
+                !["OR", "REPLACE", "EDITIONABLE", "NONEDITIONABLE"]
+                    .iter()
+                    .any(|w| is_word(Some(t), w))
+            });
+            ["PROCEDURE", "FUNCTION", "PACKAGE", "TRIGGER", "TYPE"]
+                .iter()
+                .any(|w| is_word(kind, w))
+        }
+        _ => false,
+    }
+}
+
+/// The terminator set by a MySQL `DELIMITER` command lexed as a batch
+/// separator, or `None` for other separators.
+fn delimiter_command(text: &str) -> Option<&str> {
+    let (word, rest) = text.split_at_checked(9)?;
+    word.eq_ignore_ascii_case("DELIMITER").then(|| rest.trim())
+}
+
 pub fn format_tokens(tokens: &[Token<'_>], options: &FormatOptions) -> String {
+    let mut output = String::new();
+    format_tokens_into(tokens, options, &mut output);
+    output
+}
+
+/// Format `tokens` like [`format_tokens`] into `output`, replacing its
+/// contents but reusing its allocation. Placeholders are rewritten first
+/// when `options.placeholders` asks for it.
+pub fn format_tokens_into(tokens: &[Token<'_>], options: &FormatOptions, output: &mut String) {
+    let mut buffer = std::mem::take(output);
+    buffer.clear();
+    let renamed = options
+        .placeholders
+        .map(|style| params::rename_placeholders(tokens, style))
+        .unwrap_or_default();
+    let rewritten;
+    let tokens = if renamed.is_empty() {
+        tokens
+    } else {
+        rewritten = params::with_renamed(tokens, &renamed);
+        &rewritten
+    };
     if tokens.is_empty() {
-        return String::new();
+        *output = buffer;
+        return;
+    }
+
+    *output = match options.style {
+        FormatStyle::Basic => basic::format(tokens, options, buffer),
+        FormatStyle::Streamline => streamline::format(tokens, options, buffer),
+        FormatStyle::Aligned => aligned::format(tokens, options, buffer),
+        FormatStyle::Dataops => dataops::format(tokens, options, buffer),
+        FormatStyle::Custom(style) => {
+            let mut base = FormatterBase::new(tokens, options);
+            base.output = buffer;
+            (style.new)(base).format()
+        }
+    };
+}
+
+/// Format `tokens` like [`format_tokens`], returning each output line with
+/// the clause context and indentation depth it was produced under.
+pub fn explain_tokens(tokens: &[Token<'_>], options: &FormatOptions) -> Vec<ExplainedLine> {
+    if tokens.is_empty() {
+        return Vec::new();
     }
 
     match options.style {
-        FormatStyle::Basic => basic::format(tokens, options),
-        FormatStyle::Streamline => streamline::format(tokens, options),
-        FormatStyle::Aligned => aligned::format(tokens, options),
-        FormatStyle::Dataops => dataops::format(tokens, options),
+        FormatStyle::Basic => basic::explain(tokens, options),
+        FormatStyle::Streamline => streamline::explain(tokens, options),
+        FormatStyle::Aligned => aligned::explain(tokens, options),
+        FormatStyle::Dataops => dataops::explain(tokens, options),
+        FormatStyle::Custom(style) => (style.new)(FormatterBase::new(tokens, options)).explain(),
     }
 }
 
+/// Render `tokens` on a single line with minimal whitespace, dropping
+/// comments. Keyword case follows `options.uppercase`.
+pub fn minify_tokens(tokens: &[Token<'_>], options: &FormatOptions) -> String {
+    compact::minify(tokens, options)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,4 +945,84 @@ mod tests {
         let result = format_tokens(&[], &FormatOptions::default());
         assert_eq!(result, "");
     }
+
+    #[test]
+    fn test_typed_literals_take_keyword_case() {
+        let sql = "select date '2024-01-01', interval '1:30' hour to minute, interval 7 day, \
+                   date, day from t where ts > timestamp '2024-01-01 00:00'";
+        let tokens = crate::lexer::tokenize(sql);
+        assert_eq!(
+            format_tokens(&tokens, &FormatOptions::default()),
+            "SELECT\n    DATE '2024-01-01',\n    INTERVAL '1:30' HOUR TO MINUTE,\n    \
+             INTERVAL 7 DAY,\n    date,\n    day\nFROM\n    t\nWHERE\n    \
+             ts > TIMESTAMP '2024-01-01 00:00'"
+        );
+        let lowercase = FormatOptions {
+            uppercase: false,
+            style: FormatStyle::Aligned,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_tokens(
+                &crate::lexer::tokenize("SELECT a FROM t WHERE d > DATE '2024-01-01'"),
+                &lowercase
+            ),
+            "select a\n  from t\n where d > date '2024-01-01'"
+        );
+    }
+
+    #[test]
+    fn test_needs_lookahead() {
+        let needs = |sql: &str, dialect: Dialect| {
+            needs_lookahead(&crate::lexer::tokenize_with_dialect(sql, dialect), dialect)
+        };
+        assert!(!needs("begin; select 1;", Dialect::Postgres));
+        assert!(!needs("begin transaction; select 1;", Dialect::Snowflake));
+        assert!(needs("begin\n  let x := 1;", Dialect::Snowflake));
+        assert!(needs("begin null;", Dialect::Oracle));
+        assert!(needs(
+            "create or replace procedure p as begin null;",
+            Dialect::Oracle
+        ));
+        assert!(!needs("create table t (a int);", Dialect::Oracle));
+        assert!(needs("begin select 1;", Dialect::Bigquery));
+        assert!(!needs("begin select 1; end;", Dialect::Bigquery));
+        assert!(needs("if @x > 0 begin select 1;", Dialect::Tsql));
+        assert!(needs("{% if x %} select 1;", Dialect::Generic));
+        assert!(!needs("{% if x %} select 1; {% endif %}", Dialect::Generic));
+        assert!(needs("DELIMITER $$\nselect 1$$", Dialect::Mysql));
+        assert!(!needs(
+            "DELIMITER $$\nselect 1$$\nDELIMITER ;\n",
+            Dialect::Mysql
+        ));
+    }
+
+    #[test]
+    fn test_explain_tokens() {
+        let tokens = crate::lexer::tokenize("select a from (select b from t) x");
+        let lines: Vec<(String, &str, usize)> = explain_tokens(&tokens, &FormatOptions::default())
+            .into_iter()
+            .map(|l| (l.text, l.clause, l.depth))
+            .collect();
+        let texts: Vec<&str> = lines.iter().map(|l| l.0.as_str()).collect();
+        assert_eq!(
+            texts.join("\n"),
+            format_tokens(&tokens, &FormatOptions::default())
+        );
+        let states: Vec<(&str, usize)> = lines.iter().map(|l| (l.1, l.2)).collect();
+        assert_eq!(
+            states,
+            [
+                ("SELECT", 1),
+                ("SELECT", 1),
+                ("FROM", 1),
+                ("FROM", 1),
+                ("SELECT", 2),
+                ("SELECT", 2),
+                ("FROM", 2),
+                ("FROM", 2),
+                ("FROM", 0),
+            ]
+        );
+    }
 }
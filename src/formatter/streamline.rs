@@ -26,9 +26,8 @@ impl<'a> StreamlineFormatter<'a> {
     }
 
     fn write_indent(&mut self, depth: usize) {
-        for _ in 0..depth {
-            self.base.output.push_str("  ");
-        }
+        let width = (depth + self.base.block_depth()) * self.base.indent_width(2);
+        self.base.output.extend(std::iter::repeat_n(' ', width));
     }
 
     fn write_newline_at(&mut self, depth: usize) {
@@ -193,7 +192,7 @@ impl<'a> StreamlineFormatter<'a> {
 
         if kw == KeywordKind::GroupBy {
             self.base.clause_context = ClauseContext::GroupBy;
-        } else if kw == KeywordKind::OrderBy {
+        } else if kw.is_order_modifier() {
             self.base.clause_context = ClauseContext::OrderBy;
         }
 
@@ -295,10 +294,17 @@ impl<'a> StreamlineFormatter<'a> {
     ) {
         let next = filtered.get(idx + 1).copied();
         let is_subquery = matches!(next, Some(Token::Keyword(kw)) if kw.is_clause_starter());
+        // `PIVOT (...)` keeps its parenthesis on the keyword's line
+        let after_pivot = matches!(
+            prev_token,
+            Some(Token::Keyword(KeywordKind::Pivot | KeywordKind::Unpivot))
+        );
 
         if self.needs_indent_newline {
             self.needs_indent_newline = false;
-            self.write_newline_at(self.indent_depth);
+            if !after_pivot {
+                self.write_newline_at(self.indent_depth);
+            }
         }
         if self.needs_space_only {
             self.needs_space_only = false;
@@ -383,6 +389,8 @@ impl<'a> StreamlineFormatter<'a> {
 
         self.base.output.push('\n');
         self.base.output.push('\n');
+        // Statements inside an open block keep its indentation
+        self.write_indent(0);
 
         self.indent_depth = 0;
         self.base.clause_context = ClauseContext::None;
@@ -448,6 +456,10 @@ impl<'a> SqlFormatter<'a> for StreamlineFormatter<'a> {
         &mut self.base
     }
 
+    fn indent_depth(&self) -> usize {
+        self.indent_depth
+    }
+
     fn on_comment(&mut self) {
         self.clear_pending_state();
     }
@@ -456,6 +468,19 @@ impl<'a> SqlFormatter<'a> for StreamlineFormatter<'a> {
         self.after_comma_newline = false;
     }
 
+    fn on_statement_end(&mut self) {
+        self.indent_depth = 0;
+    }
+
+    fn on_own_line(&mut self) {
+        self.clear_pending_state();
+        self.needs_indent_newline = true;
+    }
+
+    fn block_indent(&self) -> usize {
+        (self.indent_depth + self.base.block_depth()) * self.base.indent_width(2)
+    }
+
     fn format_keyword(&mut self, kw: KeywordKind, prev_token: Option<&Token<'a>>) {
         self.do_format_keyword(kw, prev_token);
     }
@@ -486,11 +511,24 @@ impl<'a> SqlFormatter<'a> for StreamlineFormatter<'a> {
     }
 }
 
-pub(crate) fn format<'a>(tokens: &'a [Token<'a>], options: &'a FormatOptions) -> String {
+/// Format `tokens` into `output`, which must be empty.
+pub(crate) fn format<'a>(
+    tokens: &'a [Token<'a>],
+    options: &'a FormatOptions,
+    output: String,
+) -> String {
     let mut formatter = StreamlineFormatter::new(tokens, options);
+    formatter.base.output = output;
     formatter.format()
 }
 
+pub(crate) fn explain<'a>(
+    tokens: &'a [Token<'a>],
+    options: &'a FormatOptions,
+) -> Vec<super::ExplainedLine> {
+    StreamlineFormatter::new(tokens, options).explain()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::config::{FormatOptions, FormatStyle};
@@ -504,6 +542,7 @@ mod tests {
             &FormatOptions {
                 uppercase: false,
                 style: FormatStyle::Streamline,
+                ..FormatOptions::default()
             },
         )
     }
@@ -566,4 +605,22 @@ from
         let result = fmt("select 1");
         assert!(!result.ends_with('\n'));
     }
+
+    #[test]
+    fn test_goose_annotations_on_own_lines() {
+        let result = fmt("-- +goose Up\nselect 1; -- +goose Down\nselect 2;");
+        assert_eq!(
+            result,
+            "-- +goose Up\nselect\n  1;\n\n-- +goose Down\nselect\n  2;"
+        );
+    }
+
+    #[test]
+    fn test_directive_never_absorbs_following_tokens() {
+        let result = fmt("select id -- +goose StatementEnd\nfrom users");
+        assert_eq!(
+            result,
+            "select\n  id\n-- +goose StatementEnd\nfrom\n  users"
+        );
+    }
 }
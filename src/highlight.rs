@@ -0,0 +1,204 @@
+//! Syntax highlighting from the lexer's token classification, rendered as
+//! HTML or ANSI escapes. The command line's `--format html` and `--color`
+//! use these renderers.
+
+use crate::config::Dialect;
+use crate::lexer;
+use crate::token::Token;
+
+/// What a token is highlighted as. Tokens of no class (identifiers,
+/// operators, punctuation, whitespace) are left plain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TokenClass {
+    /// Keywords and batch separators such as `GO`
+    Keyword,
+    String,
+    Number,
+    Comment,
+    /// Template expressions and bind placeholders
+    Template,
+}
+
+impl TokenClass {
+    pub fn of(token: &Token<'_>) -> Option<TokenClass> {
+        match token {
+            Token::Keyword(_) | Token::BatchSeparator(_) => Some(TokenClass::Keyword),
+            Token::StringLiteral(_) | Token::PrefixedString(_) | Token::DollarString(_) => {
+                Some(TokenClass::String)
+            }
+            Token::NumberLiteral(_) => Some(TokenClass::Number),
+            Token::LineComment(_) | Token::BlockComment(_) => Some(TokenClass::Comment),
+            Token::TemplateVariable(_)
+            | Token::TemplateBlock(_)
+            | Token::TemplateTag(_)
+            | Token::Placeholder(_) => Some(TokenClass::Template),
+            _ => None,
+        }
+    }
+
+    /// The CSS class [`to_html`] marks the class with.
+    pub fn css_class(self) -> &'static str {
+        match self {
+            TokenClass::Keyword => "kw",
+            TokenClass::String => "str",
+            TokenClass::Number => "num",
+            TokenClass::Comment => "com",
+            TokenClass::Template => "tpl",
+        }
+    }
+}
+
+/// `sql` HTML-escaped, with each classified token wrapped in a
+/// `<span class="...">` of its [`css_class`](TokenClass::css_class).
+pub fn to_html(sql: &str, dialect: Dialect) -> String {
+    let mut out = String::with_capacity(sql.len() * 2);
+    for (token, range) in lexer::tokenize_with_offsets(sql, dialect) {
+        let text = &sql[range];
+        match TokenClass::of(&token) {
+            Some(class) => {
+                out.push_str("<span class=\"");
+                out.push_str(class.css_class());
+                out.push_str("\">");
+                escape_html(text, &mut out);
+                out.push_str("</span>");
+            }
+            None => escape_html(text, &mut out),
+        }
+    }
+    out
+}
+
+/// [`to_html`] inside `<pre class="sql"><code>`, ready to embed in a page.
+pub fn html_block(sql: &str, dialect: Dialect) -> String {
+    format!(
+        "<pre class=\"sql\"><code>{}</code></pre>",
+        to_html(sql, dialect)
+    )
+}
+
+/// ANSI escape sequences for each [`TokenClass`], written before a token of
+/// the class and followed by [`Theme::reset`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub keyword: &'static str,
+    pub string: &'static str,
+    pub number: &'static str,
+    pub comment: &'static str,
+    pub template: &'static str,
+    pub reset: &'static str,
+}
+
+impl Default for Theme {
+    /// Bold blue keywords, green strings, yellow numbers, dim comments and
+    /// cyan templates, as `--color` prints them.
+    fn default() -> Self {
+        Theme {
+            keyword: "\x1b[1;34m",
+            string: "\x1b[32m",
+            number: "\x1b[33m",
+            comment: "\x1b[2m",
+            template: "\x1b[36m",
+            reset: "\x1b[0m",
+        }
+    }
+}
+
+impl Theme {
+    pub fn color(&self, class: TokenClass) -> &'static str {
+        match class {
+            TokenClass::Keyword => self.keyword,
+            TokenClass::String => self.string,
+            TokenClass::Number => self.number,
+            TokenClass::Comment => self.comment,
+            TokenClass::Template => self.template,
+        }
+    }
+}
+
+/// `sql` with each classified token wrapped in its `theme` color and reset.
+/// Everything else is copied through unchanged.
+pub fn to_ansi(sql: &str, dialect: Dialect, theme: &Theme) -> String {
+    let mut out = String::with_capacity(sql.len() * 2);
+    for (token, range) in lexer::tokenize_with_offsets(sql, dialect) {
+        let text = &sql[range];
+        match TokenClass::of(&token) {
+            Some(class) => {
+                out.push_str(theme.color(class));
+                out.push_str(text);
+                out.push_str(theme.reset);
+            }
+            None => out.push_str(text),
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str, out: &mut String) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_html() {
+        assert_eq!(
+            to_html(
+                "SELECT 'a<b', 1 -- x & y\nFROM t WHERE a > :p",
+                Dialect::Generic
+            ),
+            "<span class=\"kw\">SELECT</span> <span class=\"str\">&#39;a&lt;b&#39;</span>, \
+             <span class=\"num\">1</span> <span class=\"com\">-- x &amp; y</span>\n\
+             <span class=\"kw\">FROM</span> t <span class=\"kw\">WHERE</span> a &gt; \
+             <span class=\"tpl\">:p</span>"
+        );
+    }
+
+    #[test]
+    fn test_to_ansi() {
+        let out = to_ansi(
+            "SELECT 'a', 1 -- note\nFROM t",
+            Dialect::Generic,
+            &Theme::default(),
+        );
+        assert_eq!(
+            out,
+            "\x1b[1;34mSELECT\x1b[0m \x1b[32m'a'\x1b[0m, \x1b[33m1\x1b[0m \
+             \x1b[2m-- note\x1b[0m\n\x1b[1;34mFROM\x1b[0m t"
+        );
+    }
+
+    #[test]
+    fn test_to_ansi_keeps_text() {
+        let sql = "SELECT\n    {{ ref('x') }}.id\nFROM\n    t;";
+        let theme = Theme {
+            keyword: "<k>",
+            template: "<t>",
+            reset: "</>",
+            ..Theme::default()
+        };
+        let out = to_ansi(sql, Dialect::Generic, &theme);
+        assert_eq!(
+            out,
+            "<k>SELECT</>\n    <t>{{ ref('x') }}</>.id\n<k>FROM</>\n    t;"
+        );
+    }
+
+    #[test]
+    fn test_html_block() {
+        assert_eq!(
+            html_block("SELECT \"a\"", Dialect::Generic),
+            "<pre class=\"sql\"><code><span class=\"kw\">SELECT</span> &quot;a&quot;</code></pre>"
+        );
+    }
+}
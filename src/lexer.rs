@@ -1,4 +1,13 @@
-use crate::token::{KeywordKind, Token, lookup_keyword};
+//! Zero-copy SQL lexer. Its output is a flat [`Token`] stream that never
+//! fails: characters the lexer cannot make sense of become
+//! [`Token::Error`], unclosed literals and comments are read to the end of
+//! the input, and [`diagnostics`] reports those places. Dialects change how quotes,
+//! variables, placeholders and batch separators are read.
+
+use std::ops::Range;
+
+use crate::config::Dialect;
+use crate::token::{KeywordKind, Span, SpannedToken, Token, lookup_keyword};
 
 const TWO_WORD_KEYWORDS: &[(KeywordKind, &str, KeywordKind)] = &[
     (KeywordKind::Order, "BY", KeywordKind::OrderBy),
@@ -13,23 +22,51 @@ const TWO_WORD_KEYWORDS: &[(KeywordKind, &str, KeywordKind)] = &[
     (KeywordKind::Foreign, "KEY", KeywordKind::ForeignKey),
     (KeywordKind::Rows, "BETWEEN", KeywordKind::RowsBetween),
     (KeywordKind::Range, "BETWEEN", KeywordKind::RangeBetween),
+    (KeywordKind::Lateral, "VIEW", KeywordKind::LateralView),
+];
+
+/// Hive/Spark clauses whose first word is not a keyword on its own, so
+/// `sort` or `cluster` stay usable as column names.
+const IDENTIFIER_TWO_WORD_KEYWORDS: &[(&str, &str, KeywordKind)] = &[
+    ("DISTRIBUTE", "BY", KeywordKind::DistributeBy),
+    ("CLUSTER", "BY", KeywordKind::ClusterBy),
+    ("SORT", "BY", KeywordKind::SortBy),
+];
+
+const THREE_CHAR_OPS: &[&[u8]] = &[b"->>", b"#>>", b"!~~", b"<->", b"||/"];
+const TWO_CHAR_OPS: &[&[u8]] = &[
+    b"<>", b"!=", b"<=", b">=", b"||", b"::", b"->", b"=>", b"@>", b"#>", b"#-", b"~~", b"&&",
 ];
 
-const THREE_CHAR_OPS: &[&[u8]] = &[b"->>"];
-const TWO_CHAR_OPS: &[&[u8]] = &[b"<>", b"!=", b"<=", b">=", b"||", b"::", b"->"];
+/// Operators only PostgreSQL reads as one: elsewhere `<@name` compares with
+/// a parameter and `?` is a placeholder.
+const POSTGRES_OPS: &[&[u8]] = &[b"<@", b"?|", b"?&"];
+
+/// Words that may follow END without closing a BEGIN or CASE (END IF, END LOOP, ...).
+const END_QUALIFIERS: &[&str] = &["LOOP", "WHILE", "REPEAT"];
 
+#[derive(Clone)]
 struct Lexer<'a> {
     input: &'a str,
     bytes: &'a [u8],
     pos: usize,
+    dialect: Dialect,
+    at_statement_start: bool,
+    scanning_block: bool,
+    /// Statement terminator set by a MySQL `DELIMITER` command, when not `;`
+    delimiter: Option<&'a [u8]>,
 }
 
 impl<'a> Lexer<'a> {
-    fn new(input: &'a str) -> Self {
+    fn new(input: &'a str, dialect: Dialect) -> Self {
         Self {
             input,
             bytes: input.as_bytes(),
             pos: 0,
+            dialect,
+            at_statement_start: true,
+            scanning_block: false,
+            delimiter: None,
         }
     }
 
@@ -51,6 +88,16 @@ impl<'a> Lexer<'a> {
         &self.input[start..end]
     }
 
+    /// Find `needle` at or after `self.pos + offset`, returning its absolute position.
+    fn find_from(&self, needle: &[u8], offset: usize) -> Option<usize> {
+        let from = self.pos + offset;
+        self.bytes
+            .get(from..)?
+            .windows(needle.len())
+            .position(|w| w == needle)
+            .map(|p| from + p)
+    }
+
     fn next_token(&mut self) -> Option<Token<'a>> {
         let b = self.peek()?;
 
@@ -58,6 +105,16 @@ impl<'a> Lexer<'a> {
             // Whitespace
             b if b.is_ascii_whitespace() => Some(self.lex_whitespace()),
 
+            // Terminator set by a MySQL `DELIMITER` command: //, $$
+            _ if self
+                .delimiter
+                .is_some_and(|d| self.bytes[self.pos..].starts_with(d)) =>
+            {
+                let start = self.pos;
+                self.pos += self.delimiter.map_or(0, <[u8]>::len);
+                Some(Token::BatchSeparator(self.slice(start, self.pos)))
+            }
+
             // Line comment: --
             b'-' if self.peek_at(1) == Some(b'-') => Some(self.lex_line_comment()),
 
@@ -67,9 +124,57 @@ impl<'a> Lexer<'a> {
             // String literal: 'single-quoted'
             b'\'' => Some(self.lex_string_literal()),
 
+            // Oracle alternative quoting: q'[...]', q'{...}', q'!...!'
+            b'q' | b'Q' if self.dialect == Dialect::Oracle && self.q_quote_end().is_some() => {
+                Some(self.lex_q_quote())
+            }
+
+            // PostgreSQL escape string: E'it\'s'
+            b'e' | b'E'
+                if self.dialect.supports_escape_strings() && self.peek_at(1) == Some(b'\'') =>
+            {
+                Some(self.lex_escape_string())
+            }
+
+            // Hex and bit strings: x'1F', b'0101'
+            b'x' | b'X' | b'b' | b'B' if self.peek_at(1) == Some(b'\'') => {
+                Some(self.lex_bit_string())
+            }
+
+            // Unicode escape string: U&'d\0061ta', U&'d!0061ta' UESCAPE '!'
+            b'u' | b'U' if self.bytes[self.pos + 1..].starts_with(b"&'") => {
+                Some(self.lex_unicode_string())
+            }
+
+            // SQL*Plus statement terminator: / alone on a line
+            b'/' if self.dialect == Dialect::Oracle && self.is_slash_line(self.pos) => {
+                let start = self.pos;
+                self.advance();
+                Some(Token::BatchSeparator(self.slice(start, self.pos)))
+            }
+
+            // Oracle outer join marker: (+)
+            b'(' if self.dialect == Dialect::Oracle
+                && self.bytes[self.pos..].starts_with(b"(+)") =>
+            {
+                let start = self.pos;
+                self.pos += 3;
+                Some(Token::Operator(self.slice(start, self.pos)))
+            }
+
             // Quoted identifier: "double-quoted"
             b'"' => Some(self.lex_quoted_identifier()),
 
+            // Backtick-quoted identifier: `project.dataset.table`
+            b'`' if self.dialect.supports_backtick_identifiers() => {
+                Some(self.lex_backtick_identifier())
+            }
+
+            // Bracket-quoted identifier: [Order Details]
+            b'[' if self.dialect.supports_bracket_identifiers() => {
+                Some(self.lex_bracket_identifier())
+            }
+
             // Number literal starting with digit
             b'0'..=b'9' => Some(self.lex_number()),
 
@@ -91,6 +196,49 @@ impl<'a> Lexer<'a> {
             // Template variable: {{...}}
             b'{' if self.peek_at(1) == Some(b'{') => Some(self.lex_template_variable()),
 
+            // Jinja statement {% ... %} and comment {# ... #}
+            b'{' if matches!(self.peek_at(1), Some(b'%' | b'#')) => Some(self.lex_template_block()),
+
+            // ERB template tag: <% ... %>
+            b'<' if self.peek_at(1) == Some(b'%') && self.find_from(b"%>", 2).is_some() => {
+                Some(self.lex_erb_tag())
+            }
+
+            // Dollar-quoted string: $$...$$, or $tag$...$tag$ in PostgreSQL
+            b'$' if self.dollar_string_end().is_some() => Some(self.lex_dollar_string()),
+
+            // Variables: BigQuery @@error.message, T-SQL @name and @@ROWCOUNT,
+            // MySQL @@version
+            b'@' if self.starts_variable() => Some(self.lex_variable()),
+
+            // Flyway placeholder: ${...}
+            b'$' if self.peek_at(1) == Some(b'{') => Some(self.lex_flyway_placeholder()),
+
+            // Bind placeholders: `$1`, `:name`, and `?`, `?1` outside
+            // PostgreSQL, where `?` tests for a JSON key
+            b'$' if self.peek_at(1).is_some_and(|c| c.is_ascii_digit()) => {
+                Some(self.lex_bind_placeholder())
+            }
+            b'?' if self.dialect != Dialect::Postgres => Some(self.lex_bind_placeholder()),
+            b':' if self.starts_named_placeholder() => Some(self.lex_bind_placeholder()),
+            // `@name` where it is not a variable, except in PostgreSQL, where
+            // `@` is an operator
+            b'@' if self.dialect != Dialect::Postgres
+                && self
+                    .peek_at(1)
+                    .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_') =>
+            {
+                Some(self.lex_bind_placeholder())
+            }
+            // PostgreSQL JSON, array and pattern operators: @>, #>>, ?|
+            b'@' | b'#' | b'?' => Some(self.lex_operator()),
+            // Python DB-API placeholders: %s, %(name)s
+            b'%' if self.pyformat_placeholder_end().is_some() => {
+                let start = self.pos;
+                self.pos = self.pyformat_placeholder_end().unwrap_or(self.bytes.len());
+                Some(Token::Placeholder(self.slice(start, self.pos)))
+            }
+
             // Lone braces as operators
             b'{' | b'}' => {
                 let start = self.pos;
@@ -105,11 +253,12 @@ impl<'a> Lexer<'a> {
             // Keywords and identifiers
             b if b.is_ascii_alphabetic() || b == b'_' => Some(self.lex_word()),
 
-            // Unknown character: emit as single-char operator
+            // Unknown character
             _ => {
                 let start = self.pos;
-                self.advance();
-                Some(Token::Operator(self.slice(start, self.pos)))
+                let len = self.input[start..].chars().next().map_or(1, char::len_utf8);
+                self.pos += len;
+                Some(Token::Error(self.slice(start, self.pos)))
             }
         }
     }
@@ -127,6 +276,7 @@ impl<'a> Lexer<'a> {
     }
 
     fn lex_line_comment(&mut self) -> Token<'a> {
+        let comment_start = self.pos;
         // Skip the '--'
         self.advance();
         self.advance();
@@ -137,7 +287,31 @@ impl<'a> Lexer<'a> {
             }
             self.advance();
         }
-        Token::LineComment(self.slice(start, self.pos))
+        let text = self.slice(start, self.pos);
+        if region_directive(text) == Some(false) {
+            self.pos = self.find_region_end();
+            return Token::Unformatted(self.slice(comment_start, self.pos));
+        }
+        Token::LineComment(text)
+    }
+
+    /// End of the line holding the `-- sql-indent: on` comment that closes
+    /// a region opened before `self.pos`, or the end of input.
+    fn find_region_end(&self) -> usize {
+        let mut line_start = self.pos;
+        while line_start < self.bytes.len() {
+            let line_end = self.input[line_start..]
+                .find('\n')
+                .map_or(self.bytes.len(), |i| line_start + i);
+            let line = self.input[line_start..line_end].trim();
+            if let Some(text) = line.strip_prefix("--")
+                && region_directive(text) == Some(true)
+            {
+                return line_end;
+            }
+            line_start = line_end + 1;
+        }
+        self.input.trim_end().len().max(self.pos)
     }
 
     fn lex_block_comment(&mut self) -> Token<'a> {
@@ -163,6 +337,18 @@ impl<'a> Lexer<'a> {
     }
 
     fn lex_string_literal(&mut self) -> Token<'a> {
+        if self.dialect.supports_backslash_escapes() {
+            let quote = self.pos;
+            let end = escape_string_end(&self.bytes[quote..]);
+            // Unclosed: consume to end
+            self.pos = end.map_or(self.bytes.len(), |end| quote + end);
+            let content_end = if end.is_some() {
+                self.pos - 1
+            } else {
+                self.pos
+            };
+            return Token::StringLiteral(self.slice(quote + 1, content_end));
+        }
         // Skip opening quote
         self.advance();
         let start = self.pos;
@@ -208,8 +394,201 @@ impl<'a> Lexer<'a> {
         Token::QuotedIdentifier(self.slice(start, self.pos))
     }
 
+    /// Position just past the closing `]'` of a `q'[...]'` literal starting at
+    /// the current position.
+    fn q_quote_end(&self) -> Option<usize> {
+        if self.peek_at(1) != Some(b'\'') {
+            return None;
+        }
+        let open = self.peek_at(2)?;
+        let close = match open {
+            b'[' => b']',
+            b'{' => b'}',
+            b'(' => b')',
+            b'<' => b'>',
+            b if b.is_ascii_whitespace() || b == b'\'' => return None,
+            b => b,
+        };
+        self.find_from(&[close, b'\''], 3).map(|p| p + 2)
+    }
+
+    fn lex_q_quote(&mut self) -> Token<'a> {
+        let start = self.pos;
+        self.pos = self.q_quote_end().unwrap_or(self.bytes.len());
+        Token::PrefixedString(self.slice(start, self.pos))
+    }
+
+    fn lex_bit_string(&mut self) -> Token<'a> {
+        let start = self.pos;
+        // Unclosed: consume to end
+        self.pos = self
+            .find_from(b"'", 2)
+            .map_or(self.bytes.len(), |close| close + 1);
+        Token::PrefixedString(self.slice(start, self.pos))
+    }
+
+    fn lex_unicode_string(&mut self) -> Token<'a> {
+        let start = self.pos;
+        let Some(end) = quoted_string_end(&self.bytes[start + 2..]) else {
+            // Unclosed: consume to end
+            self.pos = self.bytes.len();
+            return Token::PrefixedString(self.slice(start, self.pos));
+        };
+        self.pos = start + 2 + end;
+        if let Some((word, word_end)) = self.peek_word_after_whitespace(self.pos)
+            && word.eq_ignore_ascii_case("UESCAPE")
+        {
+            let quote = word_end
+                + self.bytes[word_end..]
+                    .iter()
+                    .take_while(|b| b.is_ascii_whitespace())
+                    .count();
+            if self.bytes.get(quote) == Some(&b'\'')
+                && let Some(end) = quoted_string_end(&self.bytes[quote..])
+            {
+                self.pos = quote + end;
+            }
+        }
+        Token::PrefixedString(self.slice(start, self.pos))
+    }
+
+    fn lex_escape_string(&mut self) -> Token<'a> {
+        let start = self.pos;
+        // Unclosed: consume to end
+        self.pos = escape_string_end(&self.bytes[start + 1..])
+            .map_or(self.bytes.len(), |end| start + 1 + end);
+        Token::PrefixedString(self.slice(start, self.pos))
+    }
+
+    fn lex_backtick_identifier(&mut self) -> Token<'a> {
+        // Skip opening backtick
+        self.advance();
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            // A doubled backtick is one inside the name: `a``b`
+            if b == b'`' && self.peek_at(1) == Some(b'`') {
+                self.advance();
+            } else if b == b'`' {
+                let end = self.pos;
+                self.advance(); // skip closing backtick
+                return Token::BacktickIdentifier(self.slice(start, end));
+            }
+            self.advance();
+        }
+        Token::BacktickIdentifier(self.slice(start, self.pos))
+    }
+
+    fn lex_bracket_identifier(&mut self) -> Token<'a> {
+        // Skip opening bracket
+        self.advance();
+        let start = self.pos;
+        while let Some(b) = self.peek() {
+            // A doubled closing bracket is one inside the name: [a]]b]
+            if b == b']' && self.peek_at(1) == Some(b']') {
+                self.advance();
+            } else if b == b']' {
+                let end = self.pos;
+                self.advance(); // skip closing bracket
+                return Token::BracketIdentifier(self.slice(start, end));
+            }
+            self.advance();
+        }
+        Token::BracketIdentifier(self.slice(start, self.pos))
+    }
+
+    fn starts_variable(&self) -> bool {
+        let sigils = match self.dialect {
+            Dialect::Bigquery => 2,
+            Dialect::Tsql if self.peek_at(1) == Some(b'@') => 2,
+            Dialect::Tsql => 1,
+            // `@@` matches text searches in PostgreSQL
+            Dialect::Postgres => return false,
+            _ if self.peek_at(1) == Some(b'@') => 2,
+            _ => return false,
+        };
+        (0..sigils).all(|i| self.peek_at(i) == Some(b'@'))
+            && self
+                .peek_at(sigils)
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+    }
+
+    /// `:name`, but not a Snowflake path (`col:field`), a slice bound
+    /// (`arr[1:n]`) or either colon of `::`.
+    fn starts_named_placeholder(&self) -> bool {
+        let after_word = self.pos > 0
+            && matches!(self.bytes[self.pos - 1],
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b')' | b']' | b'"' | b'`' | b':');
+        !after_word
+            && self
+                .peek_at(1)
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+    }
+
+    /// Position just past a `%s` or `%(name)s` placeholder starting at the
+    /// current position. A word right after it means `%` is a modulo.
+    fn pyformat_placeholder_end(&self) -> Option<usize> {
+        let is_word = |c: &u8| c.is_ascii_alphanumeric() || *c == b'_';
+        let mut end = self.pos + 1;
+        if self.peek_at(1) == Some(b'(') {
+            let name = self.bytes[end + 1..]
+                .iter()
+                .take_while(|c| is_word(c))
+                .count();
+            if name == 0 || self.bytes.get(end + 1 + name) != Some(&b')') {
+                return None;
+            }
+            end += name + 2;
+        }
+        (self.bytes.get(end) == Some(&b's') && !self.bytes.get(end + 1).is_some_and(is_word))
+            .then_some(end + 1)
+    }
+
+    /// A `?`, `$`, `:` or `@` sigil followed by a number or name.
+    fn lex_bind_placeholder(&mut self) -> Token<'a> {
+        let start = self.pos;
+        self.advance();
+        while let Some(b) = self.peek() {
+            if b.is_ascii_alphanumeric() || b == b'_' {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        Token::Placeholder(self.slice(start, self.pos))
+    }
+
+    fn lex_variable(&mut self) -> Token<'a> {
+        let start = self.pos;
+        while self.peek() == Some(b'@') {
+            self.advance();
+        }
+        while let Some(b) = self.peek() {
+            if b.is_ascii_alphanumeric() || b == b'_' {
+                self.advance();
+            } else {
+                break;
+            }
+        }
+        Token::Identifier(self.slice(start, self.pos))
+    }
+
     fn lex_number(&mut self) -> Token<'a> {
         let start = self.pos;
+        // Hex and binary integers: 0x1F, 0b0101
+        let radix_digits: Option<fn(&u8) -> bool> = match (self.peek(), self.peek_at(1)) {
+            (Some(b'0'), Some(b'x' | b'X')) => Some(u8::is_ascii_hexdigit),
+            (Some(b'0'), Some(b'b' | b'B')) => Some(|b| matches!(b, b'0' | b'1')),
+            _ => None,
+        };
+        if let Some(is_digit) = radix_digits
+            && self.peek_at(2).as_ref().is_some_and(is_digit)
+        {
+            self.pos += 2;
+            while self.peek().as_ref().is_some_and(is_digit) {
+                self.advance();
+            }
+            return Token::NumberLiteral(self.slice(start, self.pos));
+        }
         // Integer part (may be empty if starting with '.')
         while let Some(b'0'..=b'9') = self.peek() {
             self.advance();
@@ -221,6 +600,16 @@ impl<'a> Lexer<'a> {
                 self.advance();
             }
         }
+        // Exponent: 1e10, 2.5E-3
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let sign = usize::from(matches!(self.peek_at(1), Some(b'+' | b'-')));
+            if matches!(self.peek_at(1 + sign), Some(b'0'..=b'9')) {
+                self.pos += 1 + sign;
+                while let Some(b'0'..=b'9') = self.peek() {
+                    self.advance();
+                }
+            }
+        }
         Token::NumberLiteral(self.slice(start, self.pos))
     }
 
@@ -240,7 +629,11 @@ impl<'a> Lexer<'a> {
 
         // Two-char operators
         if remaining.len() >= 2 {
-            for &op in TWO_CHAR_OPS {
+            let postgres_ops = match self.dialect {
+                Dialect::Postgres => POSTGRES_OPS,
+                _ => &[],
+            };
+            for &op in TWO_CHAR_OPS.iter().chain(postgres_ops) {
                 if remaining.starts_with(op) {
                     let start = self.pos;
                     self.pos += 2;
@@ -249,10 +642,15 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        // Single-char operator
+        // Single-char operator. `@` and `#` alone are operators only in
+        // PostgreSQL (absolute value, bitwise XOR)
         let start = self.pos;
         self.advance();
-        Token::Operator(self.slice(start, self.pos))
+        let op = self.slice(start, self.pos);
+        if matches!(op, "@" | "#") && self.dialect != Dialect::Postgres {
+            return Token::Error(op);
+        }
+        Token::Operator(op)
     }
 
     fn lex_template_variable(&mut self) -> Token<'a> {
@@ -261,17 +659,35 @@ impl<'a> Lexer<'a> {
         self.advance();
         self.advance();
         let content_start = self.pos;
+        let mut quote: Option<u8> = None;
         loop {
-            match self.peek() {
-                None => {
+            match (self.peek(), quote) {
+                (None, _) => {
                     // Unclosed: emit first '{' as operator
                     self.pos = brace_start + 1;
                     return Token::Operator(self.slice(brace_start, self.pos));
                 }
-                Some(b'}') if self.peek_at(1) == Some(b'}') => {
-                    let end = self.pos;
+                // Go template string arguments may contain '}}'
+                (Some(b'\\'), Some(b'"')) => {
+                    self.advance();
+                    self.advance();
+                }
+                (Some(b), Some(q)) if b == q => {
+                    self.advance();
+                    quote = None;
+                }
+                (Some(b'"' | b'`'), None) => {
+                    quote = self.advance();
+                }
+                (Some(b'}'), None) if self.peek_at(1) == Some(b'}') => {
+                    let mut end = self.pos;
                     self.advance();
                     self.advance();
+                    // Handlebars triple-stash: {{{raw}}}
+                    if self.bytes[content_start] == b'{' && self.peek() == Some(b'}') {
+                        self.advance();
+                        end += 1;
+                    }
                     return Token::TemplateVariable(self.slice(content_start, end));
                 }
                 _ => {
@@ -281,6 +697,104 @@ impl<'a> Lexer<'a> {
         }
     }
 
+    fn lex_template_block(&mut self) -> Token<'a> {
+        let start = self.pos;
+        let close = self.peek_at(1).unwrap_or(b'%');
+        // Skip '{%' / '{#'
+        self.advance();
+        self.advance();
+        let mut quote: Option<u8> = None;
+        loop {
+            match (self.peek(), quote) {
+                (None, _) => {
+                    // Unclosed: emit '{' as operator
+                    self.pos = start + 1;
+                    return Token::Operator(self.slice(start, self.pos));
+                }
+                (Some(b), Some(q)) if b == q => {
+                    self.advance();
+                    quote = None;
+                }
+                (Some(b'\'' | b'"'), None) if close == b'%' => {
+                    quote = self.advance();
+                }
+                (Some(b), None) if b == close && self.peek_at(1) == Some(b'}') => {
+                    self.advance();
+                    self.advance();
+                    return Token::TemplateBlock(self.slice(start, self.pos));
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
+    fn lex_erb_tag(&mut self) -> Token<'a> {
+        let start = self.pos;
+        // Caller guarantees a closing '%>' exists
+        let close = self.find_from(b"%>", 2).unwrap_or(self.bytes.len());
+        self.pos = (close + 2).min(self.bytes.len());
+        Token::TemplateTag(self.slice(start, self.pos))
+    }
+
+    /// Position just past the closing delimiter of a dollar-quoted string
+    /// starting at the current position. Tags follow identifier rules, so
+    /// `$1` stays a placeholder.
+    fn dollar_string_end(&self) -> Option<usize> {
+        if !self.dialect.supports_dollar_strings() {
+            return None;
+        }
+        let tag = if self.dialect == Dialect::Postgres
+            && self
+                .peek_at(1)
+                .is_some_and(|c| c.is_ascii_alphabetic() || c == b'_')
+        {
+            self.bytes[self.pos + 1..]
+                .iter()
+                .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+                .count()
+        } else {
+            0
+        };
+        if self.peek_at(tag + 1) != Some(b'$') {
+            return None;
+        }
+        let delimiter = &self.bytes[self.pos..self.pos + tag + 2];
+        self.find_from(delimiter, delimiter.len())
+            .map(|close| close + delimiter.len())
+    }
+
+    fn lex_dollar_string(&mut self) -> Token<'a> {
+        let start = self.pos;
+        // Caller guarantees a closing delimiter exists
+        self.pos = self.dollar_string_end().unwrap_or(self.bytes.len());
+        Token::DollarString(self.slice(start, self.pos))
+    }
+
+    fn lex_flyway_placeholder(&mut self) -> Token<'a> {
+        let start = self.pos;
+        // Skip '${'
+        self.advance();
+        self.advance();
+        loop {
+            match self.peek() {
+                None | Some(b'\n') => {
+                    // Unclosed: emit '$' as operator
+                    self.pos = start + 1;
+                    return Token::Operator(self.slice(start, self.pos));
+                }
+                Some(b'}') => {
+                    self.advance();
+                    return Token::Placeholder(self.slice(start, self.pos));
+                }
+                _ => {
+                    self.advance();
+                }
+            }
+        }
+    }
+
     fn lex_word(&mut self) -> Token<'a> {
         let start = self.pos;
         while let Some(b) = self.peek() {
@@ -292,12 +806,252 @@ impl<'a> Lexer<'a> {
         }
         let word = self.slice(start, self.pos);
 
+        if self.dialect == Dialect::Oracle
+            && self.starts_plsql_unit(word)
+            && let Some(end) = self.find_plsql_unit_end()
+        {
+            self.pos = end;
+            return Token::ProceduralBlock(self.slice(start, end));
+        }
+
+        if self.starts_procedural_block(word)
+            && let Some(end) = self.scan_procedural_block(word)
+        {
+            self.pos = end;
+            return Token::ProceduralBlock(self.slice(start, end));
+        }
+
+        if let Some((end, delimiter)) = self.delimiter_command(word, start) {
+            self.pos = end;
+            self.delimiter = (delimiter != b";").then_some(delimiter);
+            return Token::BatchSeparator(self.slice(start, end));
+        }
+
+        if self.delimiter.is_some()
+            && self.at_statement_start
+            && !self.scanning_block
+            && let Some(end) = self.find_delimited_body_end()
+        {
+            self.pos = end;
+            return Token::ProceduralBlock(self.slice(start, end));
+        }
+
+        if self.is_batch_separator(word, start) {
+            self.skip_batch_count();
+            return Token::BatchSeparator(self.slice(start, self.pos));
+        }
+
         match lookup_keyword(word) {
+            Some(kind) if !self.dialect.has_keyword(kind) => Token::Identifier(word),
             Some(kind) => self.try_combine_keyword(kind),
-            None => Token::Identifier(word),
+            None => self.try_identifier_keyword(word),
         }
     }
 
+    fn try_identifier_keyword(&mut self, word: &'a str) -> Token<'a> {
+        for &(first, expected, combined) in IDENTIFIER_TWO_WORD_KEYWORDS {
+            if word.eq_ignore_ascii_case(first)
+                && let Some((next, next_end)) = self.peek_word_after_whitespace(self.pos)
+                && next.eq_ignore_ascii_case(expected)
+            {
+                self.pos = next_end;
+                return Token::Keyword(combined);
+            }
+        }
+        Token::Identifier(word)
+    }
+
+    /// T-SQL `GO` (optionally with a repeat count) alone on its line ends a batch.
+    fn is_batch_separator(&self, word: &str, start: usize) -> bool {
+        if self.dialect != Dialect::Tsql || !word.eq_ignore_ascii_case("GO") {
+            return false;
+        }
+        let mut rest = self.bytes[self.pos..]
+            .iter()
+            .take_while(|&&b| b != b'\n')
+            .skip_while(|b| b.is_ascii_whitespace())
+            .skip_while(|b| b.is_ascii_digit());
+        self.is_line_start(start) && rest.all(|b| b.is_ascii_whitespace())
+    }
+
+    /// MySQL client `DELIMITER //` starting a statement line: statements
+    /// end at `//` instead of `;` until `DELIMITER ;`. Returns the end of
+    /// the command, without trailing whitespace, and the new terminator.
+    fn delimiter_command(&self, word: &str, start: usize) -> Option<(usize, &'a [u8])> {
+        if self.dialect != Dialect::Mysql
+            || !self.at_statement_start
+            || !word.eq_ignore_ascii_case("DELIMITER")
+            || !self.is_line_start(start)
+            || !matches!(self.peek(), Some(b' ' | b'\t'))
+        {
+            return None;
+        }
+        let line_end = self.bytes[self.pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(self.bytes.len(), |p| self.pos + p);
+        let rest = self.input[self.pos..line_end].trim();
+        let delimiter = rest.split_ascii_whitespace().next()?;
+        let end = self.pos + self.input[self.pos..line_end].trim_end().len();
+        Some((end, delimiter.as_bytes()))
+    }
+
+    /// End of a statement under a `DELIMITER` whose body has semicolons of
+    /// its own, like a stored procedure: the last non-blank byte before the
+    /// terminator. Returns `None` for a statement without semicolons, or
+    /// when no terminator follows.
+    fn find_delimited_body_end(&self) -> Option<usize> {
+        let mut scanner = self.clone();
+        scanner.scanning_block = true;
+        let mut has_semicolon = false;
+        loop {
+            let before = scanner.pos;
+            match scanner.next()? {
+                Token::Semicolon => has_semicolon = true,
+                Token::BatchSeparator(_) if has_semicolon => {
+                    return Some(self.input[..before].trim_end().len());
+                }
+                Token::BatchSeparator(_) => return None,
+                _ => {}
+            }
+        }
+    }
+
+    /// Whether only whitespace precedes `pos` on its line.
+    fn is_line_start(&self, pos: usize) -> bool {
+        self.bytes[..pos]
+            .iter()
+            .rev()
+            .take_while(|&&b| b != b'\n')
+            .all(|b| b.is_ascii_whitespace())
+    }
+
+    /// Whether only whitespace follows `pos` on its line.
+    fn is_line_end(&self, pos: usize) -> bool {
+        self.bytes[pos..]
+            .iter()
+            .take_while(|&&b| b != b'\n')
+            .all(|b| b.is_ascii_whitespace())
+    }
+
+    /// SQL*Plus `/` alone on a line: runs the buffer, ending the statement.
+    fn is_slash_line(&self, pos: usize) -> bool {
+        self.bytes.get(pos) == Some(&b'/') && self.is_line_start(pos) && self.is_line_end(pos + 1)
+    }
+
+    /// PL/SQL units are sent to the server as a whole and end at a `/` line,
+    /// since their body contains semicolons: anonymous blocks
+    /// (`DECLARE`/`BEGIN`) and `CREATE [OR REPLACE] PROCEDURE|FUNCTION|...`.
+    fn starts_plsql_unit(&self, word: &str) -> bool {
+        if !self.at_statement_start {
+            return false;
+        }
+        if word.eq_ignore_ascii_case("DECLARE") || word.eq_ignore_ascii_case("BEGIN") {
+            return true;
+        }
+        if !word.eq_ignore_ascii_case("CREATE") {
+            return false;
+        }
+        let mut from = self.pos;
+        while let Some((next, end)) = self.peek_word_after_whitespace(from) {
+            if ["OR", "REPLACE", "EDITIONABLE", "NONEDITIONABLE"]
+                .iter()
+                .any(|w| next.eq_ignore_ascii_case(w))
+            {
+                from = end;
+                continue;
+            }
+            return ["PROCEDURE", "FUNCTION", "PACKAGE", "TRIGGER", "TYPE"]
+                .iter()
+                .any(|w| next.eq_ignore_ascii_case(w));
+        }
+        false
+    }
+
+    /// End of a PL/SQL unit: the last non-blank byte before its `/` line.
+    /// Returns `None` when no `/` line follows.
+    fn find_plsql_unit_end(&self) -> Option<usize> {
+        let slash = (self.pos..self.bytes.len()).find(|&p| self.is_slash_line(p))?;
+        let body = &self.input[..slash];
+        Some(body.trim_end().len())
+    }
+
+    fn skip_batch_count(&mut self) {
+        let mut end = self.pos;
+        while matches!(self.bytes.get(end), Some(b' ' | b'\t')) {
+            end += 1;
+        }
+        if self.bytes.get(end).is_some_and(|b| b.is_ascii_digit()) {
+            while self.bytes.get(end).is_some_and(|b| b.is_ascii_digit()) {
+                end += 1;
+            }
+            self.pos = end;
+        }
+    }
+
+    /// Snowflake Scripting blocks (`DECLARE ... BEGIN ... END`) are passed
+    /// through verbatim. `BEGIN` alone, `BEGIN TRANSACTION` and `BEGIN WORK`
+    /// start transactions instead.
+    fn starts_procedural_block(&self, word: &str) -> bool {
+        if self.dialect != Dialect::Snowflake || !self.at_statement_start || self.scanning_block {
+            return false;
+        }
+        if word.eq_ignore_ascii_case("DECLARE") {
+            return true;
+        }
+        if !word.eq_ignore_ascii_case("BEGIN") {
+            return false;
+        }
+        match self.peek_word_after_whitespace(self.pos) {
+            Some((next, _)) => !["TRANSACTION", "WORK", "NAME"]
+                .iter()
+                .any(|w| next.eq_ignore_ascii_case(w)),
+            None => false,
+        }
+    }
+
+    /// Find the end of a block opened by `word`, balancing BEGIN/CASE with END.
+    /// Returns `None` when the block is never closed.
+    fn scan_procedural_block(&self, word: &str) -> Option<usize> {
+        let mut scanner = self.clone();
+        scanner.scanning_block = true;
+        let mut depth = usize::from(word.eq_ignore_ascii_case("BEGIN"));
+        let mut opened = depth > 0;
+        while let Some(token) = scanner.next() {
+            match token {
+                Token::Keyword(KeywordKind::Begin) => {
+                    depth += 1;
+                    opened = true;
+                }
+                Token::Keyword(KeywordKind::Case) => depth += 1,
+                Token::Keyword(KeywordKind::End) => {
+                    let mut lookahead = scanner.clone();
+                    let next = lookahead.find(|t| !matches!(t, Token::Whitespace(_)));
+                    match next {
+                        Some(Token::Keyword(KeywordKind::If | KeywordKind::For)) => {
+                            scanner = lookahead;
+                            continue;
+                        }
+                        Some(Token::Identifier(w))
+                            if END_QUALIFIERS.iter().any(|q| w.eq_ignore_ascii_case(q)) =>
+                        {
+                            scanner = lookahead;
+                            continue;
+                        }
+                        Some(Token::Keyword(KeywordKind::Case)) => scanner = lookahead,
+                        _ => {}
+                    }
+                    depth = depth.saturating_sub(1);
+                    if opened && depth == 0 {
+                        return Some(scanner.pos);
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
     /// Peek ahead past optional whitespace for a word.
     /// Returns (word_str, word_end_pos) if found.
     fn peek_word_after_whitespace(&self, from: usize) -> Option<(&'a str, usize)> {
@@ -399,16 +1153,269 @@ impl<'a> Lexer<'a> {
     }
 }
 
-impl<'a> Iterator for Lexer<'a> {
-    type Item = Token<'a>;
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.next_token()?;
+        match token {
+            Token::Whitespace(_)
+            | Token::LineComment(_)
+            | Token::BlockComment(_)
+            | Token::Unformatted(_) => {}
+            Token::Semicolon | Token::BatchSeparator(_) => self.at_statement_start = true,
+            _ => self.at_statement_start = false,
+        }
+        Some(token)
+    }
+}
+
+/// `Some(true)` for a `-- sql-indent: on` comment, `Some(false)` for
+/// `-- sql-indent: off`, given the text after `--`.
+fn region_directive(text: &str) -> Option<bool> {
+    let text = text.trim().to_ascii_lowercase();
+    match text.strip_prefix("sql-indent:")?.trim() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Split `input` into tokens with the generic dialect's rules. Whitespace
+/// and comments are kept as tokens of their own.
+pub fn tokenize(input: &str) -> Vec<Token<'_>> {
+    tokenize_with_dialect(input, Dialect::Generic)
+}
+
+/// Split `input` into tokens, recognizing the quoting rules, variables and
+/// batch separators of `dialect`.
+pub fn tokenize_with_dialect(input: &str, dialect: Dialect) -> Vec<Token<'_>> {
+    Lexer::new(input, dialect).collect()
+}
+
+/// Like [`tokenize_with_dialect`], paired with the byte range of `input`
+/// each token was read from. Keywords do not carry their source text, so
+/// this is how callers recover the original spelling and position.
+pub fn tokenize_with_offsets(input: &str, dialect: Dialect) -> Vec<(Token<'_>, Range<usize>)> {
+    let mut lexer = Lexer::new(input, dialect);
+    let mut tokens = Vec::new();
+    loop {
+        let start = lexer.pos;
+        let Some(token) = lexer.next() else {
+            break;
+        };
+        tokens.push((token, start..lexer.pos));
+    }
+    tokens
+}
+
+/// Like [`tokenize_with_offsets`], with the line and column each token
+/// starts at, for mapping tokens back to the input in diagnostics and
+/// editor integrations.
+pub fn tokenize_spanned(input: &str, dialect: Dialect) -> Vec<SpannedToken<'_>> {
+    let mut line = 1;
+    let mut line_start = 0;
+    tokenize_with_offsets(input, dialect)
+        .into_iter()
+        .map(|(token, range)| {
+            let span = Span {
+                start: range.start,
+                end: range.end,
+                line,
+                column: input[line_start..range.start].chars().count() + 1,
+            };
+            for (i, _) in input[range].match_indices('\n') {
+                line += 1;
+                line_start = span.start + i + 1;
+            }
+            SpannedToken { token, span }
+        })
+        .collect()
+}
+
+/// Whether `token` is a character the lexer has no rule for, passed through
+/// as [`Token::Error`].
+pub fn is_unknown_character(token: &Token<'_>) -> bool {
+    matches!(token, Token::Error(_))
+}
+
+/// A problem in the input that the lexer recovers from, usually by reading
+/// to the end: the formatted output would not mean what the author intended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub message: &'static str,
+    /// Byte offset of the offending character
+    pub offset: usize,
+}
+
+/// Unclosed strings, quoted identifiers and block comments, and parentheses
+/// left unbalanced within a statement, in the order they appear in `input`.
+pub fn diagnostics(input: &str, dialect: Dialect) -> Vec<Diagnostic> {
+    let mut found = Vec::new();
+    let mut open_parens = Vec::new();
+    for (token, range) in tokenize_with_offsets(input, dialect) {
+        let text = &input[range.clone()];
+        let message = match token {
+            Token::StringLiteral(_) if !is_string_closed(text, dialect) => {
+                "unclosed string literal"
+            }
+            Token::QuotedIdentifier(_) if !is_closed(text, b'"') => "unclosed quoted identifier",
+            Token::BacktickIdentifier(_) if !is_closed(text, b'`') => "unclosed quoted identifier",
+            Token::BracketIdentifier(_) if !is_closed(text, b']') => "unclosed quoted identifier",
+            Token::PrefixedString(_) if !is_prefixed_string_closed(text) => {
+                "unclosed string literal"
+            }
+            Token::BlockComment(_) if text.len() < 4 || !text.ends_with("*/") => {
+                "unterminated block comment"
+            }
+            Token::OpenParen => {
+                open_parens.push(range.start);
+                continue;
+            }
+            Token::CloseParen if open_parens.pop().is_none() => "unmatched closing parenthesis",
+            Token::Semicolon | Token::BatchSeparator(_) => {
+                found.extend(open_parens.drain(..).map(|offset| Diagnostic {
+                    message: "unclosed parenthesis",
+                    offset,
+                }));
+                continue;
+            }
+            _ => continue,
+        };
+        found.push(Diagnostic {
+            message,
+            offset: range.start,
+        });
+    }
+    found.extend(open_parens.into_iter().map(|offset| Diagnostic {
+        message: "unclosed parenthesis",
+        offset,
+    }));
+    found.sort_by_key(|d| d.offset);
+    found
+}
+
+/// Whether a quoted token's text ends with its closing quote. Doubled quotes
+/// escape one another, so the closing one leaves an odd number at the end.
+fn is_closed(text: &str, quote: u8) -> bool {
+    let trailing = text.as_bytes()[1..]
+        .iter()
+        .rev()
+        .take_while(|&&b| b == quote)
+        .count();
+    trailing % 2 == 1
+}
+
+/// Whether a `'...'` string has its closing quote, where the dialect may
+/// escape quotes with a backslash too.
+fn is_string_closed(text: &str, dialect: Dialect) -> bool {
+    if dialect.supports_backslash_escapes() {
+        escape_string_end(text.as_bytes()) == Some(text.len())
+    } else {
+        is_closed(text, b'\'')
+    }
+}
+
+/// Whether an `E'...'`, `x'...'`, `b'...'` or `U&'...'` string has its
+/// closing quote.
+/// Oracle's `q'[...]'` is only lexed when closed.
+fn is_prefixed_string_closed(text: &str) -> bool {
+    match text.as_bytes()[0] {
+        b'e' | b'E' => escape_string_end(&text.as_bytes()[1..]) == Some(text.len() - 1),
+        b'x' | b'X' | b'b' | b'B' => text.len() > 2 && text.ends_with('\''),
+        b'u' | b'U' => quoted_string_end(&text.as_bytes()[2..]).is_some(),
+        _ => true,
+    }
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next_token()
+/// Position just past the closing quote of the standard string whose opening
+/// quote starts `bytes`, where `''` is a quote inside it.
+fn quoted_string_end(bytes: &[u8]) -> Option<usize> {
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+            b'\'' => return Some(i + 1),
+            _ => i += 1,
+        }
     }
+    None
 }
 
-pub fn tokenize(input: &str) -> Vec<Token<'_>> {
-    Lexer::new(input).collect()
+/// Position just past the closing quote of the escape string whose opening
+/// quote starts `bytes`, where both `\'` and `''` are quotes inside it.
+fn escape_string_end(bytes: &[u8]) -> Option<usize> {
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'\'' if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+            b'\'' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// Byte offsets just past each statement terminator the lexer sees: a
+/// semicolon or a batch separator (`GO`, `/`). The input is lexed as the
+/// offsets are taken, without holding its tokens.
+pub(crate) fn statement_ends(input: &str, dialect: Dialect) -> impl Iterator<Item = usize> + '_ {
+    let mut lexer = Lexer::new(input, dialect);
+    std::iter::from_fn(move || {
+        loop {
+            if let Token::Semicolon | Token::BatchSeparator(_) = lexer.next()? {
+                return Some(lexer.pos);
+            }
+        }
+    })
+}
+
+/// Split `input` into statements at semicolons and batch separators seen
+/// by the lexer, so terminators inside string literals, quoted identifiers,
+/// comments, the dialect's dollar-quoted strings and procedural blocks are
+/// ignored. Each statement keeps its terminating semicolon; surrounding
+/// whitespace and blank statements are dropped.
+pub fn split_statements(input: &str, dialect: Dialect) -> Vec<&str> {
+    statement_ranges(input, dialect)
+        .into_iter()
+        .map(|range| &input[range])
+        .collect()
+}
+
+/// Byte ranges of the statements [`split_statements`] returns.
+pub fn statement_ranges(input: &str, dialect: Dialect) -> Vec<Range<usize>> {
+    let mut lexer = Lexer::new(input, dialect);
+    let mut statements = Vec::new();
+    let mut start = 0;
+    loop {
+        let before = lexer.pos;
+        let Some(token) = lexer.next() else {
+            break;
+        };
+        let end = match token {
+            Token::Semicolon => lexer.pos,
+            Token::BatchSeparator(_) => before,
+            _ => continue,
+        };
+        statements.push(trimmed(input, start..end));
+        start = lexer.pos;
+    }
+    statements.push(trimmed(input, start..input.len()));
+    statements.retain(|range| {
+        !input[range.clone()]
+            .trim_end_matches(';')
+            .trim_end()
+            .is_empty()
+    });
+    statements
+}
+
+/// `range` without the whitespace at either end of its text.
+fn trimmed(input: &str, range: Range<usize>) -> Range<usize> {
+    let text = &input[range.clone()];
+    let start = range.start + (text.len() - text.trim_start().len());
+    start..start + text.trim().len()
 }
 
 #[cfg(test)]
@@ -440,6 +1447,171 @@ mod tests {
         assert_eq!(non_ws[5], &Token::Identifier("comets"));
     }
 
+    #[test]
+    fn test_diagnostics() {
+        let found = |sql| diagnostics(sql, Dialect::Generic);
+        assert_eq!(found("select 'a''b', \"x\", (1) /* c */"), vec![]);
+        assert_eq!(
+            found("select 'it''s"),
+            vec![Diagnostic {
+                message: "unclosed string literal",
+                offset: 7
+            }]
+        );
+        assert_eq!(
+            found("select (1; select 2)"),
+            vec![
+                Diagnostic {
+                    message: "unclosed parenthesis",
+                    offset: 7
+                },
+                Diagnostic {
+                    message: "unmatched closing parenthesis",
+                    offset: 19
+                },
+            ]
+        );
+        assert_eq!(
+            found("select (a)) from (t /* x"),
+            vec![
+                Diagnostic {
+                    message: "unmatched closing parenthesis",
+                    offset: 10
+                },
+                Diagnostic {
+                    message: "unclosed parenthesis",
+                    offset: 17
+                },
+                Diagnostic {
+                    message: "unterminated block comment",
+                    offset: 20
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statement_ends() {
+        assert_eq!(
+            statement_ends("select ';'; select 2;\nGO\nx", Dialect::Tsql).collect::<Vec<_>>(),
+            vec![11, 21, 24]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_with_offsets() {
+        let input = "select 'a' -- c\nfrom t";
+        let tokens = tokenize_with_offsets(input, Dialect::Generic);
+        let texts: Vec<&str> = tokens.iter().map(|(_, r)| &input[r.clone()]).collect();
+        assert_eq!(
+            texts,
+            vec!["select", " ", "'a'", " ", "-- c", "\n", "from", " ", "t"]
+        );
+        assert_eq!(tokens[2].0, Token::StringLiteral("a"));
+    }
+
+    #[test]
+    fn test_tokenize_spanned() {
+        let tokens = tokenize_spanned("select\n  'é', x", Dialect::Generic);
+        let spans: Vec<(usize, usize, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.span.start, t.span.end, t.span.line, t.span.column))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                (0, 6, 1, 1),
+                (6, 9, 1, 7),
+                (9, 13, 2, 3),
+                (13, 14, 2, 6),
+                (14, 15, 2, 7),
+                (15, 16, 2, 8)
+            ]
+        );
+        assert_eq!(tokens[2].token, Token::StringLiteral("é"));
+    }
+
+    #[test]
+    fn test_is_unknown_character() {
+        let unknown: Vec<Token> = tokenize("a ¤ b # c ? d -> e := f { g `h`")
+            .into_iter()
+            .filter(is_unknown_character)
+            .collect();
+        assert_eq!(
+            unknown,
+            vec![
+                Token::Error("¤"),
+                Token::Error("#"),
+                Token::Error("`"),
+                Token::Error("`")
+            ]
+        );
+        // `#` is an operator in PostgreSQL
+        assert_eq!(
+            tokenize_with_dialect("a # b", Dialect::Postgres)[2],
+            Token::Operator("#")
+        );
+    }
+
+    #[test]
+    fn test_bind_placeholders() {
+        let placeholders = |sql, dialect| -> Vec<Token> {
+            tokenize_with_dialect(sql, dialect)
+                .into_iter()
+                .filter(|t| matches!(t, Token::Placeholder(_)))
+                .collect()
+        };
+        assert_eq!(
+            placeholders(
+                "select ?, ?2, $1, :id, x::int, a:b, arr[1:n] := 1",
+                Dialect::Generic
+            ),
+            vec![
+                Token::Placeholder("?"),
+                Token::Placeholder("?2"),
+                Token::Placeholder("$1"),
+                Token::Placeholder(":id"),
+            ]
+        );
+        assert_eq!(
+            placeholders("select j ? 'k', $1, @abs", Dialect::Postgres),
+            vec![Token::Placeholder("$1")]
+        );
+        assert_eq!(
+            placeholders(
+                "select @user_id, @@version, %s, %(name)s, a %s2, b%(x), c % s",
+                Dialect::Mysql
+            ),
+            vec![
+                Token::Placeholder("@user_id"),
+                Token::Placeholder("%s"),
+                Token::Placeholder("%(name)s"),
+            ]
+        );
+        let tokens = tokenize_with_dialect("select @@version", Dialect::Mysql);
+        assert_eq!(tokens[2], Token::Identifier("@@version"));
+    }
+
+    #[test]
+    fn test_unformatted_region() {
+        let input =
+            "select 1;\n-- sql-indent: off\nselect   a,\n       b;\n  -- sql-indent: on\nselect 2";
+        let tokens = tokenize(input);
+        assert!(tokens.contains(&Token::Unformatted(
+            "-- sql-indent: off\nselect   a,\n       b;\n  -- sql-indent: on"
+        )));
+        assert_eq!(tokens.last(), Some(&Token::NumberLiteral("2")));
+    }
+
+    #[test]
+    fn test_unformatted_region_until_end() {
+        assert_tokens!(
+            "--SQL-INDENT:OFF\nselect  1\n\n",
+            Token::Unformatted("--SQL-INDENT:OFF\nselect  1"),
+            Token::Whitespace("\n\n"),
+        );
+    }
+
     #[test]
     fn test_string_literal_simple() {
         assert_tokens!("'hello world'", Token::StringLiteral("hello world"));
@@ -632,6 +1804,20 @@ mod tests {
         assert!(tokens.is_empty());
     }
 
+    #[test]
+    fn test_non_ascii_outside_literals() {
+        assert_tokens!(
+            "a = «b»",
+            Token::Identifier("a"),
+            Token::Whitespace(" "),
+            Token::Operator("="),
+            Token::Whitespace(" "),
+            Token::Error("«"),
+            Token::Identifier("b"),
+            Token::Error("»"),
+        );
+    }
+
     #[test]
     fn test_unclosed_string() {
         assert_tokens!("'unclosed", Token::StringLiteral("unclosed"));
@@ -820,6 +2006,190 @@ mod tests {
         assert_tokens!("{{ tenant_id }}", Token::TemplateVariable(" tenant_id "));
     }
 
+    #[test]
+    fn test_lex_flyway_placeholder() {
+        let tokens = tokenize("${schema}.users");
+        assert_eq!(tokens[0], Token::Placeholder("${schema}"));
+        assert_eq!(tokens[1], Token::Dot);
+        assert_eq!(tokens[2], Token::Identifier("users"));
+    }
+
+    #[test]
+    fn test_lex_unclosed_flyway_placeholder() {
+        let tokens = tokenize("${tenant");
+        assert_eq!(tokens[0], Token::Operator("$"));
+        assert_eq!(tokens[1], Token::Operator("{"));
+        assert_eq!(tokens[2], Token::Identifier("tenant"));
+    }
+
+    #[test]
+    fn test_lex_liquibase_directive() {
+        let tokens = tokenize("--changeset alice:1\nCREATE TABLE t (id int);");
+        assert_eq!(tokens[0], Token::LineComment("changeset alice:1"));
+        assert!(tokens[0].is_directive_comment());
+    }
+
+    #[test]
+    fn test_lex_go_template_dotted() {
+        assert_tokens!("{{ .TableName }}", Token::TemplateVariable(" .TableName "));
+        assert_tokens!(
+            "{{ .Schema.Name }}",
+            Token::TemplateVariable(" .Schema.Name ")
+        );
+    }
+
+    #[test]
+    fn test_lex_go_template_trim_markers() {
+        assert_tokens!("{{- if .Cond -}}", Token::TemplateVariable("- if .Cond -"));
+    }
+
+    #[test]
+    fn test_lex_go_template_string_with_braces() {
+        assert_tokens!(
+            r#"{{ printf "%s}}" .X }}"#,
+            Token::TemplateVariable(r#" printf "%s}}" .X "#)
+        );
+        assert_tokens!("{{ `raw}}` }}", Token::TemplateVariable(" `raw}}` "));
+    }
+
+    #[test]
+    fn test_lex_handlebars_sections() {
+        assert_tokens!("{{#if admin}}", Token::TemplateVariable("#if admin"));
+        assert_tokens!("{{/if}}", Token::TemplateVariable("/if"));
+        assert_tokens!("{{^unless}}", Token::TemplateVariable("^unless"));
+    }
+
+    #[test]
+    fn test_lex_handlebars_triple_stash() {
+        assert_tokens!("{{{raw_sql}}}", Token::TemplateVariable("{raw_sql}"));
+    }
+
+    #[test]
+    fn test_lex_jinja_statement_block() {
+        assert_tokens!("{% set x = 1 %}", Token::TemplateBlock("{% set x = 1 %}"));
+        assert_tokens!(
+            "{%- if is_incremental() -%}",
+            Token::TemplateBlock("{%- if is_incremental() -%}")
+        );
+    }
+
+    #[test]
+    fn test_lex_jinja_statement_with_quoted_delimiter() {
+        assert_tokens!(
+            "{% set sep = '%}' %}",
+            Token::TemplateBlock("{% set sep = '%}' %}")
+        );
+    }
+
+    #[test]
+    fn test_lex_jinja_comment() {
+        assert_tokens!(
+            "{# it's a comment #}",
+            Token::TemplateBlock("{# it's a comment #}")
+        );
+    }
+
+    #[test]
+    fn test_lex_unclosed_jinja_block() {
+        let tokens = tokenize("{% if x");
+        assert_eq!(tokens[0], Token::Operator("{"));
+        assert_eq!(tokens[1], Token::Operator("%"));
+    }
+
+    #[test]
+    fn test_lex_erb_tags() {
+        assert_tokens!("<%= user.id %>", Token::TemplateTag("<%= user.id %>"));
+        assert_tokens!("<%- if admin -%>", Token::TemplateTag("<%- if admin -%>"));
+        assert_tokens!("<%# note %>", Token::TemplateTag("<%# note %>"));
+    }
+
+    #[test]
+    fn test_lex_erb_tag_keeps_inner_operators() {
+        let tokens = tokenize("id = <%= ids.join(',') %>");
+        let non_ws = strip_whitespace(&tokens);
+        assert_eq!(non_ws[2], &Token::TemplateTag("<%= ids.join(',') %>"));
+        assert_eq!(non_ws.len(), 3);
+    }
+
+    #[test]
+    fn test_lex_unclosed_erb_is_operator() {
+        let tokens = tokenize("a <% b");
+        let non_ws = strip_whitespace(&tokens);
+        assert_eq!(non_ws[1], &Token::Operator("<"));
+        assert_eq!(non_ws[2], &Token::Operator("%"));
+    }
+
+    #[test]
+    fn test_lex_named_argument_arrow() {
+        let tokens = tokenize("flatten(input => t.arr)");
+        let non_ws = strip_whitespace(&tokens);
+        assert_eq!(non_ws[3], &Token::Operator("=>"));
+    }
+
+    #[test]
+    fn test_lex_snowflake_dollar_string() {
+        let tokens =
+            tokenize_with_dialect("EXECUTE IMMEDIATE $$ SELECT 'a;b' $$;", Dialect::Snowflake);
+        let non_ws = strip_whitespace(&tokens);
+        assert_eq!(non_ws[2], &Token::DollarString("$$ SELECT 'a;b' $$"));
+        assert_eq!(non_ws[3], &Token::Semicolon);
+    }
+
+    #[test]
+    fn test_lex_postgres_dollar_strings() {
+        let sql = "CREATE FUNCTION f() RETURNS int AS $body$ SELECT $$a;b$$, $1 $body$ LANGUAGE sql; \
+                   SELECT $$it's$$, $q$x$q$, $1";
+        let tokens = tokenize_with_dialect(sql, Dialect::Postgres);
+        let strings: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::DollarString(_)))
+            .collect();
+        assert_eq!(
+            strings,
+            vec![
+                &Token::DollarString("$body$ SELECT $$a;b$$, $1 $body$"),
+                &Token::DollarString("$$it's$$"),
+                &Token::DollarString("$q$x$q$")
+            ]
+        );
+        assert_eq!(tokens.last(), Some(&Token::Placeholder("$1")));
+        assert_eq!(split_statements(sql, Dialect::Postgres).len(), 2);
+        // Snowflake has no tags
+        let tokens = tokenize_with_dialect("$q$x$q$", Dialect::Snowflake);
+        assert_eq!(tokens[0], Token::Error("$"));
+    }
+
+    #[test]
+    fn test_lex_dollar_string_generic_dialect_unchanged() {
+        let tokens = tokenize("$$ x $$");
+        assert_eq!(tokens[0], Token::Error("$"));
+    }
+
+    #[test]
+    fn test_lex_snowflake_declare_block() {
+        let sql = "DECLARE\n  n INT DEFAULT 0;\nBEGIN\n  IF (n = 0) THEN\n    n := 1;\n  END IF;\n  RETURN CASE WHEN n > 0 THEN 'a' END;\nEND;\nSELECT 1;";
+        let tokens = tokenize_with_dialect(sql, Dialect::Snowflake);
+        let block_end = sql.find("END;\nSELECT").unwrap() + 3;
+        assert_eq!(tokens[0], Token::ProceduralBlock(&sql[..block_end]));
+        assert_eq!(tokens[1], Token::Semicolon);
+        let non_ws = strip_whitespace(&tokens);
+        assert_eq!(non_ws[2], &Token::Keyword(KeywordKind::Select));
+    }
+
+    #[test]
+    fn test_lex_snowflake_begin_transaction_not_block() {
+        let tokens = tokenize_with_dialect("BEGIN TRANSACTION;", Dialect::Snowflake);
+        assert_eq!(tokens[0], Token::Keyword(KeywordKind::Begin));
+        let tokens = tokenize_with_dialect("BEGIN;", Dialect::Snowflake);
+        assert_eq!(tokens[0], Token::Keyword(KeywordKind::Begin));
+    }
+
+    #[test]
+    fn test_lex_snowflake_unclosed_block_falls_back() {
+        let tokens = tokenize_with_dialect("BEGIN SELECT 1;", Dialect::Snowflake);
+        assert_eq!(tokens[0], Token::Keyword(KeywordKind::Begin));
+    }
+
     #[test]
     fn test_lex_unclosed_template() {
         let tokens = tokenize("{ not_template");
@@ -827,4 +2197,350 @@ mod tests {
         assert_eq!(non_ws[0], &Token::Operator("{"));
         assert_eq!(non_ws[1], &Token::Identifier("not_template"));
     }
+
+    #[test]
+    fn test_lex_bigquery_backtick_identifier() {
+        let tokens = tokenize_with_dialect("SELECT * FROM `proj.ds.t`", Dialect::Bigquery);
+        assert_eq!(tokens.last(), Some(&Token::BacktickIdentifier("proj.ds.t")));
+    }
+
+    #[test]
+    fn test_lex_mysql_backtick_identifier() {
+        let tokens = tokenize_with_dialect("SELECT `my col`, `a``b` FROM `t`", Dialect::Mysql);
+        let identifiers: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::BacktickIdentifier(_)))
+            .collect();
+        assert_eq!(
+            identifiers,
+            vec![
+                &Token::BacktickIdentifier("my col"),
+                &Token::BacktickIdentifier("a``b"),
+                &Token::BacktickIdentifier("t")
+            ]
+        );
+        assert!(diagnostics("SELECT `a``b`", Dialect::Mysql).is_empty());
+        assert_eq!(diagnostics("SELECT `a``", Dialect::Mysql).len(), 1);
+    }
+
+    #[test]
+    fn test_lex_backtick_generic_is_error() {
+        let tokens = tokenize("`a`");
+        assert_eq!(tokens[0], Token::Error("`"));
+    }
+
+    #[test]
+    fn test_lex_bigquery_system_variable() {
+        let tokens = tokenize_with_dialect("SELECT @@error.message", Dialect::Bigquery);
+        assert_eq!(tokens[2], Token::Identifier("@@error"));
+        assert_eq!(tokens[3], Token::Dot);
+    }
+
+    #[test]
+    fn test_lex_tsql_go_batch_separator() {
+        let tokens = tokenize_with_dialect("SELECT 1\nGO\nSELECT 2\ngo 5\n", Dialect::Tsql);
+        assert_eq!(tokens[4], Token::BatchSeparator("GO"));
+        assert!(tokens.contains(&Token::BatchSeparator("go 5")));
+    }
+
+    #[test]
+    fn test_lex_tsql_bracket_identifier() {
+        let tokens =
+            tokenize_with_dialect("SELECT [Order ID], [a]]b] FROM [dbo].[t]", Dialect::Tsql);
+        let identifiers: Vec<&Token> = tokens
+            .iter()
+            .filter(|t| matches!(t, Token::BracketIdentifier(_)))
+            .collect();
+        assert_eq!(
+            identifiers,
+            vec![
+                &Token::BracketIdentifier("Order ID"),
+                &Token::BracketIdentifier("a]]b"),
+                &Token::BracketIdentifier("dbo"),
+                &Token::BracketIdentifier("t")
+            ]
+        );
+        assert!(diagnostics("SELECT [a]]b]", Dialect::Tsql).is_empty());
+        assert_eq!(diagnostics("SELECT [a]]", Dialect::Tsql).len(), 1);
+        assert_eq!(tokenize("a[1]")[1], Token::Error("["));
+    }
+
+    #[test]
+    fn test_lex_tsql_go_mid_line_is_identifier() {
+        let tokens = tokenize_with_dialect("SELECT go FROM t", Dialect::Tsql);
+        assert_eq!(tokens[2], Token::Identifier("go"));
+        let tokens = tokenize("SELECT 1\nGO\n");
+        assert_eq!(tokens[4], Token::Identifier("GO"));
+    }
+
+    #[test]
+    fn test_lex_tsql_variables_and_top() {
+        let tokens = tokenize_with_dialect("SELECT TOP 1 @x, @@ROWCOUNT", Dialect::Tsql);
+        assert_eq!(tokens[2], Token::Keyword(KeywordKind::Top));
+        assert_eq!(tokens[6], Token::Identifier("@x"));
+        assert_eq!(tokens[9], Token::Identifier("@@ROWCOUNT"));
+        assert_eq!(tokenize("SELECT top")[2], Token::Identifier("top"));
+    }
+
+    #[test]
+    fn test_lex_snowflake_path_colon() {
+        let tokens = tokenize_with_dialect("v:a.b::string = :id", Dialect::Snowflake);
+        assert_eq!(tokens[1], Token::Operator(":"));
+        assert_eq!(tokens[2], Token::Identifier("a"));
+        assert_eq!(tokens[5], Token::Operator("::"));
+        assert_eq!(tokens[10], Token::Placeholder(":id"));
+    }
+
+    #[test]
+    fn test_lex_qualify_by_dialect() {
+        let sql = "SELECT a FROM t QUALIFY n = 1";
+        assert_eq!(
+            tokenize_with_dialect(sql, Dialect::Snowflake)[8],
+            Token::Keyword(KeywordKind::Qualify)
+        );
+        assert_eq!(tokenize(sql)[8], Token::Identifier("QUALIFY"));
+    }
+
+    #[test]
+    fn test_lex_oracle_plsql_block_until_slash() {
+        let sql = "BEGIN\n  dbms_output.put_line('a;b');\nEND;\n/\nSELECT 1 FROM dual;";
+        let tokens = tokenize_with_dialect(sql, Dialect::Oracle);
+        assert_eq!(
+            tokens[0],
+            Token::ProceduralBlock("BEGIN\n  dbms_output.put_line('a;b');\nEND;")
+        );
+        assert_eq!(tokens[2], Token::BatchSeparator("/"));
+        assert_eq!(tokens[4], Token::Keyword(KeywordKind::Select));
+    }
+
+    #[test]
+    fn test_lex_oracle_create_procedure() {
+        let sql = "CREATE OR REPLACE PROCEDURE p AS\nBEGIN\n  NULL;\nEND p;\n/";
+        let tokens = tokenize_with_dialect(sql, Dialect::Oracle);
+        assert_eq!(
+            tokens,
+            vec![
+                Token::ProceduralBlock("CREATE OR REPLACE PROCEDURE p AS\nBEGIN\n  NULL;\nEND p;"),
+                Token::Whitespace("\n"),
+                Token::BatchSeparator("/"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_mysql_delimiter() {
+        let sql = "DELIMITER $$\nCREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND$$\n\
+                   SELECT 2$$\nDELIMITER ;\nSELECT 3;";
+        let tokens = tokenize_with_dialect(sql, Dialect::Mysql);
+        assert_eq!(tokens[0], Token::BatchSeparator("DELIMITER $$"));
+        assert_eq!(
+            tokens[2],
+            Token::ProceduralBlock("CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND")
+        );
+        assert_eq!(tokens[3], Token::BatchSeparator("$$"));
+        assert_eq!(tokens[5], Token::Keyword(KeywordKind::Select));
+        assert_eq!(tokens[8], Token::BatchSeparator("$$"));
+        assert_eq!(tokens[10], Token::BatchSeparator("DELIMITER ;"));
+        assert_eq!(
+            split_statements(sql, Dialect::Mysql),
+            vec![
+                "CREATE PROCEDURE p()\nBEGIN\n  SELECT 1;\nEND",
+                "SELECT 2",
+                "SELECT 3;"
+            ]
+        );
+        assert_eq!(tokenize("DELIMITER $$")[0], Token::Identifier("DELIMITER"));
+    }
+
+    #[test]
+    fn test_lex_oracle_q_quote_and_outer_join() {
+        let tokens = tokenize_with_dialect(
+            "SELECT q'[it's]', q'!a!' FROM a, b WHERE a.id = b.id(+)",
+            Dialect::Oracle,
+        );
+        assert_eq!(tokens[2], Token::PrefixedString("q'[it's]'"));
+        assert_eq!(tokens[5], Token::PrefixedString("q'!a!'"));
+        assert_eq!(tokens.last(), Some(&Token::Operator("(+)")));
+    }
+
+    #[test]
+    fn test_lex_postgres_json_and_array_operators() {
+        let operators = |sql, dialect| -> Vec<Token> {
+            tokenize_with_dialect(sql, dialect)
+                .into_iter()
+                .filter(|t| matches!(t, Token::Operator(_)))
+                .collect()
+        };
+        let sql = "a@>b, a<@b, a?|b, a?&b, a#>b, a#>>b, a#-b, a~~b, a!~~b, a&&b, a<->b, ||/a";
+        assert_eq!(
+            operators(sql, Dialect::Postgres),
+            [
+                "@>", "<@", "?|", "?&", "#>", "#>>", "#-", "~~", "!~~", "&&", "<->", "||/"
+            ]
+            .map(Token::Operator)
+        );
+        // `<@name` compares with a parameter outside PostgreSQL
+        assert_eq!(operators("a<@b", Dialect::Tsql), vec![Token::Operator("<")]);
+    }
+
+    #[test]
+    fn test_lex_postgres_escape_string() {
+        let sql = r"SELECT E'it\'s; a\\', e'x''y', E FROM t";
+        let tokens = tokenize_with_dialect(sql, Dialect::Postgres);
+        assert_eq!(tokens[2], Token::PrefixedString(r"E'it\'s; a\\'"));
+        assert_eq!(tokens[5], Token::PrefixedString("e'x''y'"));
+        assert_eq!(tokens[8], Token::Identifier("E"));
+        assert!(diagnostics(sql, Dialect::Postgres).is_empty());
+        assert_eq!(
+            diagnostics(r"SELECT E'a\'", Dialect::Postgres)[0].message,
+            "unclosed string literal"
+        );
+        // Elsewhere E is a name and the backslash is ordinary
+        let tokens = tokenize(r"SELECT E'a\'");
+        assert_eq!(tokens[3], Token::StringLiteral(r"a\"));
+    }
+
+    #[test]
+    fn test_lex_unicode_escape_strings() {
+        let sql = r"SELECT U&'d\0061ta', u&'d!0061ta' UESCAPE '!', U&'it''s', u & 1";
+        let tokens = tokenize(sql);
+        assert_eq!(tokens[2], Token::PrefixedString(r"U&'d\0061ta'"));
+        assert_eq!(tokens[5], Token::PrefixedString("u&'d!0061ta' UESCAPE '!'"));
+        assert_eq!(tokens[8], Token::PrefixedString("U&'it''s'"));
+        assert_eq!(tokens[11], Token::Identifier("u"));
+        assert!(diagnostics(sql, Dialect::Generic).is_empty());
+        assert_eq!(
+            diagnostics("SELECT U&'a", Dialect::Generic)[0].message,
+            "unclosed string literal"
+        );
+    }
+
+    #[test]
+    fn test_lex_backslash_escaped_strings() {
+        let sql = r"SELECT 'it\'s', 'a\\', 'x''y' FROM t";
+        for dialect in [Dialect::Mysql, Dialect::Bigquery, Dialect::Spark] {
+            let tokens = tokenize_with_dialect(sql, dialect);
+            assert_eq!(tokens[2], Token::StringLiteral(r"it\'s"));
+            assert_eq!(tokens[5], Token::StringLiteral(r"a\\"));
+            assert_eq!(tokens[8], Token::StringLiteral("x''y"));
+            assert!(diagnostics(sql, dialect).is_empty());
+        }
+        assert_eq!(
+            diagnostics(r"SELECT 'a\'", Dialect::Mysql)[0].message,
+            "unclosed string literal"
+        );
+        // Standard SQL strings end at the first lone quote
+        assert_eq!(tokenize(r"SELECT 'a\'")[2], Token::StringLiteral(r"a\"));
+    }
+
+    #[test]
+    fn test_lex_scientific_numbers() {
+        let numbers: Vec<Token> = tokenize("SELECT 1e10, 2.5E-3, .5e+2, 3ex, 4e- 1 FROM t")
+            .into_iter()
+            .filter(|t| matches!(t, Token::NumberLiteral(_)))
+            .collect();
+        assert_eq!(
+            numbers,
+            vec![
+                Token::NumberLiteral("1e10"),
+                Token::NumberLiteral("2.5E-3"),
+                Token::NumberLiteral(".5e+2"),
+                Token::NumberLiteral("3"),
+                Token::NumberLiteral("4"),
+                Token::NumberLiteral("1")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_hex_and_bit_literals() {
+        let sql = "SELECT 0x1F, 0XaB, 0b0101, 0xg, x'1F', X'', b'0101', B'1' FROM t";
+        let literals: Vec<Token> = tokenize(sql)
+            .into_iter()
+            .filter(|t| matches!(t, Token::NumberLiteral(_) | Token::PrefixedString(_)))
+            .collect();
+        assert_eq!(
+            literals,
+            vec![
+                Token::NumberLiteral("0x1F"),
+                Token::NumberLiteral("0XaB"),
+                Token::NumberLiteral("0b0101"),
+                Token::NumberLiteral("0"),
+                Token::PrefixedString("x'1F'"),
+                Token::PrefixedString("X''"),
+                Token::PrefixedString("b'0101'"),
+                Token::PrefixedString("B'1'")
+            ]
+        );
+        assert!(diagnostics(sql, Dialect::Generic).is_empty());
+        assert_eq!(diagnostics("SELECT x'1F", Dialect::Generic).len(), 1);
+    }
+
+    #[test]
+    fn test_lex_oracle_division_is_operator() {
+        let tokens = tokenize_with_dialect("SELECT a / b FROM t", Dialect::Oracle);
+        assert_eq!(tokens[4], Token::Operator("/"));
+    }
+
+    #[test]
+    fn test_lex_spark_clauses() {
+        let tokens = tokenize_with_dialect(
+            "SELECT `my col` FROM t LATERAL VIEW explode(x) e DISTRIBUTE BY a CLUSTER  BY b SORT BY c",
+            Dialect::Spark,
+        );
+        let keywords: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Keyword(kw) => Some(*kw),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tokens[2], Token::BacktickIdentifier("my col"));
+        assert_eq!(
+            keywords,
+            vec![
+                KeywordKind::Select,
+                KeywordKind::From,
+                KeywordKind::LateralView,
+                KeywordKind::DistributeBy,
+                KeywordKind::ClusterBy,
+                KeywordKind::SortBy,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_sort_without_by_is_identifier() {
+        let tokens = tokenize("SELECT sort, cluster FROM t");
+        assert_eq!(tokens[2], Token::Identifier("sort"));
+        assert_eq!(tokens[5], Token::Identifier("cluster"));
+    }
+
+    #[test]
+    fn test_split_statements() {
+        let sql = "select ';' from a; -- c;\nselect 2 /* ; */ ;\n\n  ;select 3";
+        assert_eq!(
+            split_statements(sql, Dialect::Generic),
+            vec![
+                "select ';' from a;",
+                "-- c;\nselect 2 /* ; */ ;",
+                "select 3"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_statement_ranges() {
+        let sql = "  select 1;\n\nselect 2";
+        assert_eq!(statement_ranges(sql, Dialect::Generic), vec![2..11, 13..21]);
+    }
+
+    #[test]
+    fn test_split_statements_batch_separator() {
+        let sql = "SELECT 1\nGO\nSELECT 2\nGO\n";
+        assert_eq!(
+            split_statements(sql, Dialect::Tsql),
+            vec!["SELECT 1", "SELECT 2"]
+        );
+    }
 }
@@ -1,14 +1,629 @@
+//! A token-stream SQL formatter. [`format_sql`] is the entry point; the
+//! rest of the crate root offers variations on it (by statement, into a
+//! buffer or writer, with diagnostics or bind values).
+//!
+//! The building blocks are public for tools that need less than formatting
+//! or more control over it:
+//!
+//! - [`lexer`] turns SQL into [`Token`]s defined in [`token`], for
+//!   highlighters, linters and editor plugins
+//! - [`config`] holds [`FormatOptions`] and the enums it is built from
+//! - [`formatter`] runs the styles over a token stream and hosts the
+//!   [`SqlFormatter`](formatter::SqlFormatter) trait for styles of your own
+//! - [`highlight`] classifies tokens for syntax highlighting and renders
+//!   them as HTML or ANSI colors
+//! - [`reader`] reads SQL from an `io::BufRead` a run of statements at a
+//!   time, for inputs too large to hold in memory
+//!
+//! ```
+//! use rs_sql_indent::{FormatOptions, Token, format_sql, tokenize};
+//!
+//! let sql = format_sql("select a from t", &FormatOptions::default());
+//! assert_eq!(sql, "SELECT\n    a\nFROM\n    t");
+//!
+//! let names = tokenize("select a from t")
+//!     .into_iter()
+//!     .filter(|t| matches!(t, Token::Identifier(_)))
+//!     .count();
+//! assert_eq!(names, 2);
+//! ```
+
+use std::io;
+use std::ops::Range;
+
+pub mod analyze;
+pub mod compare;
 pub mod config;
 pub mod formatter;
+pub mod highlight;
 pub mod lexer;
+pub mod params;
+pub mod reader;
 pub mod token;
 
-#[cfg(target_arch = "wasm32")]
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "wasm")]
 pub mod wasm;
 
-pub use config::{FormatOptions, FormatStyle};
+pub use analyze::{StatementInfo, StatementKind, analyze};
+pub use compare::semantic_equal;
+pub use config::{Dialect, FormatOptions, FormatOptionsBuilder, FormatStyle, PlaceholderStyle};
+pub use highlight::Theme;
+pub use lexer::{split_statements, tokenize, tokenize_spanned, tokenize_with_dialect};
+pub use params::{ParamValue, QueryParams};
+pub use token::{KeywordKind, Span, SpannedToken, Token};
 
+/// Format `input` in the style and dialect of `options`. Formatting never
+/// fails: input the lexer does not understand is kept as written, see
+/// [`format_with_diagnostics`] to find out where.
 pub fn format_sql(input: &str, options: &FormatOptions) -> String {
-    let tokens = lexer::tokenize(input);
+    let tokens = lexer::tokenize_with_dialect(input, options.dialect);
+    formatter::format_tokens(&tokens, options)
+}
+
+/// Format `input` like [`format_sql`] and render it as highlighted HTML,
+/// a `<pre class="sql"><code>` block with `<span class="kw">` and the other
+/// [`TokenClass`](highlight::TokenClass) classes for a stylesheet to color.
+pub fn format_sql_html(input: &str, options: &FormatOptions) -> String {
+    highlight::html_block(&format_sql(input, options), options.dialect)
+}
+
+/// Format `input` like [`format_sql`] and highlight it with the ANSI
+/// escapes of `theme`, for terminal output.
+pub fn format_sql_colored(input: &str, options: &FormatOptions, theme: &Theme) -> String {
+    highlight::to_ansi(&format_sql(input, options), options.dialect, theme)
+}
+
+/// Format `input` like [`format_sql`], replacing the placeholders `params`
+/// has values for with those values as SQL literals, e.g. to print a logged
+/// query as it ran.
+pub fn format_with_params(input: &str, options: &FormatOptions, params: &QueryParams) -> String {
+    let literals = params::literals(params);
+    let tokens = lexer::tokenize_with_dialect(input, options.dialect);
+    let tokens = params::substitute(&tokens, params, &literals);
     formatter::format_tokens(&tokens, options)
 }
+
+/// Format `input` like [`format_sql`] into `output`, replacing its contents.
+/// Reusing one buffer across calls saves an allocation per query when
+/// formatting many of them.
+pub fn format_into(input: &str, options: &FormatOptions, output: &mut String) {
+    let tokens = lexer::tokenize_with_dialect(input, options.dialect);
+    formatter::format_tokens_into(&tokens, options, output);
+}
+
+/// One statement of the input, formatted on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormattedStatement {
+    pub text: String,
+    /// Where the statement is in the input, terminating semicolon included
+    pub range: Range<usize>,
+}
+
+/// Format each statement of `input` separately, in order, as the iterator
+/// is advanced. Statements are split as by [`split_statements`].
+pub fn format_statements<'a>(
+    input: &'a str,
+    options: &'a FormatOptions,
+) -> impl Iterator<Item = FormattedStatement> + 'a {
+    lexer::statement_ranges(input, options.dialect)
+        .into_iter()
+        .map(move |range| FormattedStatement {
+            text: format_sql(&input[range.clone()], options),
+            range,
+        })
+}
+
+/// Format only the statements of `input` overlapping the byte `range`, as
+/// an editor's "format selection" does, and return the whole input with
+/// everything else left as it was. An empty range selects the statement it
+/// is in or touches, like a cursor.
+pub fn format_range(input: &str, range: Range<usize>, options: &FormatOptions) -> String {
+    format_ranges(input, &[range], options)
+}
+
+/// Format only the statements of `input` overlapping any of `ranges`, like
+/// [`format_range`] for several selections, such as the lines changed in a
+/// commit. Adjacent selected statements are formatted together; the text
+/// between unselected ones is left as it was.
+pub fn format_ranges(input: &str, ranges: &[Range<usize>], options: &FormatOptions) -> String {
+    let statements = lexer::statement_ranges(input, options.dialect);
+    let selected = |s: &Range<usize>| {
+        ranges
+            .iter()
+            .any(|range| s.start <= range.end && range.start <= s.end)
+    };
+    let mut output = String::with_capacity(input.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < statements.len() {
+        if !selected(&statements[i]) {
+            i += 1;
+            continue;
+        }
+        let start = statements[i].start;
+        while i + 1 < statements.len() && selected(&statements[i + 1]) {
+            i += 1;
+        }
+        let end = statements[i].end;
+        output.push_str(&input[copied..start]);
+        output.push_str(&format_sql(&input[start..end], options));
+        copied = end;
+        i += 1;
+    }
+    output.push_str(&input[copied..]);
+    output
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The output likely does not mean what the author intended, e.g. after
+    /// an unclosed string swallowed the rest of the input
+    Error,
+    /// The input was not fully understood but is kept as written
+    Warning,
+}
+
+/// A problem found in the input while formatting it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatDiagnostic {
+    pub severity: Severity,
+    pub message: &'static str,
+    /// The token the problem was found at
+    pub span: Span,
+}
+
+/// Formatted SQL together with the problems found in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Formatted {
+    pub text: String,
+    /// In the order they appear in the input
+    pub diagnostics: Vec<FormatDiagnostic>,
+}
+
+/// Format `input` like [`format_sql`], also reporting unclosed literals and
+/// comments and unbalanced parentheses as errors, and characters the lexer
+/// passed through without understanding them as warnings.
+pub fn format_with_diagnostics(input: &str, options: &FormatOptions) -> Formatted {
+    let tokens = lexer::tokenize_spanned(input, options.dialect);
+    let diagnostics = problems(input, options, &tokens);
+    let tokens: Vec<Token<'_>> = tokens.into_iter().map(|t| t.token).collect();
+    Formatted {
+        text: formatter::format_tokens(&tokens, options),
+        diagnostics,
+    }
+}
+
+/// Format `input` like [`format_with_diagnostics`], except that statements
+/// with an unclosed literal or comment or unbalanced parentheses are kept as
+/// written instead of formatted, so a broken fragment does not scramble the
+/// indentation of what follows. Each kept region gets a warning spanning it.
+/// An unclosed string or comment runs to the end of the input, which is
+/// then kept as written from the statement it starts in.
+pub fn format_tolerant(input: &str, options: &FormatOptions) -> Formatted {
+    let tokens = lexer::tokenize_spanned(input, options.dialect);
+    let mut diagnostics = problems(input, options, &tokens);
+    let mut pieces = Vec::new();
+    for range in statement_runs(input, options) {
+        let run = &input[range.clone()];
+        if lexer::diagnostics(run, options.dialect).is_empty() {
+            let formatted = format_sql(run, options);
+            if !formatted.is_empty() {
+                pieces.push(formatted);
+            }
+            continue;
+        }
+        let kept = run.trim();
+        let start = range.start + (run.len() - run.trim_start().len());
+        pieces.push(kept.to_string());
+        diagnostics.push(FormatDiagnostic {
+            severity: Severity::Warning,
+            message: "malformed statement kept as written",
+            span: span_of(input, start..start + kept.len()),
+        });
+    }
+    diagnostics.sort_by_key(|d| d.span.start);
+    Formatted {
+        text: pieces.join("\n\n"),
+        diagnostics,
+    }
+}
+
+/// Lexer diagnostics as errors and unknown characters as warnings, in the
+/// order they appear in the input.
+fn problems(
+    input: &str,
+    options: &FormatOptions,
+    tokens: &[SpannedToken<'_>],
+) -> Vec<FormatDiagnostic> {
+    let span_at = |offset| {
+        tokens
+            .binary_search_by_key(&offset, |t| t.span.start)
+            .ok()
+            .map(|i| tokens[i].span)
+    };
+    let mut diagnostics: Vec<FormatDiagnostic> = lexer::diagnostics(input, options.dialect)
+        .into_iter()
+        .filter_map(|d| {
+            Some(FormatDiagnostic {
+                severity: Severity::Error,
+                message: d.message,
+                span: span_at(d.offset)?,
+            })
+        })
+        .collect();
+    diagnostics.extend(
+        tokens
+            .iter()
+            .filter(|t| lexer::is_unknown_character(&t.token))
+            .map(|t| FormatDiagnostic {
+                severity: Severity::Warning,
+                message: "unknown character, kept as written",
+                span: t.span,
+            }),
+    );
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
+
+/// The span of `range` in `input`, with the line and column of its start.
+fn span_of(input: &str, range: Range<usize>) -> Span {
+    let before = &input[..range.start];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Span {
+        start: range.start,
+        end: range.end,
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+/// Format `input` like [`format_sql`], writing the result to `writer` a run
+/// of statements at a time rather than building it up in one `String`, so
+/// besides `input` itself only the run being formatted is held: a statement,
+/// or a procedural or template block with the statements inside it.
+pub fn format_to<W: io::Write>(
+    input: &str,
+    options: &FormatOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    for (i, run) in formatted_runs(input, options).enumerate() {
+        if i > 0 {
+            writer.write_all(b"\n\n")?;
+        }
+        writer.write_all(run.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Format SQL read from `reader` like [`format_to`], holding only the
+/// statement being read in memory rather than the whole input. The input
+/// must be UTF-8.
+pub fn format_reader<R: io::BufRead, W: io::Write>(
+    reader: R,
+    options: &FormatOptions,
+    mut writer: W,
+) -> io::Result<()> {
+    let mut written = false;
+    for chunk in reader::StatementReader::new(reader, options.dialect) {
+        let formatted = format_sql(&chunk?.text, options);
+        // Trailing whitespace and comments-only runs can format to nothing
+        if formatted.is_empty() {
+            continue;
+        }
+        if written {
+            writer.write_all(b"\n\n")?;
+        }
+        writer.write_all(formatted.as_bytes())?;
+        written = true;
+    }
+    Ok(())
+}
+
+/// Whether `input` is already formatted, i.e. equal to [`format_sql`]'s
+/// output with or without a trailing newline. Statements are formatted and
+/// compared one run at a time, so the check stops at the first difference
+/// without formatting the rest.
+pub fn is_formatted(input: &str, options: &FormatOptions) -> bool {
+    let mut rest = input;
+    for (i, run) in formatted_runs(input, options).enumerate() {
+        if i > 0 {
+            let Some(after) = rest.strip_prefix("\n\n") else {
+                return false;
+            };
+            rest = after;
+        }
+        let Some(after) = rest.strip_prefix(run.as_str()) else {
+            return false;
+        };
+        rest = after;
+    }
+    rest.is_empty() || rest == "\n"
+}
+
+/// The output of [`format_sql`] in pieces, each a run of statements that
+/// formats the same on its own, to be joined by blank lines.
+fn formatted_runs<'a>(
+    input: &'a str,
+    options: &'a FormatOptions,
+) -> impl Iterator<Item = String> + 'a {
+    statement_runs(input, options).filter_map(move |range| {
+        let formatted = format_sql(&input[range], options);
+        // Trailing whitespace and comments-only runs can format to nothing
+        (!formatted.is_empty()).then_some(formatted)
+    })
+}
+
+/// Byte ranges of the runs of statements [`formatted_runs`] formats, which
+/// cover the whole input. A run that opens a block extends to the statement
+/// end that closes it, as in [`reader::StatementReader`].
+fn statement_runs<'a>(
+    input: &'a str,
+    options: &'a FormatOptions,
+) -> impl Iterator<Item = Range<usize>> + 'a {
+    let mut start = 0;
+    let mut recheck_len = 0;
+    lexer::statement_ends(input, options.dialect)
+        .chain([input.len()])
+        .filter_map(move |end| {
+            let last = end == input.len();
+            if end <= start || (!last && end - start < recheck_len) {
+                return None;
+            }
+            let tokens = lexer::tokenize_with_dialect(&input[start..end], options.dialect);
+            if !last && formatter::needs_lookahead(&tokens, options.dialect) {
+                // Waiting for the run to double keeps a block that never
+                // closes from being lexed again at every statement end
+                recheck_len = (end - start) * 2;
+                return None;
+            }
+            recheck_len = 0;
+            let run = start..end;
+            start = end;
+            Some(run)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_from_crate_root() {
+        let tokens: Vec<Token> = tokenize_with_dialect("select `a`", Dialect::Bigquery)
+            .into_iter()
+            .filter(|t| !matches!(t, Token::Whitespace(_)))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Keyword(KeywordKind::Select),
+                Token::BacktickIdentifier("a"),
+            ]
+        );
+        assert_eq!(tokenize("select").len(), 1);
+    }
+
+    #[test]
+    fn test_split_statements_ignores_quoted_semicolons() {
+        let sql = "select 'it''s;' as \"a;b\"; /* ; */ select $$ x; $$; -- ;\n";
+        assert_eq!(
+            split_statements(sql, Dialect::Snowflake),
+            vec![
+                "select 'it''s;' as \"a;b\";",
+                "/* ; */ select $$ x; $$;",
+                "-- ;"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_to_and_format_reader_match_format_sql() {
+        for input in [
+            "select a from t; select 'x;\ny' from u; -- note\nselect 3",
+            "select 1;\nbegin\n  let x := 1;\n  return x;\nend;\nselect 2;\n",
+            "{% if x %}\nselect 1;\n{% endif %}\nselect 2;\n-- end",
+            "",
+        ] {
+            let options = FormatOptions {
+                dialect: Dialect::Snowflake,
+                ..FormatOptions::default()
+            };
+            let mut output = Vec::new();
+            format_to(input, &options, &mut output).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format_sql(input, &options)
+            );
+            let mut output = Vec::new();
+            format_reader(input.as_bytes(), &options, &mut output).unwrap();
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format_sql(input, &options)
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_into_reuses_buffer() {
+        let options = FormatOptions::default();
+        let mut output = String::from("stale");
+        format_into("select a, b from t", &options, &mut output);
+        assert_eq!(output, format_sql("select a, b from t", &options));
+        let buffer = output.as_ptr();
+        format_into("select a, b from t", &options, &mut output);
+        assert_eq!(output.as_ptr(), buffer);
+        format_into("", &options, &mut output);
+        assert_eq!(output, "");
+    }
+
+    #[test]
+    fn test_statement_runs_end_with_their_block() {
+        let options = FormatOptions {
+            dialect: Dialect::Snowflake,
+            ..FormatOptions::default()
+        };
+        let input = "select 1;\nbegin\n  let x := 1;\n  return x;\nend;\nselect 2;\n";
+        let runs: Vec<&str> = statement_runs(input, &options)
+            .map(|range| &input[range])
+            .collect();
+        assert_eq!(
+            runs,
+            vec![
+                "select 1;",
+                "\nbegin\n  let x := 1;\n  return x;\nend;",
+                "\nselect 2;",
+                "\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_formatted() {
+        let options = FormatOptions::default();
+        let formatted = format_sql("select a from t; select b from u", &options);
+        assert!(is_formatted(&formatted, &options));
+        assert!(is_formatted(&format!("{}\n", formatted), &options));
+        assert!(!is_formatted(&format!("{}\n\n", formatted), &options));
+        assert!(!is_formatted("select a from t", &options));
+        assert!(!is_formatted(&formatted.replace("    b", "  b"), &options));
+        assert!(is_formatted("", &options));
+    }
+
+    #[test]
+    fn test_format_with_diagnostics() {
+        let options = FormatOptions::default();
+        let input = "select a ¤ b,\n  f(c from t";
+        let formatted = format_with_diagnostics(input, &options);
+        assert_eq!(formatted.text, format_sql(input, &options));
+        assert_eq!(
+            formatted.diagnostics,
+            vec![
+                FormatDiagnostic {
+                    severity: Severity::Warning,
+                    message: "unknown character, kept as written",
+                    span: Span {
+                        start: 9,
+                        end: 11,
+                        line: 1,
+                        column: 10
+                    }
+                },
+                FormatDiagnostic {
+                    severity: Severity::Error,
+                    message: "unclosed parenthesis",
+                    span: Span {
+                        start: 18,
+                        end: 19,
+                        line: 2,
+                        column: 4
+                    }
+                },
+            ]
+        );
+        assert!(
+            format_with_diagnostics("select 1", &options)
+                .diagnostics
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_format_range() {
+        let options = FormatOptions::default();
+        let input = "select  a from t;\nselect b from u;\n\nselect c from v;\n";
+        assert_eq!(
+            format_range(input, 20..22, &options),
+            "select  a from t;\nSELECT\n    b\nFROM\n    u;\n\nselect c from v;\n"
+        );
+        assert_eq!(
+            format_range(input, 10..40, &options),
+            format!("{}\n", format_sql(input, &options))
+        );
+        assert_eq!(
+            format_range(input, 17..17, &options),
+            format!(
+                "{}\nselect b from u;\n\nselect c from v;\n",
+                format_sql("select  a from t;", &options)
+            )
+        );
+        assert_eq!(format_range("  ", 0..1, &options), "  ");
+    }
+
+    #[test]
+    fn test_format_ranges() {
+        let options = FormatOptions::default();
+        let input = "select a;
+select b;
+select c;
+select d;
+";
+        assert_eq!(
+            format_ranges(input, &[0..3, 30..30, 22..25], &options),
+            "SELECT\n    a;\nselect b;\nSELECT\n    c;\n\nSELECT\n    d;\n"
+        );
+        assert_eq!(format_ranges(input, &[], &options), input);
+    }
+
+    #[test]
+    fn test_format_tolerant() {
+        let options = FormatOptions::default();
+        let input = "select a from t;\nselect f(b from u;\nselect c from v;";
+        let formatted = format_tolerant(input, &options);
+        assert_eq!(
+            formatted.text,
+            "SELECT\n    a\nFROM\n    t;\n\nselect f(b from u;\n\nSELECT\n    c\nFROM\n    v;"
+        );
+        assert_eq!(
+            formatted.diagnostics,
+            vec![
+                FormatDiagnostic {
+                    severity: Severity::Warning,
+                    message: "malformed statement kept as written",
+                    span: Span {
+                        start: 17,
+                        end: 35,
+                        line: 2,
+                        column: 1
+                    }
+                },
+                FormatDiagnostic {
+                    severity: Severity::Error,
+                    message: "unclosed parenthesis",
+                    span: Span {
+                        start: 25,
+                        end: 26,
+                        line: 2,
+                        column: 9
+                    }
+                },
+            ]
+        );
+        let clean = "select 1; select 2";
+        assert_eq!(
+            format_tolerant(clean, &options),
+            format_with_diagnostics(clean, &options)
+        );
+    }
+
+    #[test]
+    fn test_format_statements() {
+        let input = "select a from t; -- next\ninsert into t values (1);";
+        let options = FormatOptions::default();
+        let statements: Vec<FormattedStatement> = format_statements(input, &options).collect();
+        assert_eq!(
+            statements,
+            vec![
+                FormattedStatement {
+                    text: "SELECT\n    a\nFROM\n    t;".to_string(),
+                    range: 0..16,
+                },
+                FormattedStatement {
+                    text: format_sql("-- next\ninsert into t values (1);", &options),
+                    range: 17..input.len(),
+                },
+            ]
+        );
+    }
+}
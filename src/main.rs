@@ -1,42 +1,1269 @@
-use std::io::{self, Read};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::thread;
+use std::time::Duration;
 
-use clap::Parser;
-use rs_sql_indent::{FormatOptions, FormatStyle, format_sql};
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use rs_sql_indent::config::{PRESETS, Preset};
+use rs_sql_indent::highlight::Theme;
+use rs_sql_indent::{
+    Dialect, FormatOptions, FormatStyle, PlaceholderStyle, Severity, format_range, format_ranges,
+    format_sql, format_tolerant, formatter, highlight, lexer,
+};
+
+mod cli;
+
+use cli::changed;
+use cli::color::{ColorChoice, OutputFormat};
+use cli::config_file;
+use cli::config_search;
+use cli::daemon;
+use cli::dbt;
+use cli::diagnostic;
+use cli::document;
+use cli::embedded::{self, Host};
+use cli::encoding::{Bom, Encoding, FileFormat, LineEndings};
+use cli::env;
+use cli::fix;
+use cli::glob::Pattern;
+use cli::progress::Progress;
+use cli::report::{self, FileReport, Reporter};
+use cli::show_config::{self, ConfigFormat};
+use cli::sqlfluff;
+use cli::staged::{self, StagedFile};
+use cli::stream;
+use cli::verify;
+use cli::walk;
+use cli::watch::Watcher;
+
+const WATCH_INTERVAL: Duration = Duration::from_millis(300);
 
 #[derive(Parser)]
 #[command(version, about)]
 struct Cli {
-    /// Output keywords in lowercase
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// SQL files or directories to format. Reads stdin when none are given
+    files: Vec<PathBuf>,
+
+    /// Print a unified diff of the changes instead of the formatted SQL
+    #[arg(long)]
+    diff: bool,
+
+    /// Write the formatted SQL (or diff) for a single input to FILE instead
+    /// of stdout. The input is read in full first, so FILE may be the input
+    #[arg(short, long, value_name = "FILE", conflicts_with_all = ["write", "watch"])]
+    output: Option<PathBuf>,
+
+    /// Also apply safe rewrites: `!=` becomes `<>`, empty statements are
+    /// dropped and the last statement gets a semicolon
+    #[arg(long)]
+    fix: bool,
+
+    /// Check that the output lexes to the same tokens as the input, ignoring
+    /// whitespace and keyword case. Inputs that fail are left untouched
+    #[arg(long)]
+    verify: bool,
+
+    /// Keep the statements of malformed input with problems as written
+    /// instead of formatting them. The problems are still reported
+    #[arg(long, conflicts_with = "fix")]
+    tolerant: bool,
+
+    /// Refuse to format input with an unclosed literal or comment or with
+    /// unbalanced parentheses, instead of warning about it
+    #[arg(long, conflicts_with = "tolerant")]
+    strict: bool,
+
+    /// Print only the paths of inputs whose formatting would change, one per
+    /// line, and exit with status 1 if there are any
+    #[arg(
+        short,
+        long,
+        conflicts_with_all = ["diff", "write", "output", "watch", "compare_styles"]
+    )]
+    list_different: bool,
+
+    /// Format and print only the Nth statement of the input, counting from 1
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with_all = ["diff", "write", "staged", "watch", "list_different", "embedded"]
+    )]
+    statement_index: Option<u32>,
+
+    /// Format only the statements overlapping the byte range START:END of
+    /// the input and keep the rest as it is, e.g. an editor selection
+    #[arg(
+        long,
+        value_name = "START:END",
+        value_parser = parse_range,
+        conflicts_with_all = ["statement_index", "fix", "staged", "watch", "embedded"]
+    )]
+    range: Option<Range<usize>>,
+
+    /// Format only the statements touching lines changed since this git
+    /// revision, leaving the rest of each file as it is. Files added since
+    /// then are formatted whole
+    #[arg(
+        long,
+        value_name = "REF",
+        conflicts_with_all = [
+            "range", "statement_index", "fix", "staged", "watch", "embedded", "dbt", "daemon"
+        ]
+    )]
+    changed_since: Option<String>,
+
+    /// Rewrite the files in place instead of printing them
+    #[arg(short, long, conflicts_with = "diff")]
+    write: bool,
+
+    /// With --write, copy each file to FILE.SUFFIX (`.bak` by default)
+    /// before overwriting it. Unchanged files get no backup
+    #[arg(
+        long,
+        value_name = "SUFFIX",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".bak",
+        requires = "write"
+    )]
+    backup: Option<String>,
+
+    /// Format the files staged in git, rewriting the staged copies (and the
+    /// working tree copies without unstaged changes). For pre-commit hooks
+    #[arg(
+        long,
+        conflicts_with_all = ["files", "files_from", "write", "watch", "output", "compare_styles"]
+    )]
+    staged: bool,
+
+    /// Skip files and directories matching this glob while searching
+    /// directories (e.g. 'vendor/**', '*.gen.sql'). May be repeated
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Also format the paths listed in FILE, one per line (`-` reads the
+    /// list from stdin)
+    #[arg(long, value_name = "FILE")]
+    files_from: Option<PathBuf>,
+
+    /// Treat the inputs as source files of this language and format only
+    /// the SQL in their multi-line string literals
+    #[arg(long, value_name = "LANG")]
+    embedded: Option<Host>,
+
+    /// With --embedded yaml or json, format the values of keys matching this
+    /// glob. May be repeated [default: rawSql, query, sql]
+    #[arg(long, value_name = "GLOB", requires = "embedded")]
+    sql_key: Vec<String>,
+
+    /// Treat directories holding a dbt_project.yml as dbt projects, formatting
+    /// the SQL under their model, analysis, snapshot and test paths. Each
+    /// model's Jinja header (`{{ config(...) }}` and the like) is kept as
+    /// written
+    #[arg(long, conflicts_with_all = ["embedded", "staged"])]
+    dbt: bool,
+
+    /// Keep running and reformat the files whenever they are saved
+    #[arg(long, conflicts_with_all = ["diff", "write"])]
+    watch: bool,
+
+    /// Keep running and answer JSON formatting requests, one per line on
+    /// stdin, with one JSON reply per line on stdout. For editor plugins
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "files", "files_from", "diff", "write", "watch", "staged", "output",
+            "list_different", "compare_styles"
+        ]
+    )]
+    daemon: bool,
+
+    /// How results are reported: the formatted SQL, or a JSON summary of
+    /// every input (path, changed, byte counts, warnings)
+    #[arg(long, value_enum, default_value_t = Reporter::Text)]
+    reporter: Reporter,
+
+    /// Don't print the "N files checked, M reformatted" line after
+    /// formatting several files, or any file with --write or --staged
+    #[arg(long)]
+    no_summary: bool,
+
+    /// Stop at the first input that cannot be read, formatted or written,
+    /// instead of carrying on and listing every failure at the end
+    #[arg(long)]
+    fail_fast: bool,
+
+    /// Print whether each file was reformatted or unchanged to stderr as it
+    /// is processed
+    #[arg(short, long, conflicts_with = "quiet")]
+    verbose: bool,
+
+    /// Print only errors to stderr: no summary, progress or per-file
+    /// messages
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Show a progress bar on stderr while formatting files
     #[arg(long)]
+    progress: bool,
+
+    /// Character encoding of the inputs. Files rewritten in place or
+    /// written with --output keep it; output printed to stdout is UTF-8
+    #[arg(long, value_enum, default_value_t = Encoding::Auto, global = true)]
+    encoding: Encoding,
+
+    /// Line breaks of the formatted SQL: `auto` keeps those of each input
+    #[arg(long, value_enum, default_value_t = LineEndings::Auto, global = true)]
+    line_endings: LineEndings,
+
+    /// Byte order mark of rewritten files: `auto` keeps each file's own
+    #[arg(long, value_enum, default_value_t = Bom::Auto, global = true)]
+    bom: Bom,
+
+    /// Print the formatted SQL as is, or as highlighted HTML for embedding
+    /// in web pages
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Sql,
+        conflicts_with_all = ["diff", "write", "staged", "list_different", "embedded", "daemon"]
+    )]
+    format: OutputFormat,
+
+    /// Highlight the formatted SQL with ANSI colors
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto, global = true)]
+    color: ColorChoice,
+
+    /// Format stdin with every style, one after another under a header
+    #[arg(long, conflicts_with_all = ["files", "style", "diff", "write", "watch", "output"])]
+    compare_styles: bool,
+
+    /// Print the effective options after merging config files and flags,
+    /// then exit
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "toml"
+    )]
+    show_config: Option<ConfigFormat>,
+
+    /// Start from a named bundle of style, keyword case and indent width:
+    /// pgformatter, sqlfluff or compact. Other options override it
+    #[arg(long, value_name = "NAME", value_parser = preset_names(), global = true)]
+    preset: Option<String>,
+
+    /// Output keywords in lowercase
+    #[arg(long, global = true)]
     lowercase: bool,
 
-    /// Formatting style
-    #[arg(long, value_enum, default_value_t = FormatStyle::Basic)]
-    style: FormatStyle,
+    /// Formatting style [default: basic]
+    #[arg(long, value_enum, global = true)]
+    style: Option<FormatStyle>,
+
+    /// SQL dialect, enabling its quoting rules, variables and scripting
+    /// blocks [default: generic]
+    #[arg(long, value_enum, global = true)]
+    dialect: Option<Dialect>,
+
+    /// Rewrite bind placeholders as `?`, `$1` or `:name`
+    #[arg(long, value_enum, value_name = "STYLE", global = true)]
+    placeholders: Option<PlaceholderStyle>,
+
+    /// Read dialect, keyword case, indent width and comma position from a
+    /// sqlfluff config file. Flags given on the command line take precedence.
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ".sqlfluff",
+        global = true
+    )]
+    sqlfluff: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Split a multi-statement script into numbered per-statement files
+    Split(cli::split::SplitArgs),
+    /// Collapse SQL onto a single line with minimal whitespace, dropping
+    /// comments
+    Minify {
+        /// SQL file to minify. Reads stdin when omitted
+        file: Option<PathBuf>,
+    },
+    /// Report statement counts and kinds, tables and nesting depth per file
+    Stats(cli::stats::StatsArgs),
+    /// Print the formatted SQL with the clause and indentation depth the
+    /// formatter was in when it wrote each line
+    ExplainTokens {
+        /// SQL file to explain. Reads stdin when omitted
+        file: Option<PathBuf>,
+    },
+    /// List constructs the formatter does not properly understand, with
+    /// their locations
+    Doctor(cli::doctor::DoctorArgs),
+    /// Print the lexer's tokens (kind, text, position) as JSON lines
+    Tokenize(cli::tokenize::TokenizeArgs),
+    /// Print a shell completion script, e.g. `rs-sql-indent completions bash`
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
 fn main() {
     let cli = Cli::parse();
+    let mut resolver = OptionsResolver::new(&cli);
+    let options = resolver.current_dir();
+    if let Some(format) = cli.show_config {
+        print!("{}", show_config::render(&options, format));
+        return;
+    }
+    if cli.daemon {
+        if let Err(e) = daemon::run(io::stdin().lock(), io::stdout().lock(), &options) {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    if cli.compare_styles {
+        compare_styles(&read_stdin(&cli).0, options);
+        return;
+    }
+
+    let paths = input_paths(&cli);
+
+    match &cli.command {
+        Some(Command::Split(args)) => {
+            let input = match &args.file {
+                Some(path) => read_source(&cli, path).0,
+                None => read_stdin(&cli).0,
+            };
+            match cli::split::run(args, &input, &options) {
+                Ok(paths) => {
+                    for path in paths {
+                        println!("{}", path.display());
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Error writing statements: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Some(Command::Minify { file }) => {
+            let input = match file {
+                Some(path) => read_source(&cli, path).0,
+                None => read_stdin(&cli).0,
+            };
+            let tokens = lexer::tokenize_with_dialect(&input, options.dialect);
+            println!("{}", formatter::minify_tokens(&tokens, &options));
+        }
+        Some(Command::ExplainTokens { file }) => {
+            let input = match file {
+                Some(path) => read_source(&cli, path).0,
+                None => read_stdin(&cli).0,
+            };
+            let tokens = lexer::tokenize_with_dialect(&input, options.dialect);
+            for line in formatter::explain_tokens(&tokens, &options) {
+                println!("{:<8} {:>2} | {}", line.clause, line.depth, line.text);
+            }
+        }
+        Some(Command::Doctor(args)) if args.paths.is_empty() => {
+            let input = read_stdin(&cli).0;
+            let findings = cli::doctor::scan(&input, options.dialect);
+            print!("{}", cli::doctor::render("<stdin>", &input, &findings));
+        }
+        Some(Command::Doctor(args)) => {
+            let files = walk::collect_files(&args.paths, &[], &["sql"]).unwrap_or_else(|e| {
+                eprintln!("Error searching for SQL files: {}", e);
+                process::exit(1);
+            });
+            for path in &files {
+                let input = read_source(&cli, path).0;
+                let findings = cli::doctor::scan(&input, options.dialect);
+                print!(
+                    "{}",
+                    cli::doctor::render(&path.display().to_string(), &input, &findings)
+                );
+            }
+        }
+        Some(Command::Stats(args)) if args.paths.is_empty() => {
+            let stats = cli::stats::collect(&read_stdin(&cli).0, options.dialect);
+            print!("{}", cli::stats::render("<stdin>", &stats));
+        }
+        Some(Command::Stats(args)) => {
+            let files = walk::collect_files(&args.paths, &[], &["sql"]).unwrap_or_else(|e| {
+                eprintln!("Error searching for SQL files: {}", e);
+                process::exit(1);
+            });
+            for path in &files {
+                let stats = cli::stats::collect(&read_source(&cli, path).0, options.dialect);
+                print!(
+                    "{}",
+                    cli::stats::render(&path.display().to_string(), &stats)
+                );
+            }
+        }
+        Some(Command::Tokenize(args)) => {
+            let input = match &args.file {
+                Some(path) => read_source(&cli, path).0,
+                None => read_stdin(&cli).0,
+            };
+            print!("{}", cli::tokenize::run(args, &input, options.dialect));
+        }
+        Some(Command::Completions { shell }) => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(*shell, &mut command, name, &mut io::stdout());
+        }
+        None if cli.staged => {
+            let reports = format_staged(&cli, &mut resolver);
+            finish(&cli, &reports);
+        }
+        // An empty `--files-from` list means nothing to do, not "read stdin"
+        None if paths.is_empty() && cli.files_from.is_none() => {
+            if cli.write || cli.watch {
+                eprintln!("Error: --write and --watch need files or directories");
+                process::exit(1);
+            }
+            if cli.changed_since.is_some() {
+                eprintln!("Error: --changed-since needs files or directories");
+                process::exit(1);
+            }
+            if streams(&cli) {
+                stream_stdin(&cli, &options);
+                return;
+            }
+            let (input, format) = read_stdin(&cli);
+            let report = process(&cli, "<stdin>", Target::Stdin, &input, format, &options);
+            finish(&cli, std::slice::from_ref(&report));
+        }
+        None => {
+            let excludes: Vec<Pattern> = cli.exclude.iter().map(|p| Pattern::new(p)).collect();
+            if cli.watch {
+                watch(&cli, &paths, excludes, &mut resolver);
+            }
+            let files =
+                walk::collect_files(&paths, &excludes, extensions(&cli)).unwrap_or_else(|e| {
+                    eprintln!("Error searching for SQL files: {}", e);
+                    process::exit(1);
+                });
+            if cli.output.is_some() && files.len() > 1 {
+                eprintln!("Error: --output takes a single input, got {}", files.len());
+                process::exit(1);
+            }
+            let reports = batch(&cli, &files, |path| {
+                let name = path.display().to_string();
+                let options = match resolver.for_file(path) {
+                    Ok(options) => options,
+                    Err(e) => {
+                        eprintln!("Error in {}", e);
+                        return error_report(&name, 0, e);
+                    }
+                };
+                match load(&cli, path) {
+                    Ok((input, format)) => {
+                        process(&cli, &name, Target::File(path), &input, format, &options)
+                    }
+                    Err(e) => {
+                        eprintln!("Error reading {}: {}", name, e);
+                        error_report(&name, 0, e)
+                    }
+                }
+            });
+            finish(&cli, &reports);
+        }
+    }
+}
+
+/// Report the results of a run: the JSON document under `--reporter json`,
+/// otherwise a summary on stderr for batch runs. Exits with status 1 when
+/// any input failed, or under `--list-different` when any was listed.
+fn finish(cli: &Cli, reports: &[FileReport]) {
+    if cli.reporter == Reporter::Json {
+        println!("{}", report::to_json(reports));
+    } else if reports.len() > 1 && reports.iter().any(|r| r.error.is_some()) {
+        eprint!("{}", report::failures(reports));
+    }
+    if cli.reporter == Reporter::Text
+        && !cli.no_summary
+        && !cli.quiet
+        && !cli.list_different
+        && (reports.len() > 1 || cli.write || cli.staged)
+    {
+        eprintln!("{}", report::summary(reports));
+    }
+    let listed = cli.list_different && reports.iter().any(|r| r.changed);
+    if listed || reports.iter().any(|r| r.error.is_some()) {
+        process::exit(1);
+    }
+}
 
-    let uppercase = !cli.lowercase;
+fn preset_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(
+        PRESETS
+            .iter()
+            .map(|p| PossibleValue::new(p.name).help(p.description)),
+    )
+}
 
-    let options = FormatOptions {
-        uppercase,
-        style: cli.style,
+/// Parse `--range START:END` byte offsets.
+fn parse_range(value: &str) -> Result<Range<usize>, String> {
+    let (start, end) = value
+        .split_once(':')
+        .ok_or_else(|| "expected START:END".to_string())?;
+    let offset = |s: &str| {
+        s.parse::<usize>()
+            .map_err(|_| format!("`{}` is not a byte offset", s))
     };
+    let range = offset(start)?..offset(end)?;
+    if range.start > range.end {
+        return Err(format!("start {} is after end {}", range.start, range.end));
+    }
+    Ok(range)
+}
+
+/// Merges the format options from `RS_SQL_INDENT_*` environment variables,
+/// the `sql-indent.toml` files applying to an input's directory, the preset,
+/// the sqlfluff config file and command-line flags, later ones taking
+/// precedence. Each directory is resolved once.
+struct OptionsResolver<'a> {
+    cli: &'a Cli,
+    environment: FormatOptions,
+    by_dir: HashMap<PathBuf, Result<FormatOptions, String>>,
+}
+
+impl<'a> OptionsResolver<'a> {
+    fn new(cli: &'a Cli) -> Self {
+        let mut environment = FormatOptions::default();
+        if let Err(e) = env::apply(|name| std::env::var(name).ok(), &mut environment) {
+            eprintln!("Error in environment: {}", e);
+            process::exit(1);
+        }
+        Self {
+            cli,
+            environment,
+            by_dir: HashMap::new(),
+        }
+    }
+
+    /// The options for input read in the current directory, such as stdin.
+    /// Exits on a broken configuration file.
+    fn current_dir(&mut self) -> FormatOptions {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        self.for_dir(&cwd).unwrap_or_else(|e| {
+            eprintln!("Error in {}", e);
+            process::exit(1);
+        })
+    }
+
+    /// The options for the file at `path`, from the configuration files
+    /// found from its directory up.
+    fn for_file(&mut self, path: &Path) -> Result<FormatOptions, String> {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let dir = fs::canonicalize(dir)
+            .unwrap_or_else(|_| std::env::current_dir().unwrap_or_default().join(dir));
+        self.for_dir(&dir)
+    }
+
+    fn for_dir(&mut self, dir: &Path) -> Result<FormatOptions, String> {
+        if let Some(options) = self.by_dir.get(dir) {
+            return options.clone();
+        }
+        let mut options = self.environment;
+        let mut result = Ok(());
+        for path in config_search::config_files(dir) {
+            result = config_file::apply(&read_file(&path), &mut options)
+                .map_err(|e| format!("{}: {}", path.display(), e));
+            if result.is_err() {
+                break;
+            }
+        }
+        let options = result.map(|()| {
+            apply_flags(self.cli, &mut options);
+            options
+        });
+        self.by_dir.insert(dir.to_path_buf(), options.clone());
+        options
+    }
+}
+
+/// Apply the preset, the sqlfluff config file and the command-line flags,
+/// which override the environment and configuration files.
+fn apply_flags(cli: &Cli, options: &mut FormatOptions) {
+    if let Some(preset) = cli.preset.as_deref().and_then(Preset::find) {
+        preset.apply(options);
+    }
+
+    if let Some(path) = &cli.sqlfluff {
+        if let Err(e) = sqlfluff::apply(&read_file(path), options) {
+            eprintln!("Error in {}: {}", path.display(), e);
+            process::exit(1);
+        }
+    }
+
+    if cli.lowercase {
+        options.uppercase = false;
+    }
+    if let Some(style) = cli.style {
+        options.style = style;
+    }
+    if let Some(dialect) = cli.dialect {
+        options.dialect = dialect;
+    }
+    if cli.placeholders.is_some() {
+        options.placeholders = cli.placeholders;
+    }
+}
+
+/// Print `input` formatted with each style, headed by a `-- style: ...` line.
+fn compare_styles(input: &str, options: FormatOptions) {
+    for (i, style) in FormatStyle::value_variants().iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        let options = FormatOptions {
+            style: *style,
+            ..options
+        };
+        println!("-- style: {}", style);
+        println!("{}", format_sql(input, &options));
+    }
+}
+
+/// The paths given as arguments followed by those listed in `--files-from`.
+fn input_paths(cli: &Cli) -> Vec<PathBuf> {
+    let mut paths = cli.files.clone();
+    if let Some(list) = &cli.files_from {
+        let contents = if list.as_os_str() == "-" {
+            let mut contents = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut contents) {
+                eprintln!("Error reading stdin: {}", e);
+                process::exit(1);
+            }
+            contents
+        } else {
+            read_file(list)
+        };
+        paths.extend(
+            contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(PathBuf::from),
+        );
+    }
+    if cli.dbt {
+        paths = paths.into_iter().flat_map(dbt_paths).collect();
+    }
+    paths
+}
 
-    let mut input = String::new();
-    if let Err(e) = io::stdin().read_to_string(&mut input) {
+/// The SQL directories of `path` if it is a dbt project, otherwise `path`.
+fn dbt_paths(path: PathBuf) -> Vec<PathBuf> {
+    let project_file = path.join(dbt::PROJECT_FILE);
+    if !project_file.is_file() {
+        return vec![path];
+    }
+    dbt::sql_paths(&read_file(&project_file))
+        .into_iter()
+        .map(|dir| path.join(dir))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// Plain SQL printed to stdout can be written statement by statement; the
+/// diff, JSON summary, `--list-different`, `--statement-index`, `--range`,
+/// `--changed-since`, `--output`, dbt and embedded modes need the whole
+/// input.
+fn streams(cli: &Cli) -> bool {
+    !cli.diff
+        && !cli.list_different
+        && cli.format == OutputFormat::Sql
+        && cli.statement_index.is_none()
+        && cli.range.is_none()
+        && cli.changed_since.is_none()
+        && cli.output.is_none()
+        && cli.reporter == Reporter::Text
+        && cli.embedded.is_none()
+        && !cli.dbt
+        && cli.encoding.is_line_based()
+        && io::stdin().lock().fill_buf().is_ok_and(|head| {
+            // Input with a byte order mark is decoded in one piece, and a
+            // per-file directive applies to every statement
+            (cli.encoding != Encoding::Auto || Encoding::from_bom(head).is_none())
+                && !config_file::has_directive(&String::from_utf8_lossy(head))
+        })
+}
+
+fn stream_stdin(cli: &Cli, options: &FormatOptions) {
+    let color = cli.color.enabled();
+    let result = stream::run(
+        io::stdin().lock(),
+        io::stdout().lock(),
+        options.dialect,
+        cli.encoding,
+        cli.line_endings,
+        |sql, first_line| {
+            let formatted = check_input(cli, "<stdin>", sql, options, first_line)
+                .and_then(|()| format_sql_input(cli, sql, options))
+                .map_err(|e| io::Error::other(format!("<stdin>: {}", e)))?;
+            Ok(render(cli, "<stdin>", sql, &formatted, options, color))
+        },
+    );
+    match result {
+        Ok(true) => {}
+        Ok(false) => {
+            eprintln!("Error: no SQL input provided");
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn read_stdin(cli: &Cli) -> (String, FileFormat) {
+    let mut bytes = Vec::new();
+    if let Err(e) = io::stdin().read_to_end(&mut bytes) {
         eprintln!("Error reading stdin: {}", e);
         process::exit(1);
     }
+    let (input, format) = decode(cli, "stdin", &bytes);
 
     if input.trim().is_empty() {
         eprintln!("Error: no SQL input provided");
         process::exit(1);
     }
+    (input, format)
+}
+
+fn read_file(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", path.display(), e);
+        process::exit(1);
+    })
+}
+
+/// Read an input file in the `--encoding` encoding, or the one detected.
+fn load(cli: &Cli, path: &Path) -> Result<(String, FileFormat), String> {
+    let bytes = fs::read(path).map_err(|e| e.to_string())?;
+    FileFormat::decode(&bytes, cli.encoding)
+}
+
+fn read_source(cli: &Cli, path: &Path) -> (String, FileFormat) {
+    load(cli, path).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", path.display(), e);
+        process::exit(1);
+    })
+}
+
+fn decode(cli: &Cli, name: &str, bytes: &[u8]) -> (String, FileFormat) {
+    FileFormat::decode(bytes, cli.encoding).unwrap_or_else(|e| {
+        eprintln!("Error reading {}: {}", name, e);
+        process::exit(1);
+    })
+}
+
+/// Overwrite a source file with its formatted contents, first saving the
+/// original under `--backup`.
+fn write_in_place(
+    cli: &Cli,
+    path: &Path,
+    formatted: &str,
+    format: FileFormat,
+) -> Result<(), String> {
+    if let Some(suffix) = &cli.backup {
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(suffix);
+        fs::copy(path, &backup).map_err(|e| format!("cannot back up the file: {}", e))?;
+    }
+    write_file(path, formatted, format)
+}
+
+fn write_file(path: &Path, contents: &str, format: FileFormat) -> Result<(), String> {
+    fs::write(path, format.encode(contents)?)
+        .map_err(|e| format!("cannot write {}: {}", path.display(), e))
+}
+
+/// Patterns of the document keys whose values `--embedded yaml` and `json`
+/// format.
+fn sql_keys(cli: &Cli) -> Vec<Pattern> {
+    if cli.sql_key.is_empty() {
+        document::DEFAULT_KEYS
+            .iter()
+            .map(|k| Pattern::new(k))
+            .collect()
+    } else {
+        cli.sql_key.iter().map(|k| Pattern::new(k)).collect()
+    }
+}
+
+/// Extensions of the files searched for in directories.
+fn extensions(cli: &Cli) -> &'static [&'static str] {
+    cli.embedded.map_or(&["sql"], Host::extensions)
+}
+
+/// Format `input` as it is written out. With `--embedded`, `input` is a
+/// source file and only its SQL literals are formatted; with `--dbt`, it is
+/// a model whose Jinja header is kept as written.
+fn format_input(cli: &Cli, input: &str, options: &FormatOptions) -> Result<String, String> {
+    if let Some(index) = cli.statement_index {
+        return format_sql_input(cli, select_statement(input, index, options)?, options);
+    }
+    if let Some(range) = &cli.range {
+        let formatted = format_range(input, range.clone(), options);
+        verify_partial(cli, input, &formatted, options)?;
+        return Ok(formatted);
+    }
+    match cli.embedded {
+        Some(host) => Ok(embedded::format_source(
+            host,
+            input,
+            &sql_keys(cli),
+            |sql| format_sql_input(cli, sql, options),
+        )),
+        // The Jinja header of a dbt model stays as written
+        None if cli.dbt => {
+            let model = dbt::Model::parse(input, options.dialect);
+            Ok(model.join(&format_sql_input(cli, model.body, options)?))
+        }
+        None => format_sql_input(cli, input, options),
+    }
+}
+
+/// Format the statements of the file at `path` touching the lines changed
+/// since `git_ref`. A file that did not exist at `git_ref` is formatted
+/// whole.
+fn format_changed(
+    cli: &Cli,
+    git_ref: &str,
+    path: &Path,
+    input: &str,
+    options: &FormatOptions,
+) -> Result<String, String> {
+    let Some(lines) = changed::changed_lines(git_ref, path).map_err(|e| e.to_string())? else {
+        return format_input(cli, input, options);
+    };
+    let formatted = format_ranges(input, &changed::byte_ranges(input, &lines), options);
+    verify_partial(cli, input, &formatted, options)?;
+    Ok(formatted)
+}
+
+/// Under `--verify`, check a partly formatted `input` still lexes to the
+/// same tokens.
+fn verify_partial(
+    cli: &Cli,
+    input: &str,
+    formatted: &str,
+    options: &FormatOptions,
+) -> Result<(), String> {
+    if cli.verify {
+        let tokens = lexer::tokenize_with_dialect(input, options.dialect);
+        verify::check(&tokens, formatted, options.dialect)
+            .map_err(|e| format!("formatting would change the SQL, {}", e))?;
+    }
+    Ok(())
+}
+
+/// The `index`th statement (from 1) of `input`, for `--statement-index`.
+fn select_statement<'a>(
+    input: &'a str,
+    index: u32,
+    options: &FormatOptions,
+) -> Result<&'a str, String> {
+    let statements = lexer::split_statements(input, options.dialect);
+    statements.get(index as usize - 1).copied().ok_or_else(|| {
+        format!(
+            "no statement {}: the input has {} statement{}",
+            index,
+            statements.len(),
+            if statements.len() == 1 { "" } else { "s" }
+        )
+    })
+}
+
+/// Format SQL with a trailing newline, applying the `--fix` rewrites first
+/// when requested. Under `--verify`, fails when the output does not lex
+/// back to the same tokens.
+fn format_sql_input(cli: &Cli, input: &str, options: &FormatOptions) -> Result<String, String> {
+    let mut tokens = lexer::tokenize_with_dialect(input, options.dialect);
+    if cli.fix {
+        tokens = fix::apply(tokens);
+    }
+    let formatted = if cli.tolerant {
+        format!("{}\n", format_tolerant(input, options).text)
+    } else {
+        format!("{}\n", formatter::format_tokens(&tokens, options))
+    };
+    if cli.verify {
+        verify::check(&tokens, &formatted, options.dialect)
+            .map_err(|e| format!("formatting would change the SQL, {}", e))?;
+    }
+    Ok(formatted)
+}
 
-    let formatted = format_sql(&input, &options);
-    println!("{}", formatted);
+/// Print a caret diagnostic for each unclosed literal, comment or
+/// parenthesis in `input`, whose first line is `first_line`. Malformed SQL
+/// is only warned about, unless `--strict` refuses it rather than format it
+/// into something unexpected. Embedded sources are not SQL as a whole and
+/// are left to the literal rewriter.
+fn check_input(
+    cli: &Cli,
+    name: &str,
+    input: &str,
+    options: &FormatOptions,
+    first_line: usize,
+) -> Result<(), String> {
+    if cli.embedded.is_some() {
+        return Ok(());
+    }
+    let found = lexer::diagnostics(input, options.dialect);
+    let severity = if cli.strict {
+        Severity::Error
+    } else {
+        Severity::Warning
+    };
+    for d in &found {
+        eprint!(
+            "{}",
+            diagnostic::render(name, input, d, severity, first_line)
+        );
+    }
+    if !cli.strict {
+        return Ok(());
+    }
+    match found.len() {
+        0 => Ok(()),
+        1 => Err("malformed SQL: 1 problem reported above".to_string()),
+        n => Err(format!("malformed SQL: {} problems reported above", n)),
+    }
+}
+
+/// `options` overridden by the `-- sql-indent:` directive on the first line
+/// of `input`. Embedded sources are not SQL and have none.
+fn file_options(cli: &Cli, input: &str, options: &FormatOptions) -> Result<FormatOptions, String> {
+    let mut options = *options;
+    if cli.embedded.is_none() {
+        config_file::apply_directive(input, &mut options)?;
+    }
+    Ok(options)
+}
+
+/// Rewrite `path` with its formatted contents, leaving unchanged files alone.
+/// Returns whether the file was rewritten.
+fn write_formatted(
+    cli: &Cli,
+    path: &Path,
+    input: &str,
+    format: FileFormat,
+    options: &FormatOptions,
+) -> Result<bool, String> {
+    let options = &file_options(cli, input, options)?;
+    check_input(cli, &path.display().to_string(), input, options, 1)?;
+    let formatted = format_input(cli, input, options)?;
+    let out_format = format.with(cli.line_endings, cli.bom);
+    if formatted == input && out_format == format {
+        return Ok(false);
+    }
+    write_in_place(cli, path, &formatted, out_format)?;
+    Ok(true)
+}
+
+/// Where an input came from, and so where `--write` and `--staged` put the
+/// result.
+enum Target<'a> {
+    Stdin,
+    File(&'a Path),
+    /// A staged file in the repository rooted at the path
+    Index(&'a Path, &'a StagedFile),
+}
+
+/// Format one input and write, print or only report the result, depending
+/// on the flags and where the input came from.
+fn process(
+    cli: &Cli,
+    name: &str,
+    target: Target<'_>,
+    input: &str,
+    format: FileFormat,
+    options: &FormatOptions,
+) -> FileReport {
+    let options = match file_options(cli, input, options) {
+        Ok(options) => options,
+        Err(e) => return failed(name, input.len(), e),
+    };
+    let options = &options;
+    let formatted = match check_input(cli, name, input, options, 1).and_then(|()| {
+        match (&cli.changed_since, &target) {
+            (Some(git_ref), Target::File(path)) => {
+                format_changed(cli, git_ref, path, input, options)
+            }
+            _ => format_input(cli, input, options),
+        }
+    }) {
+        Ok(formatted) => formatted,
+        Err(e) => return failed(name, input.len(), e),
+    };
+    // A file whose line endings or byte order mark change is rewritten too
+    let out_format = format.with(cli.line_endings, cli.bom);
+    let changed = formatted != input || out_format != format;
+    let written = if cli.list_different {
+        if changed {
+            println!("{}", name);
+        }
+        Ok(())
+    } else if let (true, Target::File(path)) = (cli.write, &target) {
+        if changed {
+            write_in_place(cli, path, &formatted, out_format)
+        } else {
+            Ok(())
+        }
+    } else if let (false, Target::Index(root, file)) = (cli.diff, &target) {
+        if changed {
+            update_index(root, file, input, format, &formatted, out_format)
+        } else {
+            Ok(())
+        }
+    } else if let Some(output) = &cli.output {
+        write_file(
+            output,
+            &render(cli, name, input, &formatted, options, false),
+            out_format,
+        )
+    } else {
+        if cli.reporter == Reporter::Text {
+            let color = cli.color.enabled();
+            let rendered = render(cli, name, input, &formatted, options, color);
+            print!("{}", out_format.line_endings(&rendered));
+        }
+        Ok(())
+    };
+    if let Err(e) = written {
+        return failed(name, input.len(), e);
+    }
+
+    FileReport {
+        path: name.to_string(),
+        changed,
+        bytes_in: input.len(),
+        bytes_out: formatted.len(),
+        warnings: if cli.reporter == Reporter::Json {
+            warnings(cli, input, &formatted, options)
+        } else {
+            Vec::new()
+        },
+        error: None,
+    }
+}
+
+/// Print and report an input that could not be formatted or written.
+fn failed(name: &str, bytes_in: usize, error: String) -> FileReport {
+    eprintln!("Error: {}: {}", name, error);
+    error_report(name, bytes_in, error)
+}
+
+/// The report of an input left alone because of `error`.
+fn error_report(name: &str, bytes_in: usize, error: String) -> FileReport {
+    FileReport {
+        path: name.to_string(),
+        changed: false,
+        bytes_in,
+        bytes_out: 0,
+        warnings: Vec::new(),
+        error: Some(error),
+    }
+}
+
+/// Replace the staged copy of `file` with `formatted`.
+fn update_index(
+    root: &Path,
+    file: &StagedFile,
+    input: &str,
+    format: FileFormat,
+    formatted: &str,
+    out_format: FileFormat,
+) -> Result<(), String> {
+    let original = format.encode(input)?;
+    let contents = out_format.encode(formatted)?;
+    staged::update(root, file, &original, &contents)
+        .map_err(|e| format!("cannot update the index: {}", e))
+}
+
+/// Format every staged file from its index copy. Failing to list the
+/// staged files aborts; a file that cannot be read, formatted or updated is
+/// reported and left as staged.
+fn format_staged(cli: &Cli, resolver: &mut OptionsResolver) -> Vec<FileReport> {
+    let excludes: Vec<Pattern> = cli.exclude.iter().map(|p| Pattern::new(p)).collect();
+    let root = staged::repo_root().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    });
+    let files = staged::list(&root, extensions(cli)).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    });
+
+    let files: Vec<&StagedFile> = files
+        .iter()
+        .filter(|f| !excludes.iter().any(|p| p.matches(&f.path)))
+        .collect();
+    batch(cli, &files, |file| {
+        let name = file.path.display().to_string();
+        let options = match resolver.for_file(&root.join(&file.path)) {
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("Error in {}", e);
+                return error_report(&name, 0, e);
+            }
+        };
+        let decoded = staged::read(&root, file)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| FileFormat::decode(&bytes, cli.encoding));
+        match decoded {
+            Ok((input, format)) => process(
+                cli,
+                &name,
+                Target::Index(&root, file),
+                &input,
+                format,
+                &options,
+            ),
+            Err(e) => {
+                eprintln!("Error reading {} from the index: {}", name, e);
+                error_report(&name, 0, e)
+            }
+        }
+    })
+}
+
+/// Process each of `items` in turn, drawing the `--progress` bar and
+/// printing the `--verbose` messages.
+fn batch<T>(cli: &Cli, items: &[T], mut run: impl FnMut(&T) -> FileReport) -> Vec<FileReport> {
+    let mut progress = Progress::new(items.len(), cli.progress && !cli.quiet);
+    let mut reports = Vec::with_capacity(items.len());
+    for item in items {
+        progress.clear();
+        let report = run(item);
+        if cli.verbose && report.error.is_none() {
+            let outcome = if report.changed {
+                "reformatted"
+            } else {
+                "unchanged"
+            };
+            eprintln!("{} {}", outcome, report.path);
+        }
+        let stop = cli.fail_fast && report.error.is_some();
+        reports.push(report);
+        progress.advance();
+        if stop {
+            break;
+        }
+    }
+    progress.clear();
+    reports
+}
+
+/// Problems worth flagging to CI even though formatting succeeded.
+fn warnings(cli: &Cli, input: &str, formatted: &str, options: &FormatOptions) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if input.trim().is_empty() {
+        warnings.push("no SQL found".to_string());
+    } else if cli.changed_since.is_none()
+        // Under --changed-since a second pass would format untouched lines too
+        && format_input(cli, formatted, options).is_ok_and(|again| again != formatted)
+    {
+        warnings.push("formatting is not stable: a second pass changes the output".to_string());
+    }
+    warnings
+}
+
+/// Poll the given paths forever, reformatting each file after it is saved.
+fn watch(
+    cli: &Cli,
+    paths: &[PathBuf],
+    excludes: Vec<Pattern>,
+    resolver: &mut OptionsResolver,
+) -> ! {
+    let mut watcher = Watcher::new(paths.to_vec(), excludes, extensions(cli)).unwrap_or_else(|e| {
+        eprintln!("Error searching for SQL files: {}", e);
+        process::exit(1);
+    });
+    if !cli.quiet {
+        eprintln!("Watching for changes (Ctrl-C to stop)");
+    }
+    loop {
+        thread::sleep(WATCH_INTERVAL);
+        let changed = match watcher.poll() {
+            Ok(changed) => changed,
+            Err(e) => {
+                eprintln!("Error searching for SQL files: {}", e);
+                continue;
+            }
+        };
+        for path in changed {
+            // The editor may still be writing; retry on the next change
+            let Ok(bytes) = fs::read(&path) else {
+                continue;
+            };
+            let (input, format) = match FileFormat::decode(&bytes, cli.encoding) {
+                Ok(decoded) => decoded,
+                Err(e) => {
+                    eprintln!("skipped {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let written = resolver
+                .for_file(&path)
+                .and_then(|options| write_formatted(cli, &path, &input, format, &options));
+            match written {
+                Ok(true) => {
+                    watcher.refresh(&path);
+                    if !cli.quiet {
+                        eprintln!("formatted {}", path.display());
+                    }
+                }
+                Ok(false) if cli.quiet => {}
+                Ok(false) => eprintln!("unchanged {}", path.display()),
+                Err(e) => eprintln!("skipped {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+/// The formatted SQL, or the diff to it, for one input.
+fn render(
+    cli: &Cli,
+    name: &str,
+    input: &str,
+    formatted: &str,
+    options: &FormatOptions,
+    color: bool,
+) -> String {
+    if cli.diff {
+        cli::diff::unified_diff(input, formatted, name, name)
+    } else if cli.format == OutputFormat::Html {
+        let sql = formatted.strip_suffix('\n').unwrap_or(formatted);
+        format!("{}\n", highlight::html_block(sql, options.dialect))
+    } else if color {
+        highlight::to_ansi(formatted, options.dialect, &Theme::default())
+    } else {
+        formatted.to_string()
+    }
 }
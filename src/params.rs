@@ -0,0 +1,371 @@
+//! Inlining of query parameters, so a query logged with its bind values can
+//! be printed as the SQL that actually ran.
+
+use crate::config::PlaceholderStyle;
+use crate::lexer::tokenize;
+use crate::token::Token;
+
+/// A bind value, written into the query as a SQL literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamValue {
+    Null,
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    /// Quoted as a string literal, with quotes inside it doubled
+    Text(String),
+}
+
+impl ParamValue {
+    /// The value as a SQL literal: `NULL`, `TRUE`, `42`, `'it''s'`.
+    pub fn to_sql(&self) -> String {
+        match self {
+            ParamValue::Null => "NULL".to_string(),
+            ParamValue::Bool(true) => "TRUE".to_string(),
+            ParamValue::Bool(false) => "FALSE".to_string(),
+            ParamValue::Integer(n) => n.to_string(),
+            ParamValue::Float(f) if f.is_finite() => f.to_string(),
+            // NaN and infinities have no literal; PostgreSQL accepts these
+            ParamValue::Float(f) => format!("'{}'", f),
+            ParamValue::Text(s) => format!("'{}'", s.replace('\'', "''")),
+        }
+    }
+}
+
+impl From<bool> for ParamValue {
+    fn from(b: bool) -> Self {
+        ParamValue::Bool(b)
+    }
+}
+
+impl From<i64> for ParamValue {
+    fn from(n: i64) -> Self {
+        ParamValue::Integer(n)
+    }
+}
+
+impl From<f64> for ParamValue {
+    fn from(f: f64) -> Self {
+        ParamValue::Float(f)
+    }
+}
+
+impl From<&str> for ParamValue {
+    fn from(s: &str) -> Self {
+        ParamValue::Text(s.to_string())
+    }
+}
+
+impl From<String> for ParamValue {
+    fn from(s: String) -> Self {
+        ParamValue::Text(s)
+    }
+}
+
+impl<T: Into<ParamValue>> From<Option<T>> for ParamValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(ParamValue::Null, Into::into)
+    }
+}
+
+/// Values for the placeholders of a query.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum QueryParams {
+    #[default]
+    None,
+    /// For `?` in order, and `$1` or `?1` by position (from 1)
+    Indexed(Vec<ParamValue>),
+    /// For `:name`, `@name` and `$name`
+    Named(Vec<(String, ParamValue)>),
+}
+
+/// Replace the placeholders in `tokens` that `params` has a value for with
+/// that value's literal tokens, which borrow from `literals`. Placeholders
+/// without a value are left as written.
+pub fn substitute<'a>(
+    tokens: &[Token<'a>],
+    params: &QueryParams,
+    literals: &'a [String],
+) -> Vec<Token<'a>> {
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut next_index = 0;
+    let mut i = 0;
+    while i < tokens.len() {
+        let (value, len) = match placeholder(&tokens[i..]) {
+            Some((Placeholder::Next, len)) => {
+                next_index += 1;
+                (index_value(params, literals, next_index - 1), len)
+            }
+            Some((Placeholder::Position(n), len)) => {
+                (index_value(params, literals, n.wrapping_sub(1)), len)
+            }
+            Some((Placeholder::Name(name), len)) => (name_value(params, literals, name), len),
+            None => (None, 1),
+        };
+        match value {
+            Some(literal) => out.extend(tokenize(literal)),
+            None => out.extend_from_slice(&tokens[i..i + len]),
+        }
+        i += len;
+    }
+    out
+}
+
+/// The literals `substitute` borrows from, in the order of `params`.
+pub fn literals(params: &QueryParams) -> Vec<String> {
+    match params {
+        QueryParams::None => Vec::new(),
+        QueryParams::Indexed(values) => values.iter().map(ParamValue::to_sql).collect(),
+        QueryParams::Named(values) => values.iter().map(|(_, v)| v.to_sql()).collect(),
+    }
+}
+
+/// New text for the bind placeholders of `tokens` in `style`, by token
+/// index. Numbering starts over with each statement, and a name used twice
+/// keeps its number. Statements numbering their placeholders out of order
+/// (`$2 ... $1`) are left alone when rewriting to `?`, which cannot say so.
+pub(crate) fn rename_placeholders(
+    tokens: &[Token<'_>],
+    style: PlaceholderStyle,
+) -> Vec<(usize, String)> {
+    let mut renamed = Vec::new();
+    let mut statement = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Placeholder(text) => statement.extend(bind_placeholder(text).map(|p| (i, p))),
+            Token::Semicolon | Token::BatchSeparator(_) => {
+                renamed.extend(rename_statement(&statement, style));
+                statement.clear();
+            }
+            _ => {}
+        }
+    }
+    renamed.extend(rename_statement(&statement, style));
+    renamed
+}
+
+fn rename_statement(
+    placeholders: &[(usize, Placeholder<'_>)],
+    style: PlaceholderStyle,
+) -> Vec<(usize, String)> {
+    if style == PlaceholderStyle::Question {
+        let in_order = placeholders.iter().enumerate().all(|(k, (_, p))| match p {
+            Placeholder::Position(n) => *n == k + 1,
+            _ => true,
+        });
+        if !in_order {
+            return Vec::new();
+        }
+    }
+    let mut names: Vec<(&str, usize)> = Vec::new();
+    let mut count = 0;
+    let mut renamed = Vec::new();
+    for &(i, placeholder) in placeholders {
+        let number = match placeholder {
+            Placeholder::Position(n) => n,
+            Placeholder::Next => {
+                count += 1;
+                count
+            }
+            Placeholder::Name(name) => match names.iter().find(|(n, _)| *n == name) {
+                Some(&(_, number)) => number,
+                None => {
+                    count += 1;
+                    names.push((name, count));
+                    count
+                }
+            },
+        };
+        renamed.push((
+            i,
+            match (style, placeholder) {
+                (PlaceholderStyle::Question, _) => "?".to_string(),
+                (PlaceholderStyle::Dollar, _) => format!("${}", number),
+                (PlaceholderStyle::Colon, Placeholder::Name(name)) => format!(":{}", name),
+                (PlaceholderStyle::Colon, _) => format!(":p{}", number),
+            },
+        ));
+    }
+    renamed
+}
+
+/// `tokens` with the placeholders at the indexes in `renamed` replaced.
+pub(crate) fn with_renamed<'a>(
+    tokens: &[Token<'a>],
+    renamed: &'a [(usize, String)],
+) -> Vec<Token<'a>> {
+    let mut tokens = tokens.to_vec();
+    for (i, text) in renamed {
+        tokens[*i] = Token::Placeholder(text);
+    }
+    tokens
+}
+
+/// A bind placeholder, as the lexer reads them.
+#[derive(Clone, Copy)]
+enum Placeholder<'a> {
+    /// `?` or `%s`, taking the next value
+    Next,
+    /// `$1` or `?1`, from 1
+    Position(usize),
+    /// `:name`, `%(name)s` or `@name`
+    Name(&'a str),
+}
+
+/// The bind placeholder a `Token::Placeholder` holds. Flyway's `${name}`
+/// is not one, nor is `@name`, which may be a session variable and so is
+/// only substituted, never renamed.
+fn bind_placeholder(text: &str) -> Option<Placeholder<'_>> {
+    match text.as_bytes().first()? {
+        b'?' if text.len() == 1 => Some(Placeholder::Next),
+        b'?' | b'$' => text[1..].parse().ok().map(Placeholder::Position),
+        b':' => Some(Placeholder::Name(&text[1..])),
+        b'%' if text == "%s" => Some(Placeholder::Next),
+        b'%' => text
+            .strip_prefix("%(")
+            .and_then(|name| name.strip_suffix(")s"))
+            .map(Placeholder::Name),
+        _ => None,
+    }
+}
+
+/// The placeholder `tokens` start with, and how many tokens it spans.
+fn placeholder<'a>(tokens: &[Token<'a>]) -> Option<(Placeholder<'a>, usize)> {
+    match tokens {
+        [Token::Placeholder(text), ..] => match text.strip_prefix('@') {
+            Some(name) => Some((Placeholder::Name(name), 1)),
+            None => Some((bind_placeholder(text)?, 1)),
+        },
+        [
+            Token::Operator("@") | Token::Error("@" | "$"),
+            Token::Identifier(name),
+            ..,
+        ] => Some((Placeholder::Name(name), 2)),
+        // T-SQL and BigQuery read `@name` as one token
+        [Token::Identifier(word), ..] if word.len() > 1 && word.starts_with('@') => {
+            Some((Placeholder::Name(&word[1..]), 1))
+        }
+        _ => None,
+    }
+}
+
+fn index_value<'a>(params: &QueryParams, literals: &'a [String], index: usize) -> Option<&'a str> {
+    match params {
+        QueryParams::Indexed(_) => literals.get(index).map(String::as_str),
+        _ => None,
+    }
+}
+
+fn name_value<'a>(params: &QueryParams, literals: &'a [String], name: &str) -> Option<&'a str> {
+    match params {
+        QueryParams::Named(values) => values
+            .iter()
+            .position(|(n, _)| n == name)
+            .map(|i| literals[i].as_str()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Dialect, FormatOptions};
+    use crate::format_with_params;
+
+    /// The formatted query with whitespace collapsed, to compare on one line
+    fn inline(sql: &str, params: QueryParams) -> String {
+        let formatted = format_with_params(sql, &FormatOptions::default(), &params);
+        formatted.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    #[test]
+    fn test_to_sql() {
+        assert_eq!(ParamValue::from("it's").to_sql(), "'it''s'");
+        assert_eq!(ParamValue::from(None::<i64>).to_sql(), "NULL");
+        assert_eq!(ParamValue::from(-3).to_sql(), "-3");
+        assert_eq!(ParamValue::from(1.5).to_sql(), "1.5");
+        assert_eq!(ParamValue::from(f64::NAN).to_sql(), "'NaN'");
+        assert_eq!(ParamValue::from(true).to_sql(), "TRUE");
+    }
+
+    #[test]
+    fn test_indexed() {
+        let params = QueryParams::Indexed(vec!["a'b".into(), 2.into()]);
+        assert_eq!(
+            inline("select ?, ?, ? from t", params.clone()),
+            "SELECT 'a''b', 2, ? FROM t"
+        );
+        assert_eq!(
+            inline("select %s, %s from t", params.clone()),
+            "SELECT 'a''b', 2 FROM t"
+        );
+        assert_eq!(
+            inline("select $2, ?1, ? from t", params),
+            "SELECT 2, 'a''b', 'a''b' FROM t"
+        );
+    }
+
+    #[test]
+    fn test_named() {
+        let params = QueryParams::Named(vec![
+            ("id".to_string(), 7.into()),
+            ("name".to_string(), "x".into()),
+        ]);
+        assert_eq!(
+            inline(
+                "select * from t where id = :id and n = @name or v::int = $id",
+                params.clone()
+            ),
+            "SELECT * FROM t WHERE id = 7 AND n = 'x' OR v::int = 7"
+        );
+        assert_eq!(
+            inline("select %(name)s, %(id)s", params.clone()),
+            "SELECT 'x', 7"
+        );
+        let options = FormatOptions {
+            dialect: Dialect::Tsql,
+            ..FormatOptions::default()
+        };
+        assert_eq!(
+            format_with_params("select @name, @other", &options, &params),
+            "SELECT\n    'x',\n    @other"
+        );
+    }
+
+    #[test]
+    fn test_rename_placeholders() {
+        let rename = |sql, style| {
+            let options = FormatOptions {
+                placeholders: Some(style),
+                ..FormatOptions::default()
+            };
+            let formatted = crate::format_sql(sql, &options);
+            formatted.split_whitespace().collect::<Vec<_>>().join(" ")
+        };
+        let sql = "select :a, ?, :a from t where x = ${flyway}; select ?, ?";
+        assert_eq!(
+            rename(sql, PlaceholderStyle::Dollar),
+            "SELECT $1, $2, $1 FROM t WHERE x = ${flyway}; SELECT $1, $2"
+        );
+        assert_eq!(
+            rename(sql, PlaceholderStyle::Colon),
+            "SELECT :a, :p2, :a FROM t WHERE x = ${flyway}; SELECT :p1, :p2"
+        );
+        assert_eq!(
+            rename("select %(a)s, %s, @v from t", PlaceholderStyle::Dollar),
+            "SELECT $1, $2, @v FROM t"
+        );
+        assert_eq!(
+            rename("select $1, $2; select $2, $1", PlaceholderStyle::Question),
+            "SELECT ?, ?; SELECT $2, $1"
+        );
+    }
+
+    #[test]
+    fn test_unknown_placeholders_kept() {
+        assert_eq!(
+            inline("select ?, :id", QueryParams::None),
+            inline("select ?, :id", QueryParams::Indexed(Vec::new()))
+        );
+    }
+}
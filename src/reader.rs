@@ -0,0 +1,262 @@
+//! SQL read from an [`io::BufRead`] a run of statements at a time, for
+//! inputs such as multi-gigabyte dumps that should not be held in one
+//! `&str`. Each [`Chunk`] owns its text, which its tokens borrow from, so
+//! only the statement being read stays in memory.
+//!
+//! Whenever a line may end a statement, the text read so far is lexed and
+//! everything up to its last terminator becomes a chunk. Text whose tokens
+//! depend on input further ahead (an open procedural or template block, an
+//! unterminated `$$`/`{{` opener, a `DELIMITER` in effect) is held back
+//! until the construct closes, so formatting the chunks one by one gives
+//! the same output as formatting the input in one piece.
+
+use std::io::{self, BufRead};
+
+use crate::config::Dialect;
+use crate::token::Token;
+use crate::{formatter, lexer};
+
+/// A run of complete statements, or the rest of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub text: String,
+    /// Line of the input the text starts on, from 1
+    pub first_line: usize,
+    dialect: Dialect,
+}
+
+impl Chunk {
+    /// The tokens of the chunk, borrowing its text.
+    pub fn tokens(&self) -> Vec<Token<'_>> {
+        lexer::tokenize_with_dialect(&self.text, self.dialect)
+    }
+}
+
+/// Splits SQL fed to it a line at a time into [`Chunk`]s, for callers that
+/// read and decode lines themselves.
+#[derive(Debug, Clone)]
+pub struct StatementBuffer {
+    dialect: Dialect,
+    pending: String,
+    first_line: usize,
+    /// Length `pending` must reach before its statement ends are checked
+    /// again, after it was held back for input further ahead
+    recheck_len: usize,
+}
+
+impl StatementBuffer {
+    pub fn new(dialect: Dialect) -> Self {
+        StatementBuffer {
+            dialect,
+            pending: String::new(),
+            first_line: 1,
+            recheck_len: 0,
+        }
+    }
+
+    /// Add `line`, ending in its line break unless it is the last, and
+    /// return the statements it completes, if any.
+    pub fn push_line(&mut self, line: &str) -> Option<Chunk> {
+        self.pending.push_str(line);
+        if self.pending.len() < self.recheck_len || !may_end_statement(line) {
+            return None;
+        }
+        let end = lexer::statement_ends(&self.pending, self.dialect).last()?;
+        let tokens = lexer::tokenize_with_dialect(&self.pending[..end], self.dialect);
+        if formatter::needs_lookahead(&tokens, self.dialect) {
+            // Waiting for the text to double keeps a block that never
+            // closes from being lexed again at every statement end
+            self.recheck_len = self.pending.len() * 2;
+            return None;
+        }
+        self.recheck_len = 0;
+        let chunk = Chunk {
+            text: self.pending.drain(..end).collect(),
+            first_line: self.first_line,
+            dialect: self.dialect,
+        };
+        self.first_line += chunk.text.matches('\n').count();
+        Some(chunk)
+    }
+
+    /// What is left once the input has ended, unless it is only whitespace.
+    pub fn finish(self) -> Option<Chunk> {
+        if self.pending.trim().is_empty() {
+            return None;
+        }
+        Some(Chunk {
+            text: self.pending,
+            first_line: self.first_line,
+            dialect: self.dialect,
+        })
+    }
+}
+
+/// Only lines with a semicolon or a lone batch separator can end a
+/// statement; skipping the rest keeps long statements from being re-lexed
+/// on every line.
+fn may_end_statement(line: &str) -> bool {
+    let trimmed = line.trim();
+    line.contains(';')
+        || trimmed == "/"
+        || trimmed
+            .get(..2)
+            .is_some_and(|word| word.eq_ignore_ascii_case("GO"))
+}
+
+/// The [`Chunk`]s of UTF-8 SQL read from `reader`. Invalid UTF-8 is an
+/// [`io::ErrorKind::InvalidData`] error, after which iteration ends.
+pub struct StatementReader<R> {
+    reader: R,
+    buffer: Option<StatementBuffer>,
+}
+
+impl<R: BufRead> StatementReader<R> {
+    pub fn new(reader: R, dialect: Dialect) -> Self {
+        StatementReader {
+            reader,
+            buffer: Some(StatementBuffer::new(dialect)),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StatementReader<R> {
+    type Item = io::Result<Chunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        loop {
+            let buffer = self.buffer.as_mut()?;
+            line.clear();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => return self.buffer.take()?.finish().map(Ok),
+                Ok(_) => {
+                    if let Some(chunk) = buffer.push_line(&line) {
+                        return Some(Ok(chunk));
+                    }
+                }
+                Err(e) => {
+                    self.buffer = None;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunks(input: &str, dialect: Dialect) -> Vec<(String, usize)> {
+        StatementReader::new(input.as_bytes(), dialect)
+            .map(|chunk| {
+                let chunk = chunk.unwrap();
+                (chunk.text, chunk.first_line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reads_statements_as_they_end() {
+        assert_eq!(
+            chunks(
+                "select a from t;\nselect 'x;\ny' from u; -- note\nselect 3",
+                Dialect::Generic
+            ),
+            vec![
+                ("select a from t;".to_string(), 1),
+                ("\nselect 'x;\ny' from u;".to_string(), 1),
+                (" -- note\nselect 3".to_string(), 3)
+            ]
+        );
+        assert_eq!(chunks("select 1;\n  \n", Dialect::Generic).len(), 1);
+    }
+
+    #[test]
+    fn test_transactions_stream() {
+        let mut input = "begin;\n".to_string();
+        for i in 0..1000 {
+            input.push_str(&format!("insert into t values ({});\n", i));
+        }
+        input.push_str("commit;\n");
+        let chunks = chunks(&input, Dialect::Postgres);
+        assert_eq!(chunks.len(), 1002);
+        assert_eq!(chunks[0], ("begin;".to_string(), 1));
+        assert_eq!(chunks[1001], ("\ncommit;".to_string(), 1001));
+    }
+
+    #[test]
+    fn test_cursor_declarations_stream() {
+        assert_eq!(
+            chunks(
+                "declare c cursor for select 1;\nfetch c;\n",
+                Dialect::Postgres
+            ),
+            vec![
+                ("declare c cursor for select 1;".to_string(), 1),
+                ("\nfetch c;".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oracle_tables_stream() {
+        assert_eq!(
+            chunks(
+                "create table t (a int);\ncreate table u (b int);\n",
+                Dialect::Oracle
+            ),
+            vec![
+                ("create table t (a int);".to_string(), 1),
+                ("\ncreate table u (b int);".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resumes_after_blocks_close() {
+        let input = "select 1;\nbegin\n  let x := 1;\nend;\nselect 2;\nselect 3;\nselect 4;\n";
+        assert_eq!(
+            chunks(input, Dialect::Snowflake),
+            vec![
+                ("select 1;".to_string(), 1),
+                (
+                    "\nbegin\n  let x := 1;\nend;\nselect 2;\nselect 3;".to_string(),
+                    1
+                ),
+                ("\nselect 4;".to_string(), 6)
+            ]
+        );
+
+        let input = "create procedure p as\nbegin\n  null;\nend;\n/\n\
+                     select 1 from dual;\nselect 2 from dual;\nselect 3 from dual;\n";
+        assert_eq!(
+            chunks(input, Dialect::Oracle).last(),
+            Some(&("\nselect 3 from dual;".to_string(), 7))
+        );
+
+        let input = "DELIMITER $$\ncreate procedure p() begin select 1; end$$\nDELIMITER ;\n\
+                     select 2;\nselect 3;\nselect 4;\nselect 5;\nselect 6;\nselect 7;\n";
+        assert_eq!(
+            chunks(input, Dialect::Mysql).last(),
+            Some(&("\nselect 7;".to_string(), 8))
+        );
+    }
+
+    #[test]
+    fn test_chunk_tokens() {
+        let mut reader = StatementReader::new("select `a`;\n".as_bytes(), Dialect::Mysql);
+        let chunk = reader.next().unwrap().unwrap();
+        assert_eq!(chunk.tokens()[2], Token::BacktickIdentifier("a"));
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut reader = StatementReader::new(&b"select '\xff';\n"[..], Dialect::Generic);
+        let error = reader.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(reader.next().is_none());
+    }
+}
@@ -1,3 +1,9 @@
+//! The token model shared by the lexer, the formatter and everything built
+//! on them. Tokens borrow their text from the input, except keywords, which
+//! are recognized case-insensitively and carry only their [`KeywordKind`].
+//! Whitespace and comments are tokens too, so concatenating the source text
+//! of every token gives back the input.
+
 macro_rules! define_keywords {
     (
         single {
@@ -7,6 +13,8 @@ macro_rules! define_keywords {
             $( $multi_variant:ident => $multi_str:expr ),* $(,)?
         }
     ) => {
+        /// A reserved word, or a run of them read as one (`ORDER BY`,
+        /// `LEFT OUTER JOIN`). Written back in the configured case.
         #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
         pub enum KeywordKind {
             $( $single_variant, )*
@@ -57,6 +65,7 @@ define_keywords! {
         In => "IN",
         Between => "BETWEEN",
         Like => "LIKE",
+        Ilike => "ILIKE",
         Is => "IS",
         Null => "NULL",
         As => "AS",
@@ -90,6 +99,7 @@ define_keywords! {
         Returning => "RETURNING",
         Using => "USING",
         Natural => "NATURAL",
+        Lateral => "LATERAL",
         Fetch => "FETCH",
         For => "FOR",
         Window => "WINDOW",
@@ -160,6 +170,13 @@ define_keywords! {
         Transaction => "TRANSACTION",
         Lock => "LOCK",
         Unlock => "UNLOCK",
+        Declare => "DECLARE",
+        Call => "CALL",
+        Top => "TOP",
+        Qualify => "QUALIFY",
+        Exclude => "EXCLUDE",
+        Pivot => "PIVOT",
+        Unpivot => "UNPIVOT",
     }
     multi {
         OrderBy => "ORDER BY",
@@ -177,6 +194,10 @@ define_keywords! {
         IfNotExists => "IF NOT EXISTS",
         RowsBetween => "ROWS BETWEEN",
         RangeBetween => "RANGE BETWEEN",
+        LateralView => "LATERAL VIEW",
+        DistributeBy => "DISTRIBUTE BY",
+        ClusterBy => "CLUSTER BY",
+        SortBy => "SORT BY",
     }
 }
 
@@ -191,6 +212,7 @@ impl KeywordKind {
                 | KeywordKind::Values
                 | KeywordKind::Into
                 | KeywordKind::Having
+                | KeywordKind::Qualify
                 | KeywordKind::Limit
                 | KeywordKind::Offset
                 | KeywordKind::Union
@@ -203,6 +225,8 @@ impl KeywordKind {
                 | KeywordKind::Delete
                 | KeywordKind::With
                 | KeywordKind::Fetch
+                | KeywordKind::Pivot
+                | KeywordKind::Unpivot
         )
     }
 
@@ -217,6 +241,7 @@ impl KeywordKind {
                 | KeywordKind::FullJoin
                 | KeywordKind::CrossJoin
                 | KeywordKind::Natural
+                | KeywordKind::LateralView
         )
     }
 
@@ -225,7 +250,14 @@ impl KeywordKind {
     }
 
     pub fn is_order_modifier(&self) -> bool {
-        matches!(self, KeywordKind::OrderBy | KeywordKind::GroupBy)
+        matches!(
+            self,
+            KeywordKind::OrderBy
+                | KeywordKind::GroupBy
+                | KeywordKind::DistributeBy
+                | KeywordKind::ClusterBy
+                | KeywordKind::SortBy
+        )
     }
 
     pub fn is_ddl_starter(&self) -> bool {
@@ -241,12 +273,26 @@ impl KeywordKind {
     }
 }
 
+/// One lexical element of SQL. Quoted names, string literals, comments and
+/// `{{ }}` variables hold the text between their delimiters, with escapes
+/// as written: `'it''s'` is `StringLiteral("it''s")` and `-- note` is
+/// `LineComment(" note")`. The other variants hold their text in full.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token<'a> {
     Keyword(KeywordKind),
+    /// A bare name, or a word that is not a keyword: `users`, `count`
     Identifier(&'a str),
+    /// `"name"`
     QuotedIdentifier(&'a str),
+    /// `` `name` ``, in dialects that quote identifiers with backticks
+    BacktickIdentifier(&'a str),
+    /// `[name]`, in dialects that quote identifiers with brackets
+    BracketIdentifier(&'a str),
+    /// `'text'`
     StringLiteral(&'a str),
+    /// A string with a prefix, prefix and quotes included: Oracle's
+    /// `q'[text]'`, `E'text'`, `x'1F'`, `U&'text' UESCAPE '!'`
+    PrefixedString(&'a str),
     NumberLiteral(&'a str),
     Operator(&'a str),
     Comma,
@@ -254,16 +300,239 @@ pub enum Token<'a> {
     Dot,
     OpenParen,
     CloseParen,
+    /// `-- text`, without the line break
     LineComment(&'a str),
+    /// `/* text */`
     BlockComment(&'a str),
     Whitespace(&'a str),
+    /// `{{ expression }}`, including Go template and Handlebars actions
+    /// such as `{{ if .x }}` and `{{#each rows}}`
     TemplateVariable(&'a str),
+    /// `{% statement %}` or `{# comment #}`, delimiters included
+    TemplateBlock(&'a str),
+    /// An ERB tag, delimiters included: `<% ... %>`, `<%= ... %>`
+    TemplateTag(&'a str),
+    /// A bind parameter: `?`, `$1`, `:name`, or Flyway's `${name}`
+    Placeholder(&'a str),
+    /// `$$ body $$` or `$tag$ body $tag$`
+    DollarString(&'a str),
+    /// A procedural body kept as written, such as a PL/SQL unit
+    ProceduralBlock(&'a str),
+    /// T-SQL `GO` or SQL*Plus `/` on a line of its own, a MySQL
+    /// `DELIMITER //` command or the terminator it sets
+    BatchSeparator(&'a str),
+    /// A `-- sql-indent: off` ... `-- sql-indent: on` region, both comments
+    /// included, to be written out exactly as it appears in the input.
+    Unformatted(&'a str),
+    /// A character no rule of the dialect reads, such as `` ` `` outside
+    /// the dialects quoting names with it, kept as written
+    Error(&'a str),
+}
+
+/// Magic comment prefixes used by migration tools. Comments starting with
+/// one of these (after `--`, case-insensitive) must survive formatting on
+/// their own line, otherwise the tool no longer recognizes them.
+const DIRECTIVE_COMMENT_PREFIXES: &[&str] = &[
+    // Liquibase formatted SQL
+    "liquibase formatted sql",
+    "changeset ",
+    "rollback ",
+    "rollback:",
+    "preconditions ",
+    "precondition-",
+    "comment:",
+    "validchecksum:",
+    "ignorelines:",
+    // Flyway script configuration
+    "flyway:",
+    // goose annotations (+goose Up, +goose StatementBegin, ...)
+    "+goose ",
+    // dbmate sections
+    "migrate:up",
+    "migrate:down",
+    // sqitch dependency headers
+    "requires:",
+    "conflicts:",
+];
+
+/// Sqitch script headers look like `-- Deploy project:change to pg`.
+fn is_sqitch_header(text: &str) -> bool {
+    let mut words = text.split_whitespace();
+    let Some(verb) = words.next() else {
+        return false;
+    };
+    ["deploy", "revert", "verify"]
+        .iter()
+        .any(|v| verb.eq_ignore_ascii_case(v))
+        && words.next().is_some_and(|change| change.contains(':'))
+}
+
+/// Role of a token that opens, continues, or closes an indented block, such
+/// as a template control tag or a procedural `BEGIN ... END`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockControl {
+    /// Opens a block: `{{ if .Cond }}`, `{{#each items}}`
+    Open,
+    /// Separates branches of an open block: `{{ else }}`
+    Continue,
+    /// Closes the innermost block: `{{ end }}`, `{{/if}}`
+    Close,
+    /// Control statement without a body: `{{ break }}`
+    Standalone,
+}
+
+/// Classify a Go text/template action by its leading keyword.
+fn go_template_control(action: &str) -> Option<BlockControl> {
+    let keyword = action
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("");
+    match keyword {
+        "if" | "range" | "with" | "define" | "block" => Some(BlockControl::Open),
+        "else" => Some(BlockControl::Continue),
+        "end" => Some(BlockControl::Close),
+        "break" | "continue" => Some(BlockControl::Standalone),
+        _ => None,
+    }
+}
+
+/// Classify a Handlebars/Mustache section tag by its sigil.
+fn mustache_template_control(action: &str) -> Option<BlockControl> {
+    match action.as_bytes().first() {
+        Some(b'#') => Some(BlockControl::Open),
+        // A bare `{{^}}` is Mustache's else
+        Some(b'^') if action.len() == 1 => Some(BlockControl::Continue),
+        Some(b'^') => Some(BlockControl::Open),
+        Some(b'/') => Some(BlockControl::Close),
+        _ => None,
+    }
+}
+
+/// Classify a raw Jinja `{% ... %}` statement or `{# ... #}` comment.
+fn jinja_template_control(raw: &str) -> BlockControl {
+    if raw.starts_with("{#") {
+        return BlockControl::Standalone;
+    }
+    let statement = raw
+        .get(2..raw.len().saturating_sub(2))
+        .unwrap_or("")
+        .trim_matches(|c: char| c == '-' || c == '+' || c == '~' || c.is_whitespace());
+    let keyword = statement
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or("");
+    match keyword {
+        "if" | "for" | "macro" | "call" | "filter" | "block" | "with" | "autoescape" | "trans"
+        | "materialization" | "snapshot" | "test" | "docs" => BlockControl::Open,
+        // `{% set x %}...{% endset %}` captures a block; `{% set x = 1 %}` does not
+        "set" if !statement.contains('=') => BlockControl::Open,
+        "elif" | "else" | "pluralize" => BlockControl::Continue,
+        k if k.starts_with("end") => BlockControl::Close,
+        _ => BlockControl::Standalone,
+    }
+}
+
+/// Where a token was read from in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte offset of the first byte
+    pub start: usize,
+    /// Byte offset just past the last byte
+    pub end: usize,
+    /// 1-based line of `start`
+    pub line: usize,
+    /// 1-based column of `start`, in characters
+    pub column: usize,
+}
+
+/// A token together with its position in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+}
+
+impl Token<'_> {
+    /// Name of the variant, e.g. `"Keyword"` or `"StringLiteral"`.
+    pub fn kind_name(&self) -> &'static str {
+        match self {
+            Token::Keyword(_) => "Keyword",
+            Token::Identifier(_) => "Identifier",
+            Token::QuotedIdentifier(_) => "QuotedIdentifier",
+            Token::BacktickIdentifier(_) => "BacktickIdentifier",
+            Token::BracketIdentifier(_) => "BracketIdentifier",
+            Token::StringLiteral(_) => "StringLiteral",
+            Token::PrefixedString(_) => "PrefixedString",
+            Token::NumberLiteral(_) => "NumberLiteral",
+            Token::Operator(_) => "Operator",
+            Token::Comma => "Comma",
+            Token::Semicolon => "Semicolon",
+            Token::Dot => "Dot",
+            Token::OpenParen => "OpenParen",
+            Token::CloseParen => "CloseParen",
+            Token::LineComment(_) => "LineComment",
+            Token::BlockComment(_) => "BlockComment",
+            Token::Whitespace(_) => "Whitespace",
+            Token::TemplateVariable(_) => "TemplateVariable",
+            Token::TemplateBlock(_) => "TemplateBlock",
+            Token::TemplateTag(_) => "TemplateTag",
+            Token::Placeholder(_) => "Placeholder",
+            Token::DollarString(_) => "DollarString",
+            Token::ProceduralBlock(_) => "ProceduralBlock",
+            Token::BatchSeparator(_) => "BatchSeparator",
+            Token::Unformatted(_) => "Unformatted",
+            Token::Error(_) => "Error",
+        }
+    }
+
+    /// Classifies template tags that drive control flow (e.g.
+    /// `{{ if .Cond }}`, `{{- end -}}`, `{{#if x}}`, `{{/if}}`, `{% for %}`).
+    /// Returns `None` for tags that substitute a value.
+    pub fn template_control(&self) -> Option<BlockControl> {
+        let content = match self {
+            Token::TemplateVariable(content) => content,
+            Token::TemplateBlock(raw) => return Some(jinja_template_control(raw)),
+            _ => return None,
+        };
+        let action = content
+            .trim()
+            .trim_start_matches('-')
+            .trim_end_matches('-')
+            .trim()
+            .trim_start_matches('~')
+            .trim_end_matches('~')
+            .trim();
+        mustache_template_control(action).or_else(|| go_template_control(action))
+    }
+
+    pub fn is_template_control(&self) -> bool {
+        self.template_control().is_some()
+    }
+
+    /// Returns true for line comments that carry migration tool directives.
+    pub fn is_directive_comment(&self) -> bool {
+        let Token::LineComment(text) = self else {
+            return false;
+        };
+        let text = text.trim_start().to_ascii_lowercase();
+        DIRECTIVE_COMMENT_PREFIXES
+            .iter()
+            .any(|prefix| text.starts_with(prefix))
+            || is_sqitch_header(&text)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_kind_name() {
+        assert_eq!(Token::Keyword(KeywordKind::Select).kind_name(), "Keyword");
+        assert_eq!(Token::Comma.kind_name(), "Comma");
+        assert_eq!(Token::StringLiteral("a").kind_name(), "StringLiteral");
+    }
+
     #[test]
     fn test_lookup_keyword_uppercase() {
         assert_eq!(lookup_keyword("SELECT"), Some(KeywordKind::Select));
@@ -338,12 +607,15 @@ mod tests {
         assert!(KeywordKind::Values.is_clause_starter());
         assert!(KeywordKind::Into.is_clause_starter());
         assert!(KeywordKind::Having.is_clause_starter());
+        assert!(KeywordKind::Qualify.is_clause_starter());
         assert!(KeywordKind::Limit.is_clause_starter());
         assert!(KeywordKind::Offset.is_clause_starter());
         assert!(KeywordKind::Union.is_clause_starter());
         assert!(KeywordKind::UnionAll.is_clause_starter());
         assert!(KeywordKind::Intersect.is_clause_starter());
         assert!(KeywordKind::Except.is_clause_starter());
+        assert!(KeywordKind::Pivot.is_clause_starter());
+        assert!(KeywordKind::Unpivot.is_clause_starter());
         assert!(KeywordKind::Returning.is_clause_starter());
         assert!(KeywordKind::Insert.is_clause_starter());
         assert!(KeywordKind::Update.is_clause_starter());
@@ -388,6 +660,8 @@ mod tests {
     fn test_is_order_modifier() {
         assert!(KeywordKind::OrderBy.is_order_modifier());
         assert!(KeywordKind::GroupBy.is_order_modifier());
+        assert!(KeywordKind::DistributeBy.is_order_modifier());
+        assert!(KeywordKind::SortBy.is_order_modifier());
 
         assert!(!KeywordKind::Order.is_order_modifier());
         assert!(!KeywordKind::Group.is_order_modifier());
@@ -447,4 +721,100 @@ mod tests {
         assert_eq!(KeywordKind::Full.as_str(), "FULL");
         assert_eq!(KeywordKind::Cross.as_str(), "CROSS");
     }
+
+    #[test]
+    fn test_is_directive_comment_liquibase() {
+        assert!(Token::LineComment("liquibase formatted sql").is_directive_comment());
+        assert!(Token::LineComment(" changeset alice:1").is_directive_comment());
+        assert!(Token::LineComment("rollback DROP TABLE users;").is_directive_comment());
+        assert!(Token::LineComment("preconditions onFail:HALT").is_directive_comment());
+        assert!(Token::LineComment("comment: adds users").is_directive_comment());
+    }
+
+    #[test]
+    fn test_is_directive_comment_goose() {
+        assert!(Token::LineComment(" +goose Up").is_directive_comment());
+        assert!(Token::LineComment(" +goose StatementBegin").is_directive_comment());
+        assert!(Token::LineComment(" +goose NO TRANSACTION").is_directive_comment());
+        assert!(Token::LineComment(" migrate:up").is_directive_comment());
+    }
+
+    #[test]
+    fn test_is_directive_comment_sqitch() {
+        assert!(Token::LineComment(" Deploy flipr:users to pg").is_directive_comment());
+        assert!(Token::LineComment(" Revert flipr:users from pg").is_directive_comment());
+        assert!(Token::LineComment(" requires: appschema").is_directive_comment());
+        assert!(!Token::LineComment(" deploy this after review").is_directive_comment());
+    }
+
+    #[test]
+    fn test_is_directive_comment_case_insensitive() {
+        assert!(Token::LineComment("Liquibase Formatted SQL").is_directive_comment());
+        assert!(Token::LineComment("ChangeSet bob:2").is_directive_comment());
+    }
+
+    #[test]
+    fn test_is_template_control() {
+        assert!(Token::TemplateVariable(" if .Cond ").is_template_control());
+        assert!(Token::TemplateVariable("- else -").is_template_control());
+        assert!(Token::TemplateVariable("end").is_template_control());
+        assert!(Token::TemplateVariable(" range $i, $v := .Items ").is_template_control());
+
+        assert!(!Token::TemplateVariable(" .TableName ").is_template_control());
+        assert!(!Token::TemplateVariable(" ending ").is_template_control());
+        assert!(!Token::TemplateVariable("tenant_id").is_template_control());
+        assert!(!Token::Identifier("if").is_template_control());
+    }
+
+    #[test]
+    fn test_template_control_kinds() {
+        let kind = |content| Token::TemplateVariable(content).template_control();
+        assert_eq!(kind(" if .Cond "), Some(BlockControl::Open));
+        assert_eq!(kind(" else if .Other "), Some(BlockControl::Continue));
+        assert_eq!(kind("- end -"), Some(BlockControl::Close));
+        assert_eq!(kind(" break "), Some(BlockControl::Standalone));
+    }
+
+    #[test]
+    fn test_template_control_jinja() {
+        let kind = |raw| Token::TemplateBlock(raw).template_control();
+        assert_eq!(kind("{% if is_incremental() %}"), Some(BlockControl::Open));
+        assert_eq!(kind("{%- for col in cols -%}"), Some(BlockControl::Open));
+        assert_eq!(kind("{% elif x %}"), Some(BlockControl::Continue));
+        assert_eq!(kind("{% else %}"), Some(BlockControl::Continue));
+        assert_eq!(kind("{% endfor %}"), Some(BlockControl::Close));
+        assert_eq!(kind("{%+ endif +%}"), Some(BlockControl::Close));
+        assert_eq!(kind("{% set x = 1 %}"), Some(BlockControl::Standalone));
+        assert_eq!(kind("{% set query %}"), Some(BlockControl::Open));
+        assert_eq!(
+            kind("{% include 'x.sql' %}"),
+            Some(BlockControl::Standalone)
+        );
+        assert_eq!(
+            kind("{# if this is a comment #}"),
+            Some(BlockControl::Standalone)
+        );
+    }
+
+    #[test]
+    fn test_template_control_handlebars() {
+        let kind = |content| Token::TemplateVariable(content).template_control();
+        assert_eq!(kind("#if admin"), Some(BlockControl::Open));
+        assert_eq!(kind("#each items"), Some(BlockControl::Open));
+        assert_eq!(kind("^unless"), Some(BlockControl::Open));
+        assert_eq!(kind("^"), Some(BlockControl::Continue));
+        assert_eq!(kind("else"), Some(BlockControl::Continue));
+        assert_eq!(kind("/if"), Some(BlockControl::Close));
+        assert_eq!(kind("~/each~"), Some(BlockControl::Close));
+        assert_eq!(kind("> partial"), None);
+        assert_eq!(kind("{raw}"), None);
+    }
+
+    #[test]
+    fn test_is_directive_comment_regular_comments() {
+        assert!(!Token::LineComment(" pick columns").is_directive_comment());
+        assert!(!Token::LineComment("changesets are great").is_directive_comment());
+        assert!(!Token::BlockComment("changeset alice:1").is_directive_comment());
+        assert!(!Token::Identifier("changeset").is_directive_comment());
+    }
 }
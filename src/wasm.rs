@@ -1,13 +1,248 @@
-use crate::config::{FormatOptions, FormatStyle};
-use crate::format_sql;
+use crate::config::{Dialect, FormatOptions, FormatStyle};
+use crate::token::Span;
+use crate::{Severity, format_with_diagnostics, lexer};
+use serde::Serialize;
+use wasm_bindgen::JsCast;
 use wasm_bindgen::prelude::*;
 
+#[wasm_bindgen(typescript_custom_section)]
+const OPTIONS_TYPE: &str = r#"
+/** Formatting options, named as `--show-config=json` prints them. Every field is optional. */
+export interface FormatOptions {
+  style?: "basic" | "streamline" | "aligned" | "dataops";
+  dialect?: "generic" | "postgres" | "mysql" | "sqlite" | "tsql" | "bigquery" | "snowflake" | "oracle" | "spark";
+  uppercase?: boolean;
+  /** Spaces per indentation level; `null` keeps the style's own width */
+  indent_width?: number | null;
+  placeholders?: "question" | "dollar" | "colon" | null;
+}
+
+/** A problem found in the input, positioned like a `Token`. */
+export interface Diagnostic {
+  severity: "error" | "warning";
+  message: string;
+  start: number;
+  end: number;
+  line: number;
+  column: number;
+}
+
+export interface FormatResult {
+  output: string;
+  /** Whether `output` differs from the input, a final newline aside */
+  changed: boolean;
+  diagnostics: Diagnostic[];
+}
+
+/** A token of the input, positioned in UTF-16 code units like JavaScript strings. */
+export interface Token {
+  /** The lexer's name for the token, e.g. "Keyword" or "StringLiteral" */
+  kind: string;
+  text: string;
+  start: number;
+  end: number;
+  /** 1-based */
+  line: number;
+  /** 1-based */
+  column: number;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "FormatOptions")]
+    pub type JsFormatOptions;
+
+    #[wasm_bindgen(typescript_type = "FormatResult")]
+    pub type JsFormatResult;
+
+    #[wasm_bindgen(typescript_type = "Token[]")]
+    pub type JsTokens;
+}
+
+#[derive(Serialize)]
+struct JsToken<'a> {
+    kind: &'static str,
+    text: &'a str,
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Serialize)]
+struct JsDiagnostic {
+    severity: &'static str,
+    message: &'static str,
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+#[derive(Serialize)]
+struct JsFormatted {
+    output: String,
+    changed: bool,
+    diagnostics: Vec<JsDiagnostic>,
+}
+
+/// Format `input` with `options`, missing fields taking their defaults,
+/// along with the problems found in it. Throws on unknown names or values
+/// of the wrong type.
 #[wasm_bindgen]
-pub fn format_sql_wasm(input: &str, uppercase: bool, style: &str) -> String {
-    let options = FormatOptions {
-        uppercase,
-        style: FormatStyle::from_name(style),
+pub fn format_sql_wasm(
+    input: &str,
+    options: Option<JsFormatOptions>,
+) -> Result<JsFormatResult, JsError> {
+    let options: FormatOptions = match options {
+        Some(options) => serde_wasm_bindgen::from_value(options.into())?,
+        None => FormatOptions::default(),
     };
+    // Through the builder for its range check on the indent width
+    let mut builder = FormatOptions::builder()
+        .style(options.style)
+        .dialect(options.dialect)
+        .uppercase(options.uppercase);
+    if let Some(width) = options.indent_width {
+        builder = builder.indent_width(width);
+    }
+    if let Some(style) = options.placeholders {
+        builder = builder.placeholders(style);
+    }
+    let options = builder.build().map_err(|e| JsError::new(&e))?;
+
+    let formatted = formatted(input, &options);
+    Ok(serde_wasm_bindgen::to_value(&formatted)?.unchecked_into())
+}
+
+/// The result of formatting `input`, diagnostics positioned in UTF-16 code
+/// units.
+fn formatted(input: &str, options: &FormatOptions) -> JsFormatted {
+    let formatted = format_with_diagnostics(input, options);
+    let diagnostics = formatted
+        .diagnostics
+        .iter()
+        .map(|d| {
+            let (start, end, column) = utf16_span(input, d.span);
+            JsDiagnostic {
+                severity: match d.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+                message: d.message,
+                start,
+                end,
+                line: d.span.line,
+                column,
+            }
+        })
+        .collect();
+    JsFormatted {
+        changed: input.strip_suffix('\n').unwrap_or(input) != formatted.text,
+        output: formatted.text,
+        diagnostics,
+    }
+}
+
+/// Start, end and 1-based column of `span` in UTF-16 code units.
+fn utf16_span(input: &str, span: Span) -> (usize, usize, usize) {
+    let utf16 = |text: &str| text.encode_utf16().count();
+    let start = utf16(&input[..span.start]);
+    let line_start = input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+    (
+        start,
+        start + utf16(&input[span.start..span.end]),
+        utf16(&input[line_start..span.start]) + 1,
+    )
+}
+
+/// The style names `format_sql_wasm` accepts, for a style picker.
+#[wasm_bindgen]
+pub fn style_names() -> Vec<String> {
+    FormatStyle::all().iter().map(|s| s.to_string()).collect()
+}
+
+/// The lexer's tokens for `input` in `dialect` (generic by default), for
+/// syntax highlighting. Throws on an unknown dialect name.
+#[wasm_bindgen]
+pub fn tokenize_wasm(input: &str, dialect: Option<String>) -> Result<JsTokens, JsError> {
+    let dialect: Dialect = match dialect {
+        Some(name) => name.parse().map_err(|e: String| JsError::new(&e))?,
+        None => Dialect::default(),
+    };
+    let tokens = utf16_tokens(input, dialect);
+    Ok(serde_wasm_bindgen::to_value(&tokens)?.unchecked_into())
+}
+
+/// Tokens with their offsets and columns counted in UTF-16 code units.
+fn utf16_tokens(input: &str, dialect: Dialect) -> Vec<JsToken<'_>> {
+    let mut offset = 0;
+    let mut line_start = 0;
+    lexer::tokenize_spanned(input, dialect)
+        .into_iter()
+        .map(|spanned| {
+            let span = spanned.span;
+            let text = &input[span.start..span.end];
+            let start = offset;
+            offset += text.encode_utf16().count();
+            let token = JsToken {
+                kind: spanned.token.kind_name(),
+                text,
+                start,
+                end: offset,
+                line: span.line,
+                column: start - line_start + 1,
+            };
+            if let Some(i) = text.rfind('\n') {
+                line_start = start + text[..=i].encode_utf16().count();
+            }
+            token
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf16_positions() {
+        let tokens = utf16_tokens("select '😀',\n  x", Dialect::Generic);
+        let positions: Vec<_> = tokens
+            .iter()
+            .map(|t| (t.kind, t.start, t.end, t.line, t.column))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![
+                ("Keyword", 0, 6, 1, 1),
+                ("Whitespace", 6, 7, 1, 7),
+                ("StringLiteral", 7, 11, 1, 8),
+                ("Comma", 11, 12, 1, 12),
+                ("Whitespace", 12, 15, 1, 13),
+                ("Identifier", 15, 16, 2, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_formatted_with_diagnostics() {
+        let result = formatted("select '😀', 'x", &FormatOptions::default());
+        assert!(result.changed);
+        assert_eq!(result.output, "SELECT\n    '😀',\n    'x'");
+        let diagnostics: Vec<_> = result
+            .diagnostics
+            .iter()
+            .map(|d| (d.severity, d.message, d.start, d.end, d.line, d.column))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![("error", "unclosed string literal", 13, 15, 1, 14)]
+        );
 
-    format_sql(input, &options)
+        let result = formatted("SELECT\n    1\n", &FormatOptions::default());
+        assert!(!result.changed);
+        assert!(result.diagnostics.is_empty());
+    }
 }
@@ -1,11 +1,13 @@
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, Instant};
 
 use assert_cmd::Command;
+use assert_cmd::cargo::cargo_bin_cmd;
 use predicates::prelude::*;
 
 fn cmd() -> Command {
-    Command::cargo_bin("rs-sql-indent").unwrap()
+    cargo_bin_cmd!("rs-sql-indent")
 }
 
 #[test]
@@ -188,23 +190,1145 @@ FROM
         );
 }
 
+#[test]
+fn test_compare_styles() {
+    cmd()
+        .args(["--compare-styles", "--lowercase"])
+        .write_stdin("select a, b from t")
+        .assert()
+        .success()
+        .stdout(
+            "-- style: basic\nselect\n    a,\n    b\nfrom\n    t\n\n\
+             -- style: streamline\nselect\n  a,\n  b\nfrom\n  t\n\n\
+             -- style: aligned\nselect a\n       , b\n  from t\n\n\
+             -- style: dataops\nselect\n    a\n    , b\nfrom\n    t\n",
+        );
+}
+
 #[test]
 fn test_uppercase_flag_rejected() {
     cmd()
-        .arg("--uppercase")
+        .arg("--uppercase")
+        .write_stdin("select 1")
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_style_invalid_is_error() {
+    cmd()
+        .args(["--style", "foobar"])
+        .write_stdin("select something from somewhere")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid value"));
+}
+
+#[test]
+fn test_dialect_flag() {
+    cmd()
+        .args(["--dialect", "tsql"])
+        .write_stdin("select top 5 id from t")
+        .assert()
+        .success()
+        .stdout("SELECT\n    TOP 5 id\nFROM\n    t\n");
+}
+
+#[test]
+fn test_dialect_flag_overrides_sqlfluff() {
+    cmd()
+        .args([
+            "--sqlfluff=tests/fixtures/team.sqlfluff",
+            "--dialect",
+            "bigquery",
+        ])
+        .write_stdin("select `a b` from t")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("`a b`"));
+}
+
+#[test]
+fn test_sqlfluff_config() {
+    cmd()
+        .arg("--sqlfluff=tests/fixtures/team.sqlfluff")
+        .write_stdin("select id, name from rockets")
+        .assert()
+        .success()
+        .stdout(
+            r#"select
+  id
+  , name
+from
+  rockets
+"#,
+        );
+}
+
+#[test]
+fn test_sqlfluff_config_flags_take_precedence() {
+    cmd()
+        .args([
+            "--sqlfluff=tests/fixtures/team.sqlfluff",
+            "--style",
+            "basic",
+        ])
+        .write_stdin("select id, name from rockets")
+        .assert()
+        .success()
+        .stdout(
+            r#"select
+  id,
+  name
+from
+  rockets
+"#,
+        );
+}
+
+#[test]
+fn test_show_config() {
+    cmd()
+        .args([
+            "--sqlfluff=tests/fixtures/team.sqlfluff",
+            "--style",
+            "aligned",
+        ])
+        .arg("--show-config")
+        .assert()
+        .success()
+        .stdout(
+            "style = \"aligned\"\ndialect = \"generic\"\nuppercase = false\nindent_width = 2\n\
+             # placeholders: kept as written\n",
+        );
+}
+
+#[test]
+fn test_config_files_nearest_wins() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-config-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    let package = root.join("packages/app");
+    fs::create_dir_all(&package).unwrap();
+    fs::create_dir_all(root.join(".git")).unwrap();
+    fs::write(
+        root.join("sql-indent.toml"),
+        "style = \"aligned\"\nuppercase = false\n",
+    )
+    .unwrap();
+    fs::write(package.join("sql-indent.toml"), "uppercase = true\n").unwrap();
+
+    cmd()
+        .arg("--show-config")
+        .current_dir(&package)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "style = \"aligned\"\ndialect = \"generic\"\nuppercase = true\n",
+        ));
+    cmd()
+        .args(["--show-config", "--style", "dataops"])
+        .current_dir(&package)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("style = \"dataops\""));
+
+    fs::write(package.join("sql-indent.toml"), "indent = 2\n").unwrap();
+    cmd()
+        .write_stdin("select 1")
+        .current_dir(&package)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "sql-indent.toml: line 1: unknown key `indent`",
+        ));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_config_files_found_from_each_input() {
+    let root = std::env::temp_dir().join(format!(
+        "rs-sql-indent-config-inputs-{}",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&root);
+    let nested = root.join("repo/reports");
+    fs::create_dir_all(&nested).unwrap();
+    fs::create_dir_all(root.join("repo/.git")).unwrap();
+    fs::write(
+        root.join("repo/sql-indent.toml"),
+        "uppercase = false
+",
+    )
+    .unwrap();
+    fs::write(nested.join("sql-indent.toml"), "style = \"streamline\"\n").unwrap();
+    fs::write(root.join("repo/a.sql"), "select a from t\n").unwrap();
+    fs::write(nested.join("b.sql"), "select b from t\n").unwrap();
+    fs::write(nested.join("c.sql"), "select c from t\n").unwrap();
+
+    cmd()
+        .args(["--write", "repo"])
+        .current_dir(&root)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(root.join("repo/a.sql")).unwrap(),
+        "select\n    a\nfrom\n    t\n"
+    );
+    for name in ["b", "c"] {
+        assert_eq!(
+            fs::read_to_string(nested.join(format!("{}.sql", name))).unwrap(),
+            format!("select\n  {}\nfrom\n  t\n", name)
+        );
+    }
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_environment_defaults() {
+    cmd()
+        .arg("--show-config")
+        .env("RS_SQL_INDENT_STYLE", "streamline")
+        .env("RS_SQL_INDENT_CASE", "lower")
+        .env("RS_SQL_INDENT_DIALECT", "tsql")
+        .args(["--dialect", "oracle"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "style = \"streamline\"\ndialect = \"oracle\"\nuppercase = false\n",
+        ));
+    cmd()
+        .env("RS_SQL_INDENT_INDENT_WIDTH", "two")
+        .write_stdin("select 1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "RS_SQL_INDENT_INDENT_WIDTH=two: expected a number",
+        ));
+}
+
+#[test]
+fn test_file_directive() {
+    cmd()
+        .write_stdin("-- sql-indent: style=streamline, case=lower, indent=2\nSELECT a FROM t")
+        .assert()
+        .success()
+        .stdout("-- sql-indent: style=streamline, case=lower, indent=2\nselect\n  a\nfrom\n  t\n");
+    cmd()
+        .args(["--style", "aligned"])
+        .write_stdin("-- sql-indent: case=title\nselect 1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "sql-indent directive: `case` must be upper or lower, got title",
+        ));
+}
+
+#[test]
+fn test_show_config_json() {
+    cmd()
+        .arg("--show-config=json")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("{\"style\":\"basic\""));
+}
+
+#[test]
+fn test_sqlfluff_config_missing_file() {
+    cmd()
+        .arg("--sqlfluff=tests/fixtures/does-not-exist.sqlfluff")
+        .write_stdin("select 1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error reading"));
+}
+
+#[test]
+fn test_file_argument() {
+    cmd()
+        .arg("tests/fixtures/basic__basic_select.sql")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("SELECT\n    id,\n"));
+}
+
+#[test]
+fn test_file_argument_missing() {
+    cmd()
+        .arg("tests/fixtures/does-not-exist.sql")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Error reading"));
+}
+
+#[test]
+fn test_files_from_stdin() {
+    cmd()
+        .args(["--files-from", "-"])
+        .write_stdin("tests/fixtures/basic__basic_select.sql\n\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with("SELECT\n    id,\n"));
+}
+
+#[test]
+fn test_files_from_empty_list() {
+    cmd()
+        .args(["--files-from", "-"])
+        .write_stdin("")
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_reporter_json() {
+    cmd()
+        .args(["--reporter", "json"])
+        .write_stdin("select 1\n")
+        .assert()
+        .success()
+        .stdout(
+            "{\"files\":[{\"path\":\"<stdin>\",\"changed\":true,\"bytes_in\":9,\
+             \"bytes_out\":13,\"warnings\":[],\"error\":null}],\"changed\":1}\n",
+        );
+}
+
+#[test]
+fn test_output_overwrites_input_file() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-output-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let file = root.join("q.sql");
+    fs::write(&file, "select id from t\n").unwrap();
+
+    cmd()
+        .arg(&file)
+        .arg("-o")
+        .arg(&file)
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "SELECT\n    id\nFROM\n    t\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_output_rejects_multiple_inputs() {
+    cmd()
+        .args(["-o", "out.sql"])
+        .arg("tests/fixtures/basic__basic_select.sql")
+        .arg("tests/fixtures/basic__basic_select.sql")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--output takes a single input"));
+}
+
+#[test]
+fn test_fix() {
+    cmd()
+        .arg("--fix")
+        .write_stdin("select 1 where a != b;;")
+        .assert()
+        .success()
+        .stdout("SELECT\n    1\nWHERE\n    a <> b;\n");
+}
+
+#[test]
+fn test_verify_passes() {
+    cmd()
+        .args(["--verify", "--fix"])
+        .write_stdin("select a from t where a != 1")
+        .assert()
+        .success()
+        .stdout("SELECT\n    a\nFROM\n    t\nWHERE\n    a <> 1;\n");
+}
+
+#[test]
+fn test_verify_typed_literals() {
+    cmd()
+        .arg("--verify")
+        .write_stdin("select a from t where d > date '2024-01-01'")
+        .assert()
+        .success()
+        .stdout("SELECT\n    a\nFROM\n    t\nWHERE\n    d > DATE '2024-01-01'\n");
+}
+
+#[test]
+fn test_verify_exception_block() {
+    cmd()
+        .args(["--verify", "--dialect", "bigquery"])
+        .write_stdin("begin\n  select 1;\nexception when error then\n  select 2;\nend")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("EXCEPTION WHEN ERROR THEN"));
+}
+
+#[test]
+fn test_verify_try_catch() {
+    cmd()
+        .args(["--verify", "--dialect", "tsql"])
+        .write_stdin("begin try\n  select 1;\nend try\nbegin catch\n  select 2;\nend catch")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("BEGIN TRY").and(predicate::str::contains("END CATCH")));
+}
+
+#[test]
+fn test_diff_stdin() {
+    cmd()
+        .arg("--diff")
+        .write_stdin("select id\nfrom rockets\n")
+        .assert()
+        .success()
+        .stdout(
+            r#"--- <stdin>
++++ <stdin>
+@@ -1,2 +1,4 @@
+-select id
+-from rockets
++SELECT
++    id
++FROM
++    rockets
+"#,
+        );
+}
+
+#[test]
+fn test_diff_already_formatted_is_empty() {
+    cmd()
+        .arg("--diff")
+        .write_stdin("SELECT\n    id\nFROM\n    rockets\n")
+        .assert()
+        .success()
+        .stdout("");
+}
+
+#[test]
+fn test_color_always() {
+    cmd()
+        .args(["--color", "always"])
+        .write_stdin("select 1")
+        .assert()
+        .success()
+        .stdout("\x1b[1;34mSELECT\x1b[0m\n    \x1b[33m1\x1b[0m\n");
+}
+
+#[test]
+fn test_split_subcommand() {
+    let out_dir = std::env::temp_dir().join(format!("rs-sql-indent-split-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&out_dir);
+
+    cmd()
+        .arg("split")
+        .arg("--format")
+        .arg("-d")
+        .arg(&out_dir)
+        .write_stdin("select 1; insert into t values (';');\n-- done")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("statement_003.sql"));
+
+    let read = |name: &str| fs::read_to_string(out_dir.join(name)).unwrap();
+    assert_eq!(read("statement_001.sql"), "SELECT\n    1;\n");
+    assert_eq!(
+        read("statement_002.sql"),
+        "INSERT\nINTO\n    t\nVALUES\n     (';');\n"
+    );
+    assert_eq!(read("statement_003.sql"), "-- done\n");
+    fs::remove_dir_all(&out_dir).unwrap();
+}
+
+#[test]
+fn test_directory_with_excludes() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-walk-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["models", "vendor/pkg", "models/generated"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    fs::write(root.join("models/a.sql"), "select a\n").unwrap();
+    fs::write(root.join("models/notes.txt"), "select txt\n").unwrap();
+    fs::write(root.join("models/b.gen.sql"), "select gen\n").unwrap();
+    fs::write(root.join("models/generated/c.sql"), "select c\n").unwrap();
+    fs::write(root.join("vendor/pkg/d.sql"), "select d\n").unwrap();
+
+    cmd()
+        .arg(&root)
+        .args(["--exclude", "vendor/**", "--exclude", "*.gen.sql"])
+        .args(["--exclude", "models/generated"])
+        .assert()
+        .success()
+        .stdout("SELECT\n    a\n");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_directory_with_ignore_file() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-ignore-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("dumps")).unwrap();
+    fs::create_dir_all(root.join("models")).unwrap();
+    fs::write(
+        root.join(".sqlindentignore"),
+        "# fixtures\ndumps/\n*.gen.sql\n",
+    )
+    .unwrap();
+    fs::write(root.join("models/.sqlindentignore"), "!keep.gen.sql\n").unwrap();
+    fs::write(root.join("dumps/full.sql"), "select dump\n").unwrap();
+    fs::write(root.join("models/a.gen.sql"), "select gen\n").unwrap();
+    fs::write(root.join("models/keep.gen.sql"), "select keep\n").unwrap();
+
+    cmd()
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout("SELECT\n    keep\n");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_embedded_go_directory() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-embedded-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("q.sql"), "select ignored\n").unwrap();
+    fs::write(
+        root.join("repo.go"),
+        "package repo\n\nconst q = `\n\tselect id from users\n`\n",
+    )
+    .unwrap();
+
+    cmd()
+        .args(["--embedded", "go", "--write"])
+        .arg(&root)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(root.join("repo.go")).unwrap(),
+        "package repo\n\nconst q = `\n\tSELECT\n\t    id\n\tFROM\n\t    users\n`\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("q.sql")).unwrap(),
+        "select ignored\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_write_rewrites_files() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-write-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("q.sql"), "select id from t\n").unwrap();
+
+    cmd()
+        .arg("--write")
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout("");
+    assert_eq!(
+        fs::read_to_string(root.join("q.sql")).unwrap(),
+        "SELECT\n    id\nFROM\n    t\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_write_keeps_encoding() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-encoding-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let utf16 = |text: &str| -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        bytes
+    };
+    fs::write(root.join("a.sql"), utf16("select 'é' from t\n")).unwrap();
+    fs::write(root.join("b.sql"), b"select 'caf\xe9' from t\n").unwrap();
+
+    cmd().arg("--write").arg(&root).assert().success();
+    assert_eq!(
+        fs::read(root.join("a.sql")).unwrap(),
+        utf16("SELECT\n    'é'\nFROM\n    t\n")
+    );
+    assert_eq!(
+        fs::read(root.join("b.sql")).unwrap(),
+        b"SELECT\n    'caf\xe9'\nFROM\n    t\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_write_keeps_bom_and_crlf() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-crlf-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let path = root.join("q.sql");
+    fs::write(&path, "\u{FEFF}select id\r\nfrom t\r\n").unwrap();
+
+    cmd().arg("--write").arg(&path).assert().success();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "\u{FEFF}SELECT\r\n    id\r\nFROM\r\n    t\r\n"
+    );
+    cmd().arg("-l").arg(&path).assert().success().stdout("");
+
+    cmd()
+        .args(["--write", "--line-endings", "lf", "--bom", "never"])
+        .arg(&path)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "SELECT\n    id\nFROM\n    t\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_encoding_stdin() {
+    cmd()
+        .write_stdin(&b"select 'caf\xe9'"[..])
+        .assert()
+        .success()
+        .stdout("SELECT\n    'café'\n");
+    cmd()
+        .args(["--encoding", "utf-8"])
+        .write_stdin(&b"select 'caf\xe9'"[..])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "line 1: not valid utf-8 at byte 11",
+        ));
+}
+
+#[test]
+fn test_summary_after_batch() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-summary-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.sql"), "select 1\n").unwrap();
+    fs::write(root.join("b.sql"), "SELECT\n    1\n").unwrap();
+
+    cmd()
+        .arg(&root)
+        .assert()
+        .success()
+        .stderr("2 files checked, 1 reformatted\n");
+    cmd()
+        .arg("--no-summary")
+        .arg(&root)
+        .assert()
+        .success()
+        .stderr("");
+    cmd().arg(root.join("a.sql")).assert().success().stderr("");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_verbose_quiet_progress() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-verbose-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.sql"), "select 1\n").unwrap();
+    fs::write(root.join("b.sql"), "SELECT\n    1\n").unwrap();
+    let a = root.join("a.sql").display().to_string();
+    let b = root.join("b.sql").display().to_string();
+
+    cmd()
+        .args(["-l", "--verbose"])
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(format!("reformatted {}\nunchanged {}\n", a, b));
+    cmd()
+        .args(["--quiet", "--progress"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stderr("");
+    cmd()
+        .args(["-l", "--progress"])
+        .arg(&root)
+        .assert()
+        .stderr(predicate::str::contains("] 2/2 files\r\x1b[K"));
+    cmd()
+        .args(["-q", "-v"])
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot be used with"));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_errors_listed_after_batch() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-errors-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.sql"), "select 'x\n").unwrap();
+    fs::write(root.join("b.sql"), b"select '\xff'\n").unwrap();
+    fs::write(root.join("c.sql"), "select 1\n").unwrap();
+
+    cmd()
+        .args(["--write", "--strict", "--encoding", "utf-8"])
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "Failed to format 2 files:\n  {}: malformed SQL: 1 problem reported above\n  \
+             {}: not valid utf-8 at byte 8",
+            root.join("a.sql").display(),
+            root.join("b.sql").display()
+        )))
+        .stderr(predicate::str::ends_with(
+            "3 files checked, 1 reformatted, 2 skipped (errors above)\n",
+        ));
+    assert_eq!(
+        fs::read_to_string(root.join("c.sql")).unwrap(),
+        "SELECT\n    1\n"
+    );
+
+    fs::write(root.join("c.sql"), "select 1\n").unwrap();
+    cmd()
+        .args(["--write", "--strict", "--fail-fast"])
+        .arg(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::ends_with(
+            "1 file checked, 0 reformatted, 1 skipped (errors above)\n",
+        ));
+    assert_eq!(
+        fs::read_to_string(root.join("c.sql")).unwrap(),
+        "select 1\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_list_different() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-list-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.sql"), "select 1\n").unwrap();
+    fs::write(root.join("b.sql"), "SELECT\n    1\n").unwrap();
+
+    cmd()
+        .arg("-l")
+        .arg(&root)
+        .assert()
+        .failure()
+        .stdout(format!("{}\n", root.join("a.sql").display()))
+        .stderr("");
+    cmd()
+        .arg("--list-different")
+        .arg(root.join("b.sql"))
+        .assert()
+        .success()
+        .stdout("");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_statement_index() {
+    cmd()
+        .args(["--statement-index", "2"])
+        .write_stdin("select 1;\nselect ';' from t;\nselect 3;")
+        .assert()
+        .success()
+        .stdout("SELECT\n    ';'\nFROM\n    t;\n");
+    cmd()
+        .args(["--statement-index", "4"])
+        .write_stdin("select 1; select 2")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "no statement 4: the input has 2 statements",
+        ));
+}
+
+#[test]
+fn test_range() {
+    cmd()
+        .args(["--range", "12:12"])
+        .write_stdin("select 1;\nselect a from t;\nselect   3;\n")
+        .assert()
+        .success()
+        .stdout("select 1;\nSELECT\n    a\nFROM\n    t;\nselect   3;\n");
+    cmd()
+        .args(["--range", "5:2"])
         .write_stdin("select 1")
         .assert()
-        .failure();
+        .failure()
+        .stderr(predicate::str::contains("start 5 is after end 2"));
 }
 
 #[test]
-fn test_style_invalid_is_error() {
+fn test_tolerant() {
     cmd()
-        .args(["--style", "foobar"])
-        .write_stdin("select something from somewhere")
+        .arg("--tolerant")
+        .write_stdin("select  f(a from t;\nselect b from u;\n")
+        .assert()
+        .success()
+        .stdout("select  f(a from t;\n\nSELECT\n    b\nFROM\n    u;\n")
+        .stderr(predicate::str::contains("warning: unclosed parenthesis"));
+}
+
+#[test]
+fn test_caret_diagnostic() {
+    cmd()
+        .arg("--strict")
+        .write_stdin("select 1;\nselect a\nfrom t\nwhere b = 'x\n")
         .assert()
         .failure()
-        .stderr(predicate::str::contains("invalid value"));
+        .stdout("SELECT\n    1;\n")
+        .stderr(
+            "error: unclosed string literal\n --> <stdin>:4:11\n  |\n4 | where b = 'x\n  |           ^\n\
+             Error: <stdin>: malformed SQL: 1 problem reported above\n",
+        );
+    cmd()
+        .args(["--diff", "--strict"])
+        .write_stdin("select (a from t")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::starts_with(
+            "error: unclosed parenthesis\n --> <stdin>:1:8\n",
+        ));
+}
+
+#[test]
+fn test_malformed_input_warns() {
+    cmd()
+        .write_stdin("select (a from t")
+        .assert()
+        .success()
+        .stdout("SELECT\n     (a FROM t\n")
+        .stderr(
+            "warning: unclosed parenthesis\n --> <stdin>:1:8\n  |\n1 | select (a from t\n  |        ^\n",
+        );
+}
+
+#[test]
+fn test_write_backup() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-backup-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    fs::write(root.join("a.sql"), "select id from t\n").unwrap();
+    fs::write(root.join("b.sql"), "SELECT\n    1\n").unwrap();
+
+    cmd()
+        .args(["--write", "--backup"])
+        .arg(&root)
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(root.join("a.sql.bak")).unwrap(),
+        "select id from t\n"
+    );
+    assert!(!root.join("b.sql.bak").exists());
+
+    fs::write(root.join("a.sql"), "select 2\n").unwrap();
+    cmd()
+        .args(["--write", "--backup=.orig"])
+        .arg(root.join("a.sql"))
+        .assert()
+        .success();
+    assert_eq!(
+        fs::read_to_string(root.join("a.sql.orig")).unwrap(),
+        "select 2\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_backup_requires_write() {
+    cmd()
+        .arg("--backup")
+        .write_stdin("select 1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--write"));
+}
+
+/// Run git in `root` with an identity of its own, so commits work without
+/// a user configuration, returning its output.
+fn git(root: &Path, args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(["-c", "commit.gpgsign=false"])
+        .args(args)
+        .current_dir(root)
+        .output()
+        .expect("git is needed for the --staged and --changed-since tests");
+    assert!(output.status.success(), "git {:?}", args);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_staged_rewrites_index() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-staged-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let git = |args: &[&str]| git(&root, args);
+    git(&["init", "-q"]);
+    fs::write(root.join("a.sql"), "select a from t\n").unwrap();
+    fs::write(root.join("b.sql"), "select b from t\n").unwrap();
+    fs::write(root.join("c.sql"), "select c from t\n").unwrap();
+    git(&["add", "a.sql", "b.sql"]);
+    // b.sql has unstaged changes on top of the staged copy
+    fs::write(root.join("b.sql"), "select b, b2 from t\n").unwrap();
+
+    cmd().arg("--staged").current_dir(&root).assert().success();
+
+    assert_eq!(git(&["show", ":a.sql"]), "SELECT\n    a\nFROM\n    t\n");
+    assert_eq!(
+        fs::read_to_string(root.join("a.sql")).unwrap(),
+        "SELECT\n    a\nFROM\n    t\n"
+    );
+    assert_eq!(git(&["show", ":b.sql"]), "SELECT\n    b\nFROM\n    t\n");
+    assert_eq!(
+        fs::read_to_string(root.join("b.sql")).unwrap(),
+        "select b, b2 from t\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("c.sql")).unwrap(),
+        "select c from t\n"
+    );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_changed_since_formats_touched_statements() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-changed-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let git = |args: &[&str]| {
+        git(&root, args);
+    };
+    git(&["init", "-q"]);
+    fs::write(
+        root.join("a.sql"),
+        "select a from t;
+select b from t;
+",
+    )
+    .unwrap();
+    git(&["add", "a.sql"]);
+    git(&["commit", "-q", "-m", "legacy"]);
+    fs::write(
+        root.join("a.sql"),
+        "select a from t;
+select b, c from t;
+",
+    )
+    .unwrap();
+    fs::write(root.join("new.sql"), "select d from t\n").unwrap();
+
+    cmd()
+        .args(["--changed-since", "HEAD", "--write", "."])
+        .current_dir(&root)
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(root.join("a.sql")).unwrap(),
+        "select a from t;\nSELECT\n    b,\n    c\nFROM\n    t;\n"
+    );
+    assert_eq!(
+        fs::read_to_string(root.join("new.sql")).unwrap(),
+        "SELECT\n    d\nFROM\n    t\n"
+    );
+
+    cmd()
+        .args(["--changed-since", "no-such-ref", "a.sql"])
+        .current_dir(&root)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "unknown git revision `no-such-ref`",
+        ));
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_write_requires_files() {
+    cmd()
+        .arg("--write")
+        .write_stdin("select 1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--write and --watch need files"));
+}
+
+#[test]
+fn test_watch_reformats_saved_files() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-watch-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let file = root.join("q.sql");
+    fs::write(&file, "select 1\n").unwrap();
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("rs-sql-indent"))
+        .arg("--watch")
+        .arg(&root)
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    // Untouched files are left alone until they are saved
+    std::thread::sleep(Duration::from_millis(800));
+    assert_eq!(fs::read_to_string(&file).unwrap(), "select 1\n");
+
+    fs::write(&file, "select id from t\n").unwrap();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut contents = String::new();
+    while Instant::now() < deadline {
+        contents = fs::read_to_string(&file).unwrap();
+        if contents.starts_with("SELECT") {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(contents, "SELECT\n    id\nFROM\n    t\n");
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_stdin_streams_statements() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::Stdio;
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("rs-sql-indent"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = child.stdout.take().unwrap();
+    let (sender, receiver) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            sender.send(line.unwrap()).unwrap();
+        }
+    });
+
+    // The first statement is printed while stdin is still open
+    stdin.write_all(b"select a from t;\nselect b\n").unwrap();
+    stdin.flush().unwrap();
+    let mut first = Vec::new();
+    while first.len() < 4 {
+        first.push(receiver.recv_timeout(Duration::from_secs(10)).unwrap());
+    }
+    assert_eq!(first, ["SELECT", "    a", "FROM", "    t;"]);
+
+    stdin.write_all(b"from u;\n").unwrap();
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+    let rest: Vec<String> = receiver.iter().collect();
+    assert_eq!(rest, ["", "SELECT", "    b", "FROM", "    u;"]);
+}
+
+#[test]
+fn test_explain_tokens_subcommand() {
+    cmd()
+        .args(["explain-tokens", "--style", "aligned"])
+        .write_stdin("select a from t where b = 1")
+        .assert()
+        .success()
+        .stdout("SELECT    0 | SELECT a\nFROM      0 |   FROM t\nWHERE     0 |  WHERE b = 1\n");
+}
+
+#[test]
+fn test_doctor_subcommand() {
+    cmd()
+        .arg("doctor")
+        .write_stdin("select `a`\nfrom t where x = 'y")
+        .assert()
+        .success()
+        .stdout(
+            "<stdin>:1:8: backtick-quoted identifiers are not recognized by the generic dialect; \
+             try --dialect mysql, --dialect bigquery or --dialect spark\n\
+             <stdin>:2:18: unclosed string literal\n",
+        );
+    cmd()
+        .args(["doctor", "--dialect", "bigquery"])
+        .write_stdin("select `a` from t")
+        .assert()
+        .success()
+        .stdout("<stdin>: no problems found\n");
+}
+
+#[test]
+fn test_preset() {
+    cmd()
+        .args(["--preset", "compact"])
+        .write_stdin("select a, b from t")
+        .assert()
+        .success()
+        .stdout("SELECT\n  a,\n  b\nFROM\n  t\n");
+    cmd()
+        .args(["--preset", "sqlfluff", "--style", "dataops"])
+        .write_stdin("select a, b from t")
+        .assert()
+        .success()
+        .stdout("select\n    a\n    , b\nfrom\n    t\n");
+    cmd()
+        .args(["--preset", "prettier"])
+        .write_stdin("select 1")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("pgformatter"));
+}
+
+#[test]
+fn test_minify_subcommand() {
+    cmd()
+        .args(["minify", "--lowercase"])
+        .write_stdin("SELECT\n    id,\n    name -- display\nFROM\n    users\n")
+        .assert()
+        .success()
+        .stdout("select id,name from users\n");
+}
+
+#[test]
+fn test_stats_subcommand() {
+    cmd()
+        .arg("stats")
+        .write_stdin("select * from a join b on a.id = b.id; update a set x = (select 1);")
+        .assert()
+        .success()
+        .stdout(
+            "<stdin>\n  statements: 2 (SELECT 1, UPDATE 1)\n  tables: a, b\n  max nesting depth: 1\n",
+        );
+}
+
+#[test]
+fn test_tokenize_subcommand() {
+    cmd()
+        .args(["tokenize", "--skip-whitespace"])
+        .write_stdin("select 1")
+        .assert()
+        .success()
+        .stdout(
+            "{\"kind\":\"Keyword\",\"text\":\"select\",\"offset\":0,\"line\":1,\"column\":1}\n\
+             {\"kind\":\"NumberLiteral\",\"text\":\"1\",\"offset\":7,\"line\":1,\"column\":8}\n",
+        );
+}
+
+#[test]
+fn test_completions_subcommand() {
+    cmd()
+        .args(["completions", "bash"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("--style").and(predicate::str::contains("streamline")));
 }
 
 #[test]
@@ -235,7 +1359,8 @@ fn test_fixtures() {
         let expected = fs::read_to_string(&expected_path)
             .unwrap_or_else(|e| panic!("failed to read {}: {}", expected_path.display(), e));
 
-        // Parse naming convention: {style}__{description} or {style}__{description}__lower
+        // Parse naming convention: {style}__{description}, optionally followed by
+        // __lower or by a dialect name such as __mysql
         let parts: Vec<&str> = stem.splitn(3, "__").collect();
         assert!(
             parts.len() >= 2,
@@ -244,12 +1369,18 @@ fn test_fixtures() {
         );
 
         let style = parts[0];
-        let lowercase = parts.last().is_some_and(|&p| p == "lower");
+        let variant = parts.get(2).copied();
 
-        let mut cmd = Command::cargo_bin("rs-sql-indent").unwrap();
+        let mut cmd = cargo_bin_cmd!("rs-sql-indent");
         cmd.args(["--style", style]);
-        if lowercase {
-            cmd.arg("--lowercase");
+        match variant {
+            Some("lower") => {
+                cmd.arg("--lowercase");
+            }
+            Some(dialect) => {
+                cmd.args(["--dialect", dialect]);
+            }
+            None => {}
         }
 
         // CLI uses println! which adds trailing newline
@@ -261,3 +1392,113 @@ fn test_fixtures() {
             .stdout(predicates::ord::eq(expected_stdout).normalize());
     }
 }
+
+#[test]
+fn test_placeholders() {
+    cmd()
+        .args(["--placeholders", "dollar", "--style", "streamline"])
+        .write_stdin("select * from t where a = :a and b = ? or c = :a")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("a = $1").and(predicate::str::contains("b = $2")))
+        .stdout(predicate::str::contains("c = $1"));
+}
+
+#[test]
+fn test_daemon() {
+    cmd()
+        .args(["--daemon", "--lowercase"])
+        .write_stdin(
+            "{\"id\": 1, \"sql\": \"SELECT a FROM t\"}\n\
+             {\"id\": 2, \"sql\": \"select 1\", \"options\": {\"uppercase\": true}}\n",
+        )
+        .assert()
+        .success()
+        .stdout(
+            "{\"id\":1,\"output\":\"select\\n    a\\nfrom\\n    t\",\"changed\":true,\"diagnostics\":[]}\n\
+             {\"id\":2,\"output\":\"SELECT\\n    1\",\"changed\":true,\"diagnostics\":[]}\n",
+        );
+}
+
+#[test]
+fn test_daemon_deep_nesting() {
+    cmd()
+        .arg("--daemon")
+        .write_stdin(format!("{{\"sql\": {}\n", "[".repeat(200_000)))
+        .assert()
+        .success()
+        .stdout("{\"error\":\"nesting too deep at byte 135\"}\n");
+}
+
+#[test]
+fn test_format_html() {
+    cmd()
+        .args(["--format", "html", "--style", "streamline"])
+        .write_stdin("select a from t where b < 1")
+        .assert()
+        .success()
+        .stdout(
+            "<pre class=\"sql\"><code><span class=\"kw\">SELECT</span>\n  a\n\
+             <span class=\"kw\">FROM</span>\n  t\n<span class=\"kw\">WHERE</span>\n  \
+             b &lt; <span class=\"num\">1</span></code></pre>\n",
+        );
+}
+
+#[test]
+fn test_dbt_project() {
+    let root = std::env::temp_dir().join(format!("rs-sql-indent-dbt-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&root);
+    for dir in ["models/staging", "macros", "target"] {
+        fs::create_dir_all(root.join(dir)).unwrap();
+    }
+    fs::write(
+        root.join("dbt_project.yml"),
+        "name: shop\nmodel-paths: [\"models\"]\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("models/staging/orders.sql"),
+        "{{ config(materialized='view') }}\n\nselect id from {{ source('shop', 'orders') }}\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("macros/m.sql"),
+        "{% macro m() %}1{% endmacro %}\n",
+    )
+    .unwrap();
+    fs::write(root.join("target/compiled.sql"), "select compiled\n").unwrap();
+
+    cmd()
+        .args(["--dbt", "--lowercase"])
+        .arg(&root)
+        .assert()
+        .success()
+        .stdout(
+            "{{ config(materialized='view') }}\n\n\
+             select\n    id\nfrom\n    {{ source('shop', 'orders') }}\n",
+        );
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_embedded_yaml_keys() {
+    cmd()
+        .args(["--embedded", "yaml", "--sql-key", "*.sql", "--style", "streamline"])
+        .write_stdin("data:\n  report.sql: \"select a from t\"\n  query: select b from u\n")
+        .assert()
+        .success()
+        .stdout("data:\n  report.sql: |-\n    SELECT\n      a\n    FROM\n      t\n  query: select b from u\n");
+}
+
+#[test]
+fn test_embedded_rust_single_line_query() {
+    cmd()
+        .args(["--embedded", "rust", "--style", "streamline"])
+        .write_stdin("    let row = sqlx::query!(\"select id from users where id = $1\", id);\n")
+        .assert()
+        .success()
+        .stdout(
+            "    let row = sqlx::query!(\"\n        SELECT\n          id\n        FROM\n          users\n        \
+             WHERE\n          id = $1\n    \", id);\n",
+        );
+}
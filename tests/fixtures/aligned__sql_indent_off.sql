@@ -0,0 +1,5 @@
+select a,
+-- sql-indent: off
+  b   +   c as x,
+-- sql-indent: on
+  d from t
@@ -0,0 +1,8 @@
+DELIMITER $$
+create procedure p()
+begin
+  select 1;
+end$$
+select a, b from t where a = 1$$
+DELIMITER ;
+select 2;
\ No newline at end of file
@@ -0,0 +1,7 @@
+select a,b from t;
+-- sql-indent: off
+select   a,
+         b
+from     t;
+-- sql-indent: on
+select c from u
@@ -0,0 +1,8 @@
+select region, q1, q2
+from sales
+pivot (sum(amount) for quarter in ('Q1' as q1, 'Q2' as q2)) as p
+order by region;
+
+select region, quarter, amount
+from totals unpivot (amount for quarter in (q1, q2))
+where amount > 0;
\ No newline at end of file